@@ -103,17 +103,102 @@ fn get_ring_speed(ring: u32, level: u32, seed: u32) -> f32 {
         * select(1.0,-1.0,ring % 2 == 0);
 }
 
+const ARC_SAFE: u32 = 0u;
+const ARC_HAZARD: u32 = 1u;
+
+fn get_hazard_chance(ring: u32) -> f32 {
+    return clamp((f32(ring) - 20.0) * 0.015, 0.0, 0.4);
+}
+
 fn get_ring_color(ring: u32, level: u32, seed: u32) -> u32 {
-    return u32(floor(hash_noise(ring + 2048, level, seed) * 1.0 - 0.0000001));
+    if level != 0u && !is_boss_ring(ring) && hash_noise(ring + 2048u, level, seed) < get_hazard_chance(ring) {
+        return ARC_HAZARD;
+    }
+    return ARC_SAFE;
+}
+
+const COLOR_COUNT: u32 = 3u;
+
+fn get_arc_color(ring: u32, level: u32, seed: u32) -> u32 {
+    return min(u32(hash_noise(ring + 4096u, level, seed) * f32(COLOR_COUNT)), COLOR_COUNT - 1u);
+}
+
+// Okabe-Ito sky blue, bluish green and reddish purple
+fn color_rgb(idx: u32) -> vec3<f32> {
+    switch idx {
+        case 1u: { return vec3(0.0, 0.62, 0.45); }
+        case 2u: { return vec3(0.8, 0.47, 0.65); }
+        default: { return vec3(0.34, 0.71, 0.91); }
+    }
+}
+
+// Symbol per color, so colors can be told apart without seeing them. uv is in ring widths.
+fn color_pattern(idx: u32, uv: vec2<f32>) -> f32 {
+    switch idx {
+        case 1u: { return select(0.3, 1.0, fract(uv.x * 2.0) < 0.5); } // Stripes
+        case 2u: { return select(0.3, 1.0, length(fract(uv * 2.0) - 0.5) < 0.3); } // Dots
+        default: { return 1.0; } // Solid
+    }
+}
+
+const POWER_UP_FIRST_RING: u32 = 14u;
+const POWER_UP_CHANCE: f32 = 0.08;
+const POWER_UP_SLOW_TIME: u32 = 1u;
+const POWER_UP_SHIELD: u32 = 2u;
+const POWER_UP_PUSH: u32 = 3u;
+
+// 0 for none
+fn get_power_up(ring: u32, level: u32, seed: u32) -> u32 {
+    if ring < POWER_UP_FIRST_RING || is_boss_ring(ring) || get_ring_color(ring, level, seed) == ARC_HAZARD {
+        return 0u;
+    }
+    let noise = hash_noise(ring + 8192u, level, seed);
+    if noise >= POWER_UP_CHANCE {
+        return 0u;
+    }
+    return min(u32(noise / POWER_UP_CHANCE * 3.0), 2u) + 1u;
+}
+
+// uv is in ring widths from the middle of the pickup
+fn power_up_shape(power_up: u32, uv: vec2<f32>) -> f32 {
+    let d = length(uv);
+    switch power_up {
+        case 1u: { return f32(d < 0.3 && d > 0.18); } // Slow time: ring
+        case 2u: { return f32(abs(uv.x) + abs(uv.y) < 0.32); } // Shield: diamond
+        default: { return f32(d < 0.3 && abs(uv.y) > 0.06); } // Push: split disc
+    }
+}
+
+fn power_up_rgb(power_up: u32) -> vec3<f32> {
+    switch power_up {
+        case 1u: { return vec3(0.6, 0.8, 1.0); }
+        case 2u: { return vec3(0.4, 1.0, 0.5); }
+        default: { return vec3(1.0, 1.0, 1.0); }
+    }
 }
 
 fn get_max_arcs(ring: u32) -> u32 {
     return clamp(u32(max(i32(ring) - 16, 0)) / 4u, 2u, 6u);
 }
 
+// See GpuPlayer in lib.rs
+struct Player {
+    position: vec4<f32>,
+    tint: vec4<f32>,
+    ring: u32,
+    color_idx: u32,
+    dead: u32,
+    shield: u32,
+    move_cooldown: f32,
+    invulnerable: f32,
+    step_anim: f32,
+    step_dir: f32,
+}
+
 struct State {
     position: vec4<f32>,
     resolution: vec4<f32>,
+    viewport: vec4<f32>,
 
     scale_factor: f32,
     ring_thick: f32,
@@ -125,19 +210,193 @@ struct State {
     player_offset: f32,
     player_color_idx: u32,
 
-    step_anim: f32,
+    player_count: u32,
     move_cooldown: f32,
     player_sub_ring: u32,
     player_dead: u32,
 
     player_miss: u32,
-    spare0: u32,
-    spare1: u32,
-    spare2: u32,
+    paused: u32,
+    debug_draw: u32,
+    seed: u32,
+
+    wavefront: f32,
+    invulnerable: f32,
+    colors: u32,
+
+    highest_ring: u32,
+    shield: u32,
+    slow_time: f32,
+    boss_flash: f32,
+
+    level_first_ring: u32,
+    level_ring_count: u32,
+    hue: f32,
+    hue_speed: f32,
+
+    mechanics: u32,
+    arc_scale: f32,
+    mods: u32,
+    palette_arcs: vec4<f32>,
+    palette_next_arcs: vec4<f32>,
+    palette_rings: vec4<f32>,
+    players: array<Player, 2>,
+
+    // See boss.rs
+    boss_patterns: array<vec4<f32>, 3>,
+    boss_keys: array<vec4<f32>, 12>,
+    // See Level::gpu_window in level.rs
+    level_counts: array<vec4<u32>, 8>,
+    level_arcs: array<array<vec4<f32>, 6>, 32>,
 }
 
 @group(2) @binding(0) var<uniform> state: State;
 
+const STARTING_LEVEL: u32 = 10u;
+const BOSS_RING_INTERVAL: u32 = 15u;
+const BOSS_PATTERN_COUNT: u32 = 3u;
+const MAX_BOSS_KEYS: u32 = 4u;
+
+// Mechanics::bits in sim.rs
+const MECHANIC_HAZARDS: u32 = 1u;
+const MECHANIC_POWER_UPS: u32 = 2u;
+const MECHANIC_BOSSES: u32 = 4u;
+const MECHANIC_DIVE: u32 = 8u;
+
+fn has_mechanic(mechanic: u32) -> bool {
+    return (state.mechanics & mechanic) != 0u;
+}
+
+// Mods::bits in mods.rs
+const MOD_HIDDEN: u32 = 1u;
+const MOD_MIRROR: u32 = 2u;
+const MOD_NO_COOLDOWN_FLASH: u32 = 16u;
+// Rings out from the player's next ring over which Hidden fades arcs in.
+const HIDDEN_FADE_RINGS: f32 = 4.0;
+
+fn has_mod(m: u32) -> bool {
+    return (state.mods & m) != 0u;
+}
+
+// Whether a player jumps to ring next.
+fn is_next_ring(ring: u32) -> bool {
+    for (var i = 0u; i < state.player_count; i += 1u) {
+        if ring == state.players[i].ring + 1u {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Whether a player dives to ring next.
+fn is_dive_ring(ring: u32) -> bool {
+    for (var i = 0u; i < state.player_count; i += 1u) {
+        if ring + 1u == state.players[i].ring {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Rings from ring to the closest player's next ring.
+fn rings_from_next(ring: f32) -> f32 {
+    var rings = 1e9;
+    for (var i = 0u; i < state.player_count; i += 1u) {
+        rings = min(rings, abs(f32(state.players[i].ring + 1u) - ring));
+    }
+    return rings;
+}
+
+// Rings ring is past the next ring of the closest player inside it, 0 if there is none.
+fn rings_past_next(ring: f32) -> f32 {
+    var rings = 1e9;
+    for (var i = 0u; i < state.player_count; i += 1u) {
+        let past = ring - f32(state.players[i].ring + 1u);
+        if past >= 0.0 {
+            rings = min(rings, past);
+        }
+    }
+    return select(rings, 0.0, rings == 1e9);
+}
+
+fn is_boss_ring(ring: u32) -> bool {
+    return ring > STARTING_LEVEL && (ring - STARTING_LEVEL) % BOSS_RING_INTERVAL == 0u;
+}
+
+fn boss_pattern(ring: u32) -> u32 {
+    return ((ring - STARTING_LEVEL) / BOSS_RING_INTERVAL - 1u) % BOSS_PATTERN_COUNT;
+}
+
+// (at, speed, size, 0)
+fn boss_key(pattern: u32, j: u32) -> vec4<f32> {
+    return state.boss_keys[pattern * MAX_BOSS_KEYS + j];
+}
+
+fn boss_key_end(pattern: u32, count: u32, j: u32) -> f32 {
+    if j + 1u < count {
+        return boss_key(pattern, j + 1u).x;
+    }
+    return 1.0;
+}
+
+// Start and size of a boss ring arc, same as boss_arc in boss.rs
+fn boss_arc(ring: u32, sub_ring: u32, seed: u32, t: f32, direction: f32) -> vec2<f32> {
+    let pattern = boss_pattern(ring);
+    let info = state.boss_patterns[pattern];
+    let period = info.x;
+    let count = u32(info.y);
+    let loops = floor(t / period);
+    let x = t / period - loops;
+    var current = 0u;
+    var full = 0.0;
+    for (var j = 0u; j < count; j += 1u) {
+        let a = boss_key(pattern, j);
+        let b = boss_key(pattern, (j + 1u) % count);
+        full += (a.y + b.y) * 0.5 * (boss_key_end(pattern, count, j) - a.x);
+        if a.x <= x {
+            current = j;
+        }
+    }
+    var partial = 0.0;
+    for (var j = 0u; j < current; j += 1u) {
+        let a = boss_key(pattern, j);
+        let b = boss_key(pattern, (j + 1u) % count);
+        partial += (a.y + b.y) * 0.5 * (boss_key_end(pattern, count, j) - a.x);
+    }
+    let a = boss_key(pattern, current);
+    let b = boss_key(pattern, (current + 1u) % count);
+    let f = (x - a.x) / (boss_key_end(pattern, count, current) - a.x);
+    let speed_x = a.y + (b.y - a.y) * f;
+    partial += (a.y + speed_x) * 0.5 * (x - a.x);
+    let travel = pfract((loops * full + partial) * period);
+    let offset = f32(sub_ring) / info.z + hash_noise(ring + 16384u, 0u, seed);
+    return vec2(pfract(direction * travel + offset), a.z + (b.z - a.z) * f);
+}
+
+fn is_level_ring(ring: u32) -> bool {
+    return ring >= state.level_first_ring && ring < state.level_first_ring + state.level_ring_count;
+}
+
+fn level_arc_count(ring: u32) -> u32 {
+    let i = ring - state.level_first_ring;
+    return state.level_counts[i / 4u][i % 4u];
+}
+
+// (start, size, velocity, hazard | color << 1)
+fn level_arc(ring: u32, sub_ring: u32) -> vec4<f32> {
+    return state.level_arcs[ring - state.level_first_ring][sub_ring];
+}
+
+fn get_arc_count(ring: u32) -> u32 {
+    if is_level_ring(ring) {
+        return level_arc_count(ring);
+    }
+    if is_boss_ring(ring) && has_mechanic(MECHANIC_BOSSES) {
+        return u32(state.boss_patterns[boss_pattern(ring)].z);
+    }
+    return get_max_arcs(ring);
+}
+
 struct FullscreenVertexOutput {
     @builtin(position)
     position: vec4<f32>,
@@ -152,6 +411,36 @@ fn vertex(@builtin(vertex_index) vertex_index: u32) -> FullscreenVertexOutput {
     return FullscreenVertexOutput(clip_position, uv);
 }
 
+fn draw_player(background: vec3<f32>, coord: vec2<f32>, screen_mid: vec2<f32>, player: Player) -> vec3<f32> {
+    var color = background;
+    // At the middle of the screen when it's the one being followed.
+    let center = screen_mid + (state.position.xy - player.position.xy) * state.scale_factor;
+    let dist_from_center = distance(coord.xy, center);
+    let current_frag_is_near_player = u32(dist_from_center) < u32(state.ring_thick) >> 1;
+    if player.dead == 0 && current_frag_is_near_player {
+        let cooldown_anim = (sin(state.t * 200.0) * 0.7 + 0.5) * 0.6 + 0.1;
+        let cooling_down = player.move_cooldown < 1.0 && !has_mod(MOD_NO_COOLDOWN_FLASH);
+        let flashing = cooling_down || player.invulnerable > 0.0;
+        if player.shield != 0u && dist_from_center > state.ring_thick * 0.3 {
+            color = mix(color, power_up_rgb(POWER_UP_SHIELD), 0.6);
+        }
+        var player_alpha = select(1.0, cooldown_anim, flashing);
+        let player_cir = 1.0 - clamp(dist_from_center - state.ring_thick * 0.4, 0.0, 1.0);
+        var player_color = player.tint.rgb;
+        if state.colors != 0u {
+            let uv = (coord - center) / state.ring_thick;
+            let idx = player.color_idx;
+            player_color = color_rgb(idx) * color_pattern(idx, uv + 0.25);
+            if state.player_count > 1u && dist_from_center > state.ring_thick * 0.3 {
+                // Rim in the player's tint, since both orbs take the arc colors.
+                player_color = player.tint.rgb;
+            }
+        }
+        color = mix(color, player_color, player_cir * player_alpha * player.tint.a);
+    }
+    return color;
+}
+
 fn render(coord: vec2<f32>) -> vec3<f32> {
     var color = vec3(0.0);
     let pos = state.position.xy;
@@ -163,7 +452,7 @@ fn render(coord: vec2<f32>) -> vec3<f32> {
     let ffring = floor(fring);
     let ring = u32(ffring);
 
-    if fring < state.t * 7.0 {
+    if fring < state.wavefront {
         return vec3(1.0, 0.0, 0.0);
     }
 
@@ -173,45 +462,121 @@ fn render(coord: vec2<f32>) -> vec3<f32> {
         // Draw rings
         let m = ring % 2;
         if m == 0 {
-            color = mix(vec3(0.03, 0.001, 0.0), vec3(0.01, 0.0, 0.01), sin(state.t * 40.0 + ffring * 0.2));
+            color = mix(vec3(0.03, 0.001, 0.0), vec3(0.01, 0.0, 0.01), sin(state.t * 40.0 + ffring * 0.2)) * state.palette_rings.rgb;
         }
         if m == 1 {
             //color = mix(vec3(0.02, 0.05, 0.05), vec3(0.0, 0.0, 0.05), cos(state.t * 20.0 + ffring * 0.2));
             let v = sin(state.t * 40.) * 0.5 + 0.5;
             let v2 = cos(state.t * 40.) * 0.5 + 0.5;
-            color = vec3(0.05 * v, 0.0, 0.01 * v2);
+            color = vec3(0.05 * v, 0.0, 0.01 * v2) * state.palette_rings.rgb;
         }
     }
 
+    // Hazards are colored after the hue shift so they always look the same.
+    var hazard = vec4(0.0);
+    var over_safe_arc = false;
+    // Arc colors and pickups also skip the hue shift.
+    var arc_color = vec4(0.0);
+    var pickup = vec4(0.0);
+    let background = color;
     {
         // Draw arcs
-        for (var sub_ring = 0u; sub_ring <  get_max_arcs(ring); sub_ring += 1u) {
-            let ring_speed = get_ring_speed(ring, sub_ring, 0u);
-            let arc_size = get_arc_size(ring, sub_ring, 0u);
-            let ring_start = pfract(state.t * (ring_speed * f32(ring + 1)));
+        let from_level = is_level_ring(ring);
+        let boss = is_boss_ring(ring) && has_mechanic(MECHANIC_BOSSES) && !from_level;
+        // Mirror turns every arc the other way, same as Sim::arc
+        let direction = select(1.0, -1.0, has_mod(MOD_MIRROR));
+        for (var sub_ring = 0u; sub_ring <  get_arc_count(ring); sub_ring += 1u) {
+            let ring_speed = direction * get_ring_speed(ring, sub_ring, state.seed);
+            var arc_size = get_arc_size(ring, sub_ring, state.seed);
+            var ring_start = pfract(state.t * (ring_speed * f32(ring + 1)));
+            var kind = get_ring_color(ring, sub_ring, state.seed);
+            var color_idx = get_arc_color(ring, sub_ring, state.seed);
+            var power_up = get_power_up(ring, sub_ring, state.seed);
+            if !has_mechanic(MECHANIC_POWER_UPS) {
+                power_up = 0u;
+            }
+            if boss {
+                let arc = boss_arc(ring, sub_ring, state.seed, state.t, direction);
+                ring_start = arc.x;
+                arc_size = arc.y;
+            }
+            if from_level {
+                let arc = level_arc(ring, sub_ring);
+                ring_start = pfract(arc.x + direction * state.t * arc.z);
+                arc_size = arc.y;
+                let flags = u32(arc.w);
+                kind = flags & 1u;
+                color_idx = flags >> 1u;
+                power_up = 0u;
+            }
+            if !has_mechanic(MECHANIC_HAZARDS) {
+                kind = ARC_SAFE;
+            }
+            // Adaptive difficulty and mods, same as Sim::arc
+            arc_size *= state.arc_scale;
             let start = pfract(theta - ring_start);
             if start < arc_size {
-                let v = (1.0 - pow(abs(f32(state.player_ring + 1) - f32(ffring)), 0.2) * 0.6);
-                color = vec3(0.4 * v, 0.0, 0.3 * v + 0.03);
-                if ring == state.player_ring + 1 {
-                    color = vec3(1.0, 0.3, 0.0);
+                let v = (1.0 - pow(rings_from_next(ffring), 0.2) * 0.6);
+                color = state.palette_arcs.rgb * v + vec3(0.0, 0.0, 0.03);
+                if boss {
+                    color = vec3(0.9, 0.8, 0.6) * (v * 0.5 + 0.5);
+                }
+                if is_next_ring(ring) {
+                    color = state.palette_next_arcs.rgb;
+                }
+                if is_dive_ring(ring) && has_mechanic(MECHANIC_DIVE) {
+                    // Dive target
+                    color = vec3(0.15, 0.3, 0.7);
+                }
+                if kind != ARC_HAZARD {
+                    // Where arcs overlap the safe one wins, same as on the CPU.
+                    over_safe_arc = true;
+                    hazard = vec4(0.0);
+                    if power_up != 0u && ring > state.highest_ring {
+                        let uv = vec2((start - arc_size * 0.5) * TAU * fring, fract(fring) - 0.5);
+                        let shape = power_up_shape(power_up, uv);
+                        pickup = vec4(power_up_rgb(power_up), shape);
+                    }
+                    if state.colors != 0u {
+                        let near = is_next_ring(ring) || is_dive_ring(ring);
+                        let uv = vec2(start * TAU * fring, fract(fring));
+                        let rgb = color_rgb(color_idx) * color_pattern(color_idx, uv) * select(0.35, 1.0, near);
+                        arc_color = vec4(rgb, 1.0);
+                    }
+                } else if !over_safe_arc {
+                    let near = is_next_ring(ring) || is_dive_ring(ring);
+                    let stripe = select(0.15, 1.0, fract(start * f32(ring) * 4.0) < 0.5);
+                    hazard = vec4(vec3(1.0, 0.85, 0.0) * stripe * select(0.4, 1.0, near), 1.0);
                 }
             }
         }
     }
 
+    if has_mod(MOD_HIDDEN) {
+        let fade = clamp(rings_past_next(ffring) / HIDDEN_FADE_RINGS, 0.0, 1.0);
+        color = mix(background, color, fade);
+        arc_color.a *= fade;
+        hazard.a *= fade;
+        pickup.a *= fade;
+    }
+
     color = RGBtoHSV(color);
-    color.x = fract(color.x + (max(state.t - 10.0, 0.0) * 0.05));
+    color.x = fract(color.x + state.hue + (max(state.t - 10.0, 0.0) * state.hue_speed));
     color = HSVtoRGB(color);
+    color = mix(color, arc_color.rgb, arc_color.a);
+    color = mix(color, hazard.rgb, hazard.a);
+    color = mix(color, pickup.rgb, pickup.a);
+
+    if state.boss_flash > 0.0 {
+        // Shockwave out from the player after passing a boss ring
+        let wave = f32(state.player_ring) + (1.0 - state.boss_flash) * 30.0;
+        let d = abs(fring - wave);
+        color += vec3(1.0, 0.85, 0.5) * state.boss_flash * (1.0 - clamp(d, 0.0, 1.0));
+        color += vec3(0.15) * state.boss_flash * state.boss_flash;
+    }
 
-    let dist_from_center = distance(coord.xy, screen_mid);
-    let current_frag_is_near_player = u32(dist_from_center) < u32(state.ring_thick) >> 1;
-    if state.player_dead == 0 && current_frag_is_near_player {
-        // Draw player
-        let cooldown_anim = (sin(state.t * 200.0) * 0.7 + 0.5) * 0.6 + 0.1;
-        var player_alpha = select(1.0, cooldown_anim, state.move_cooldown < 1.0);
-        let player_cir = 1.0 - clamp(dist_from_center - state.ring_thick * 0.4, 0.0, 1.0);
-        color = mix(color, vec3(1.0), player_cir * player_alpha);
+    for (var i = 0u; i < state.player_count; i += 1u) {
+        color = draw_player(color, coord, screen_mid, state.players[i]);
     }
 
     return color;
@@ -219,7 +584,8 @@ fn render(coord: vec2<f32>) -> vec3<f32> {
 
 @fragment
 fn fragment(input: FullscreenVertexOutput) -> @location(0) vec4<f32> {
-    let coord = input.position.xy;
+    // Relative to the view, which only covers half of the window in split-screen.
+    let coord = input.position.xy - state.viewport.xy;
     var color = vec3(0.0);
     color += render(coord);
     // MSAA RGSS
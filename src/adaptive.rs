@@ -0,0 +1,143 @@
+//! Adaptive difficulty: with [`RunConfig::adaptive`] on, the run eases off when the player
+//! struggles and tightens up when they cruise. It's part of the [`Sim`] state, so replays play
+//! back the same adjustments.
+
+use crate::sim::{JumpResult, RunConfig};
+
+/// Bounds of [`Adaptive::speed`], a multiplier on the game speed.
+pub const SPEED_RANGE: (f32, f32) = (0.7, 1.3);
+/// Bounds of [`Adaptive::arc_size`], a multiplier on every arc's size.
+pub const ARC_SIZE_RANGE: (f32, f32) = (0.8, 1.4);
+/// Bounds of [`Adaptive::cooldown`], a multiplier on how long a miss locks the player out.
+pub const COOLDOWN_RANGE: (f32, f32) = (0.6, 1.4);
+/// How far the multipliers move toward their targets per second, as a fraction of their range.
+const RATE: f32 = 0.04;
+/// Weight of each jump in the running averages.
+const SMOOTHING: f32 = 0.12;
+/// Miss rate that counts as struggling the most.
+const MAX_MISS_RATE: f32 = 0.4;
+/// Rings ahead of the wavefront that count as fully comfortable.
+const COMFORTABLE_MARGIN: f32 = 6.0;
+/// Seconds of run time between entries in [`Adaptive::log`].
+const LOG_INTERVAL: f32 = 5.0;
+
+/// The controller's multipliers at some point of a run, and the skill estimate behind them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjustment {
+    /// [`crate::sim::Sim::run_time`] when it was made.
+    pub time: f32,
+    pub skill: f32,
+    pub speed: f32,
+    pub arc_size: f32,
+    pub cooldown: f32,
+}
+
+impl Adjustment {
+    pub fn describe(&self) -> String {
+        format!(
+            "{:.1}s skill {:.2}: speed {:.2} arc size {:.2} cooldown {:.2}",
+            self.time, self.skill, self.speed, self.arc_size, self.cooldown
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Adaptive {
+    enabled: bool,
+    /// Running average of misses per jump.
+    pub miss_rate: f32,
+    /// Running average of how close to an arc's middle jumps land, 1 being dead center.
+    pub precision: f32,
+    /// Running average of rings between the player and the wavefront at each jump.
+    pub margin: f32,
+    pub speed: f32,
+    pub arc_size: f32,
+    pub cooldown: f32,
+    /// Every adjustment made so far, one per [`LOG_INTERVAL`] while anything changed.
+    pub log: Vec<Adjustment>,
+    next_log: f32,
+}
+
+impl Adaptive {
+    pub fn new(config: &RunConfig) -> Self {
+        Self {
+            enabled: config.adaptive,
+            miss_rate: MAX_MISS_RATE * 0.5,
+            precision: 0.5,
+            margin: COMFORTABLE_MARGIN * 0.5,
+            speed: 1.0,
+            arc_size: 1.0,
+            cooldown: 1.0,
+            log: Vec::new(),
+            next_log: LOG_INTERVAL,
+        }
+    }
+
+    /// From 0 for a player who keeps missing near the wavefront, to 1 for one landing every
+    /// jump dead center far ahead of it.
+    pub fn skill(&self) -> f32 {
+        let hits = 1.0 - (self.miss_rate / MAX_MISS_RATE).min(1.0);
+        let margin = (self.margin / COMFORTABLE_MARGIN).clamp(0.0, 1.0);
+        hits * 0.5 + self.precision * 0.2 + margin * 0.3
+    }
+
+    /// Feeds a jump into the running averages. `precision` is only known for landings.
+    pub fn observe(&mut self, result: JumpResult, precision: Option<f32>, margin: f32) {
+        if !self.enabled {
+            return;
+        }
+        let missed = matches!(result, JumpResult::Missed | JumpResult::Hazard { .. });
+        let average = |average: &mut f32, value: f32| *average += (value - *average) * SMOOTHING;
+        average(&mut self.miss_rate, missed as u32 as f32);
+        if let Some(precision) = precision {
+            average(&mut self.precision, precision);
+        }
+        average(&mut self.margin, margin);
+    }
+
+    /// Moves the multipliers toward what the current skill estimate calls for, and logs them
+    /// to [`Adaptive::log`] every [`LOG_INTERVAL`].
+    pub fn update(&mut self, dt: f32, run_time: f32) {
+        if !self.enabled {
+            return;
+        }
+        // -1 eases off as far as the bounds allow, 1 makes things as hard as they allow.
+        let push = ((self.skill() - 0.5) * 3.0).clamp(-1.0, 1.0);
+        let approach = |value: &mut f32, (low, high): (f32, f32), harder_is_higher: bool| {
+            let push = if harder_is_higher { push } else { -push };
+            let target = if push > 0.0 {
+                1.0 + (high - 1.0) * push
+            } else {
+                1.0 + (1.0 - low) * push
+            };
+            let step = (high - low) * RATE * dt;
+            *value = (*value + (target - *value).clamp(-step, step)).clamp(low, high);
+        };
+        approach(&mut self.speed, SPEED_RANGE, true);
+        approach(&mut self.arc_size, ARC_SIZE_RANGE, false);
+        approach(&mut self.cooldown, COOLDOWN_RANGE, true);
+
+        if run_time < self.next_log {
+            return;
+        }
+        self.next_log += LOG_INTERVAL;
+        let adjustment = Adjustment {
+            time: run_time,
+            skill: self.skill(),
+            speed: self.speed,
+            arc_size: self.arc_size,
+            cooldown: self.cooldown,
+        };
+        let unchanged = self.log.last().map_or(
+            adjustment.speed == 1.0 && adjustment.arc_size == 1.0 && adjustment.cooldown == 1.0,
+            |last| {
+                last.speed == adjustment.speed
+                    && last.arc_size == adjustment.arc_size
+                    && last.cooldown == adjustment.cooldown
+            },
+        );
+        if !unchanged {
+            self.log.push(adjustment);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! A bot that plays runs on its own, from sloppy to sharp. Used to exercise the game without a
+//! player, such as checking how [`crate::adaptive`] reacts to different players.
+
+use crate::sampling::{hash_noise, pfract};
+use crate::sim::{Sim, SimInput, ARC_SAFE};
+
+/// Fraction of an arc's size at each end the bot won't aim for.
+const EDGE: f32 = 0.15;
+/// Reaction time of the least skilled bot, in frames.
+const MAX_DELAY: f32 = 14.0;
+/// Chance per frame of the least skilled bot jumping without looking.
+const MAX_IMPATIENCE: f32 = 0.012;
+
+#[derive(Clone, Debug)]
+pub struct Autoplay {
+    /// From 0, slow to react and jumpy, to 1, frame perfect.
+    pub skill: f32,
+    seed: u32,
+    frame: u32,
+    /// Frames left before a jump the bot has decided on.
+    planned: Option<u32>,
+}
+
+impl Autoplay {
+    /// Bots with the same skill and seed make the same choices.
+    pub fn new(skill: f32, seed: u32) -> Self {
+        Self {
+            skill: skill.clamp(0.0, 1.0),
+            seed,
+            frame: 0,
+            planned: None,
+        }
+    }
+
+    /// Input for the next [`Sim::step`].
+    pub fn input(&mut self, sim: &Sim) -> SimInput {
+        self.frame += 1;
+        let mut input = SimInput::default();
+        if sim.over() {
+            self.planned = None;
+            return input;
+        }
+        if let Some(frames) = self.planned {
+            self.planned = frames.checked_sub(1);
+            input.jump = frames == 0;
+            return input;
+        }
+        if sim.move_cooldown < 1.0 {
+            return input;
+        }
+        let clumsiness = 1.0 - self.skill;
+        if self.noise(0) < clumsiness * MAX_IMPATIENCE {
+            input.jump = true;
+            return input;
+        }
+
+        let next = sim.player_ring + 1;
+        let phase = sim.player_phase();
+        for sub_ring in 0..sim.arc_count(next) {
+            if sim.arc_kind(next, sub_ring) != ARC_SAFE {
+                continue;
+            }
+            let (start, size) = sim.arc(next, sub_ring);
+            let within = pfract(phase - start);
+            if within < size * EDGE || within > size * (1.0 - EDGE) {
+                continue;
+            }
+            if sim.config.colors && sim.arc_color(next, sub_ring) != sim.player_color {
+                input.change_color = true;
+                continue;
+            }
+            let delay = (self.noise(1) * clumsiness * MAX_DELAY) as u32;
+            match delay.checked_sub(1) {
+                None => input.jump = true,
+                Some(frames) => self.planned = Some(frames),
+            }
+            input.change_color = false;
+            break;
+        }
+        input
+    }
+
+    fn noise(&self, salt: u32) -> f32 {
+        hash_noise(self.frame, salt, self.seed)
+    }
+}
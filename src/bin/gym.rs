@@ -0,0 +1,8 @@
+//! Headless games for training scripts, driven over stdin and stdout. See [`lib_Sol::gym`] for
+//! the protocol.
+
+use std::io;
+
+fn main() -> io::Result<()> {
+    lib_Sol::gym::serve(io::stdin().lock(), io::BufWriter::new(io::stdout().lock()))
+}
@@ -0,0 +1,45 @@
+//! Relay for online races, see [`lib_Sol::race`].
+//!
+//! `relay [ADDRESS] [--loss CHANCE] [--delay MILLISECONDS]` listens on `ADDRESS`, by default
+//! `0.0.0.0:7878`, and prints the address it got. Loss and delay are for trying out bad
+//! connections.
+
+use std::fmt::Display;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use lib_Sol::race::Relay;
+
+const USAGE: &str = "usage: relay [ADDRESS] [--loss CHANCE] [--delay MILLISECONDS]";
+
+fn main() {
+    let mut addr = String::from("0.0.0.0:7878");
+    let (mut loss, mut delay) = (0.0, 0);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--loss" => loss = parse(args.next(), "--loss takes a chance from 0 to 1"),
+            "--delay" => delay = parse(args.next(), "--delay takes milliseconds"),
+            _ if arg.starts_with('-') => fail(2, format!("unknown option {arg}\n\n{USAGE}")),
+            _ => addr = arg,
+        }
+    }
+    let mut relay =
+        Relay::bind(&addr).unwrap_or_else(|e| fail(1, format!("can't listen on {addr}: {e}")));
+    relay.loss = loss;
+    relay.delay = Duration::from_millis(delay);
+    println!("relay listening on {}", relay.local_addr().unwrap());
+    relay.run(&AtomicBool::new(false));
+}
+
+fn parse<T: std::str::FromStr>(value: Option<String>, usage: &str) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| fail(2, format!("{usage}\n\n{USAGE}")))
+}
+
+/// Reports why the relay can't run and quits with `status`: 2 for bad arguments, 1 otherwise.
+fn fail(status: i32, message: impl Display) -> ! {
+    eprintln!("{message}");
+    std::process::exit(status)
+}
@@ -0,0 +1,155 @@
+//! Boss rings: every [`BOSS_RING_INTERVAL`] levels a ring follows a hand-made pattern instead of
+//! `hash_noise`. The patterns are plain data, uploaded to the shader every frame so both sides
+//! move the arcs the same way.
+
+use bevy::math::{vec4, Vec4};
+
+use crate::sampling::{hash_noise, pfract};
+use crate::sim::STARTING_LEVEL;
+
+pub const BOSS_RING_INTERVAL: u32 = 15;
+/// Upper bound on [`BossPattern::keys`], fixed by the shader's uniform array.
+pub const MAX_BOSS_KEYS: usize = 4;
+
+/// Values at one point of a pattern's period. Interpolated linearly, wrapping around.
+#[derive(Clone, Copy, Debug)]
+pub struct BossKey {
+    /// Fraction of the period. The first key of a pattern is at 0.
+    pub at: f32,
+    /// Turns per unit of `t`. Negative runs backwards.
+    pub speed: f32,
+    /// Arc size in turns.
+    pub size: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BossPattern {
+    pub name: &'static str,
+    /// Length of one loop of the keys, in units of `t`.
+    pub period: f32,
+    /// Arcs spread evenly around the ring.
+    pub arcs: u32,
+    pub keys: &'static [BossKey],
+}
+
+const fn key(at: f32, speed: f32, size: f32) -> BossKey {
+    BossKey { at, speed, size }
+}
+
+pub const BOSS_PATTERNS: [BossPattern; 3] = [
+    BossPattern {
+        name: "SURGE",
+        period: 0.6,
+        arcs: 2,
+        keys: &[
+            key(0.0, 0.8, 0.08),
+            key(0.4, 3.0, 0.05),
+            key(0.6, 3.0, 0.05),
+        ],
+    },
+    BossPattern {
+        name: "REVERSAL",
+        period: 0.8,
+        arcs: 3,
+        keys: &[
+            key(0.0, 1.5, 0.06),
+            key(0.45, 1.5, 0.06),
+            key(0.5, -1.5, 0.06),
+            key(0.95, -1.5, 0.06),
+        ],
+    },
+    BossPattern {
+        name: "BREATH",
+        period: 0.5,
+        arcs: 2,
+        keys: &[key(0.0, 1.0, 0.12), key(0.5, 1.0, 0.025)],
+    },
+];
+
+impl BossPattern {
+    /// Key the segment containing `x` starts at, and where it ends. The last segment wraps
+    /// around to the first key.
+    fn segment(&self, x: f32) -> (usize, f32) {
+        let keys = self.keys;
+        let j = keys.iter().rposition(|k| k.at <= x).unwrap_or(0);
+        let end = keys.get(j + 1).map_or(1.0, |k| k.at);
+        (j, end)
+    }
+
+    fn lerp(&self, j: usize, end: f32, x: f32) -> (f32, f32) {
+        let a = self.keys[j];
+        let b = self.keys[(j + 1) % self.keys.len()];
+        let f = (x - a.at) / (end - a.at);
+        (
+            a.speed + (b.speed - a.speed) * f,
+            a.size + (b.size - a.size) * f,
+        )
+    }
+
+    pub fn size(&self, t: f32) -> f32 {
+        let x = pfract(t / self.period);
+        let (j, end) = self.segment(x);
+        self.lerp(j, end, x).1
+    }
+
+    /// Turns the arcs have moved by `t`, the integral of the speed curve.
+    pub fn travel(&self, t: f32) -> f32 {
+        let loops = (t / self.period).floor();
+        let x = t / self.period - loops;
+        let (current, end) = self.segment(x);
+        let mut full = 0.0;
+        let mut partial = 0.0;
+        for (j, a) in self.keys.iter().enumerate() {
+            let b = self.keys[(j + 1) % self.keys.len()];
+            let end = self.keys.get(j + 1).map_or(1.0, |k| k.at);
+            let area = (a.speed + b.speed) * 0.5 * (end - a.at);
+            full += area;
+            if j < current {
+                partial += area;
+            }
+        }
+        let a = self.keys[current];
+        let (speed_x, _) = self.lerp(current, end, x);
+        partial += (a.speed + speed_x) * 0.5 * (x - a.at);
+        pfract((loops * full + partial) * self.period)
+    }
+}
+
+pub fn is_boss_ring(ring: u32) -> bool {
+    ring > STARTING_LEVEL && (ring - STARTING_LEVEL) % BOSS_RING_INTERVAL == 0
+}
+
+/// Index into [`BOSS_PATTERNS`] for a boss ring.
+pub fn boss_pattern(ring: u32) -> usize {
+    ((ring - STARTING_LEVEL) / BOSS_RING_INTERVAL - 1) as usize % BOSS_PATTERNS.len()
+}
+
+/// Start and size of a boss ring arc at `t`, in turns, travelling forwards with a `direction`
+/// of 1 and backwards with -1.
+pub fn boss_arc(ring: u32, sub_ring: u32, seed: u32, t: f32, direction: f32) -> (f32, f32) {
+    let pattern = &BOSS_PATTERNS[boss_pattern(ring)];
+    let offset = sub_ring as f32 / pattern.arcs as f32 + hash_noise(ring + 16384, 0, seed);
+    (
+        pfract(direction * pattern.travel(t) + offset),
+        pattern.size(t),
+    )
+}
+
+/// [`BOSS_PATTERNS`] packed for the shader: `(period, key count, arcs, 0)` per pattern and
+/// `(at, speed, size, 0)` per key, [`MAX_BOSS_KEYS`] slots per pattern.
+pub fn gpu_patterns() -> ([Vec4; 3], [Vec4; 3 * MAX_BOSS_KEYS]) {
+    let mut patterns = [Vec4::ZERO; 3];
+    let mut keys = [Vec4::ZERO; 3 * MAX_BOSS_KEYS];
+    for (i, pattern) in BOSS_PATTERNS.iter().enumerate() {
+        patterns[i] = vec4(
+            pattern.period,
+            pattern.keys.len() as f32,
+            pattern.arcs as f32,
+            0.0,
+        );
+        for (j, key) in pattern.keys.iter().enumerate() {
+            keys[i * MAX_BOSS_KEYS + j] = vec4(key.at, key.speed, key.size, 0.0);
+        }
+    }
+    (patterns, keys)
+}
@@ -0,0 +1,456 @@
+//! Campaign mode: chapters of stages from `sol.campaign.ron`, each chapter with its own intro,
+//! palette, music and mechanics. Picking a chapter loads its music and levels through
+//! [`GameLoading::ChapterLoading`], the same way the game's own assets are loaded at startup.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::math::vec4;
+use bevy::prelude::*;
+use bevy_asset_loader::asset_collection::AssetCollection;
+use bevy_asset_loader::dynamic_asset::DynamicAssets;
+use bevy_asset_loader::standard_dynamic_asset::StandardDynamicAsset;
+use bevy_kira_audio::prelude::AudioSource;
+use bevy_kira_audio::{Audio, AudioControl, AudioInstance, AudioTween};
+use serde::Deserialize;
+
+use crate::cli::StandIns;
+use crate::level::{Level, LoadedLevel};
+use crate::progress::Progress;
+use crate::sampling::gain_from_db;
+use crate::settings::Settings;
+use crate::sim::{Difficulty, GameMode, Goal, Mechanics, Run, RunConfig};
+use crate::{AudioAssets, DataMaterial, GameLoading, GameMaterial, MusicHandle};
+
+/// Seconds the chapter intro stays up at the start of a stage.
+const INTRO_TIME: f32 = 6.0;
+
+#[derive(Asset, TypePath, Clone, Debug, Deserialize)]
+pub struct Campaign {
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Chapter {
+    pub name: String,
+    /// Shown at the start of every stage.
+    pub intro: String,
+    #[serde(default)]
+    pub palette: Palette,
+    /// Audio asset path, looped while the chapter is played.
+    pub music: String,
+    #[serde(default)]
+    pub mechanics: Mechanics,
+    /// See [`RunConfig::colors`].
+    #[serde(default)]
+    pub colors: bool,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// Played in order. Clearing the last one unlocks the next chapter.
+    pub stages: Vec<Stage>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Stage {
+    /// Level asset path. A level without rings plays the procedural rings.
+    pub level: String,
+    #[serde(default)]
+    pub seed: u32,
+    pub goal: Goal,
+}
+
+/// Colors fed to `game_shader.wgsl`. The default is the game's look outside of the campaign.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub arcs: [f32; 3],
+    /// Arcs of the ring the player jumps to.
+    pub next_arcs: [f32; 3],
+    /// Multiplier on the background rings.
+    pub rings: [f32; 3],
+    /// Added to the hue of everything but hazards, arc colors and pickups, in turns.
+    pub hue: f32,
+    /// Hue turns per unit of `t`.
+    pub hue_speed: f32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            arcs: [0.4, 0.0, 0.3],
+            next_arcs: [1.0, 0.3, 0.0],
+            rings: [1.0, 1.0, 1.0],
+            hue: 0.0,
+            hue_speed: 0.05,
+        }
+    }
+}
+
+impl Campaign {
+    /// The first chapter is always open, the others once the one before is cleared.
+    pub fn unlocked(&self, chapter: usize, progress: &Progress) -> bool {
+        match chapter.checked_sub(1) {
+            None => !self.chapters.is_empty(),
+            Some(previous) => {
+                self.chapters.get(chapter).is_some()
+                    && cleared(&self.chapters[previous], progress)
+                        == self.chapters[previous].stages.len()
+            }
+        }
+    }
+}
+
+/// Stages of `chapter` cleared so far.
+pub fn cleared(chapter: &Chapter, progress: &Progress) -> usize {
+    progress.campaign.get(&chapter.name).copied().unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub enum CampaignError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for CampaignError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CampaignError::Io(e) => write!(f, "failed to read campaign: {e}"),
+            CampaignError::Parse(e) => write!(f, "failed to parse campaign: {e}"),
+            CampaignError::Invalid(e) => write!(f, "invalid campaign: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CampaignError {}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    type Asset = Campaign;
+    type Settings = ();
+    type Error = CampaignError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Campaign, CampaignError> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(CampaignError::Io)?;
+        let campaign: Campaign = ron::de::from_bytes(&bytes).map_err(CampaignError::Parse)?;
+        if let Some(chapter) = campaign.chapters.iter().find(|c| c.stages.is_empty()) {
+            return Err(CampaignError::Invalid(format!(
+                "chapter {} has no stages",
+                chapter.name
+            )));
+        }
+        Ok(campaign)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign.ron"]
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct CampaignAssets {
+    #[asset(path = "sol.campaign.ron")]
+    pub campaign: Handle<Campaign>,
+}
+
+/// Assets of the picked chapter, registered as dynamic assets by [`pick_chapter`].
+#[derive(AssetCollection, Resource)]
+pub struct ChapterAssets {
+    #[asset(key = "chapter.music")]
+    pub music: Handle<AudioSource>,
+    #[asset(key = "chapter.levels", collection(typed))]
+    pub levels: Vec<Handle<Level>>,
+}
+
+#[derive(Resource, Default)]
+pub struct CurrentChapter {
+    /// Index into [`Campaign::chapters`], while in campaign mode.
+    index: Option<usize>,
+    chapter: Option<Chapter>,
+    /// One per stage, once loaded.
+    levels: Vec<Arc<Level>>,
+    /// Stage the next run is played on.
+    stage: usize,
+    /// Stage the last won run cleared.
+    cleared: usize,
+    /// Why the chapter couldn't start, shown instead of its intro.
+    error: Option<String>,
+}
+
+impl CurrentChapter {
+    /// A run of the current stage, once the chapter has loaded.
+    pub fn new_run(&self, mut config: RunConfig) -> Option<Run> {
+        let chapter = self.chapter.as_ref()?;
+        let stage = chapter.stages.get(self.stage)?;
+        let level = self.levels.get(self.stage)?;
+        config.difficulty = chapter.difficulty;
+        config.colors = chapter.colors;
+        config.mechanics = chapter.mechanics;
+        config.seed = stage.seed;
+        config.goal = Some(stage.goal);
+        Some(Run::with_level(config, Some(level.clone())))
+    }
+}
+
+/// Builds the run started from the results or pause screen.
+#[derive(SystemParam)]
+pub struct NextRun<'w> {
+    settings: Res<'w, Settings>,
+    loaded_level: Res<'w, LoadedLevel>,
+    chapter: Res<'w, CurrentChapter>,
+}
+
+impl NextRun<'_> {
+    pub fn run(&self) -> Run {
+        let config = self.settings.run_config();
+        if config.mode == GameMode::Campaign {
+            if let Some(run) = self.chapter.new_run(config) {
+                return run;
+            }
+        }
+        self.loaded_level.new_run(config)
+    }
+}
+
+/// Loops `source` in place of the current music.
+fn play_music(
+    audio: &Audio,
+    audio_instances: &mut Assets<AudioInstance>,
+    music: &mut MusicHandle,
+    source: Handle<AudioSource>,
+) {
+    if let Some(instance) = audio_instances.get_mut(&music.0) {
+        instance.stop(AudioTween::linear(Duration::from_secs_f32(0.5)));
+    }
+    music.0 = audio
+        .play(source)
+        .looped()
+        .with_volume(gain_from_db(-8.0) as f64)
+        .handle();
+}
+
+/// Starts loading the chapter picked in the settings, or goes back to the main theme when
+/// leaving campaign mode.
+pub fn pick_chapter(
+    settings: Res<Settings>,
+    campaign_assets: Res<CampaignAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    progress: Res<Progress>,
+    mut current: ResMut<CurrentChapter>,
+    mut dynamic_assets: ResMut<DynamicAssets>,
+    mut next_state: ResMut<NextState<GameLoading>>,
+    audio: (
+        Res<Audio>,
+        Res<AudioAssets>,
+        ResMut<Assets<AudioInstance>>,
+        Option<ResMut<MusicHandle>>,
+    ),
+) {
+    let picked = (settings.mode == GameMode::Campaign).then_some(settings.chapter);
+    if picked == current.index {
+        return;
+    }
+    let Some(campaign) = campaigns.get(&campaign_assets.campaign) else {
+        return;
+    };
+    current.index = picked;
+    current.chapter = None;
+    current.levels.clear();
+    current.error = None;
+    match picked.and_then(|i| campaign.chapters.get(i)) {
+        Some(chapter) => {
+            dynamic_assets.register_asset(
+                "chapter.music",
+                Box::new(StandardDynamicAsset::File {
+                    path: chapter.music.clone(),
+                }),
+            );
+            dynamic_assets.register_asset(
+                "chapter.levels",
+                Box::new(StandardDynamicAsset::Files {
+                    paths: chapter.stages.iter().map(|s| s.level.clone()).collect(),
+                }),
+            );
+            current.stage = cleared(chapter, &progress).min(chapter.stages.len() - 1);
+            current.chapter = Some(chapter.clone());
+            next_state.set(GameLoading::ChapterLoading);
+        }
+        None => {
+            let (audio, audio_assets, mut audio_instances, music) = audio;
+            if let Some(mut music) = music {
+                let theme = audio_assets.theme1.clone();
+                play_music(&audio, &mut audio_instances, &mut music, theme);
+            }
+        }
+    }
+}
+
+/// Once a chapter's assets have loaded, switches to its music and starts its current stage.
+/// Refuses to start it if any of its levels didn't load, since the stages would be off by one.
+pub fn start_chapter(
+    chapter_assets: Option<Res<ChapterAssets>>,
+    levels: Res<Assets<Level>>,
+    settings: Res<Settings>,
+    mut current: ResMut<CurrentChapter>,
+    mut run: ResMut<Run>,
+    audio: Res<Audio>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+    music: Option<ResMut<MusicHandle>>,
+) {
+    let Some(chapter_assets) = chapter_assets else {
+        return;
+    };
+    if current.chapter.is_none() || !current.levels.is_empty() {
+        return;
+    }
+    let loaded: Option<Vec<_>> = chapter_assets
+        .levels
+        .iter()
+        .map(|handle| levels.get(handle).map(|level| Arc::new(level.clone())))
+        .collect();
+    let Some(loaded) = loaded.filter(|loaded| !loaded.is_empty()) else {
+        let chapter = current.chapter.as_ref().map_or("", |c| &c.name);
+        warn!("levels of chapter {chapter} failed to load");
+        current.error = Some(format!("{}\nLEVELS FAILED TO LOAD", chapter.to_uppercase()));
+        return;
+    };
+    current.levels = loaded;
+    if let Some(mut music) = music {
+        let source = chapter_assets.music.clone();
+        play_music(&audio, &mut audio_instances, &mut music, source);
+    }
+    if let Some(new_run) = current.new_run(settings.run_config()) {
+        *run = new_run;
+    }
+}
+
+/// Saves the stage as cleared when it's won, and moves on to the next one.
+pub fn record_stage(
+    run: Res<Run>,
+    stand_ins: StandIns,
+    mut current: ResMut<CurrentChapter>,
+    mut progress: ResMut<Progress>,
+    mut recorded: Local<bool>,
+) {
+    if !run.sim.won() || run.sim.config.mode != GameMode::Campaign || stand_ins.play(&run) {
+        *recorded = false;
+        return;
+    }
+    if *recorded {
+        return;
+    }
+    *recorded = true;
+    let stage = current.stage;
+    let Some(chapter) = &current.chapter else {
+        return;
+    };
+    let stages = chapter.stages.len();
+    let cleared = progress.campaign.entry(chapter.name.clone()).or_default();
+    *cleared = (*cleared).max(stage + 1);
+    progress.save();
+    current.cleared = stage;
+    current.stage = (stage + 1).min(stages - 1);
+}
+
+#[derive(Component)]
+pub struct ChapterText;
+
+pub fn setup_chapter_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(12.0),
+            left: Val::Px(0.0),
+            right: Val::Px(0.0),
+            ..default()
+        }),
+        ChapterText,
+    ));
+}
+
+/// Feeds the chapter's palette to the shader, and shows its intro and the stage results.
+pub fn update_chapter(
+    run: Res<Run>,
+    current: Res<CurrentChapter>,
+    progress: Res<Progress>,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    game_material: Res<GameMaterial>,
+    mut text: Query<&mut Text, With<ChapterText>>,
+) {
+    let Some(gpu) = materials.get_mut(&game_material.0) else {
+        return;
+    };
+    let mut text = text.single_mut();
+    text.sections[0].value = current.error.clone().unwrap_or_default();
+    let sim = &run.sim;
+    let chapter = current
+        .chapter
+        .as_ref()
+        .filter(|_| sim.config.mode == GameMode::Campaign);
+
+    let palette = chapter.map_or_else(Palette::default, |chapter| chapter.palette);
+    let state = &mut gpu.state;
+    state.palette_arcs = Vec3::from(palette.arcs).extend(1.0);
+    state.palette_next_arcs = Vec3::from(palette.next_arcs).extend(1.0);
+    state.palette_rings = vec4(palette.rings[0], palette.rings[1], palette.rings[2], 1.0);
+    state.hue = palette.hue;
+    state.hue_speed = palette.hue_speed;
+
+    let (Some(chapter), Some(index)) = (chapter, current.index) else {
+        return;
+    };
+    let Some(goal) = sim.config.goal else {
+        return;
+    };
+    let stage = if sim.won() {
+        current.cleared
+    } else {
+        current.stage
+    };
+    let value = &mut text.sections[0].value;
+    *value = format!(
+        "CHAPTER {}  {}\nSTAGE {}/{}\n",
+        index + 1,
+        chapter.name.to_uppercase(),
+        stage + 1,
+        chapter.stages.len()
+    );
+    if sim.won() {
+        if cleared(chapter, &progress) == chapter.stages.len() && stage + 1 == chapter.stages.len()
+        {
+            value.push_str("\nCHAPTER COMPLETE\nTHE NEXT CHAPTER IS OPEN IN THE MENU\n");
+        } else {
+            value.push_str("\nSTAGE CLEAR\n");
+        }
+    } else if sim.dead {
+        value.push_str(&format!("\n{}\n", goal.describe()));
+    } else if sim.run_time < INTRO_TIME {
+        value.push_str(&format!("\n{}\n\n{}\n", chapter.intro, goal.describe()));
+    } else {
+        value.clear();
+    }
+}
@@ -0,0 +1,98 @@
+//! What the command-line options change in the running game, see [`crate::AppOptions`].
+
+use std::path::PathBuf;
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::autoplay::Autoplay;
+use crate::replay::{Replay, ReplayFrame};
+use crate::results_text;
+use crate::sim::{JumpResult, Run, SimInput};
+
+/// A replay played back in place of the player's input.
+#[derive(Resource)]
+pub(crate) struct Playback {
+    replay: Replay,
+    /// Frames stepped so far.
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, next: 0 }
+    }
+
+    /// Whether `run` is the one played back, rather than one the player started after it.
+    pub fn plays(&self, run: &Run) -> bool {
+        run.sim.config == self.replay.config && run.replay.frames.len() == self.next
+    }
+
+    /// Whether every frame of the replay has been played.
+    pub fn done(&self) -> bool {
+        self.next >= self.replay.frames.len()
+    }
+
+    /// Steps `run` on the next frame of the replay, or on `dt` with no input once they've run
+    /// out.
+    pub fn step(&mut self, run: &mut Run, dt: f32) -> [Option<JumpResult>; 2] {
+        let frame = self.replay.frames.get(self.next).copied();
+        let ReplayFrame { dt, input } = frame.unwrap_or(ReplayFrame {
+            dt,
+            input: SimInput::default(),
+        });
+        self.next += 1;
+        run.step_players(dt, [input, SimInput::default()])
+    }
+}
+
+/// Plays every run in place of the first player.
+#[derive(Resource)]
+pub(crate) struct Autoplayer(pub Autoplay);
+
+/// Replays and the bot, which play in place of the player. Their runs don't go on the
+/// leaderboard or count towards progress.
+#[derive(SystemParam)]
+pub(crate) struct StandIns<'w> {
+    playback: Option<Res<'w, Playback>>,
+    autoplayer: Option<Res<'w, Autoplayer>>,
+}
+
+impl StandIns<'_> {
+    pub fn play(&self, run: &Run) -> bool {
+        self.autoplayer.is_some() || self.playback.as_ref().is_some_and(|p| p.plays(run))
+    }
+}
+
+/// Where to save the replay of each run once it's over. Later runs overwrite it.
+#[derive(Resource)]
+pub(crate) struct Recording(pub PathBuf);
+
+pub(crate) fn record_run(run: Res<Run>, recording: Res<Recording>, mut saved: Local<bool>) {
+    if !run.sim.over() {
+        *saved = false;
+        return;
+    }
+    if *saved {
+        return;
+    }
+    *saved = true;
+    let path = &recording.0;
+    match std::fs::write(path, run.replay.to_bytes()) {
+        Ok(()) => info!("saved replay to {}", path.display()),
+        Err(e) => error!("can't save replay to {}: {e}", path.display()),
+    }
+}
+
+/// Prints the results and quits once the run is over, or the replay has run out.
+pub(crate) fn finish_headless_run(
+    run: Res<Run>,
+    playback: Option<Res<Playback>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if run.sim.over() || playback.is_some_and(|playback| playback.done()) {
+        println!("{}", results_text(&run));
+        exit.send(AppExit::Success);
+    }
+}
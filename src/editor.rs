@@ -0,0 +1,401 @@
+//! Level editor for [`crate::level`] files. F3 opens it on the level picked in the settings, or
+//! on a new one, and play-tests from the selected ring.
+
+use std::f32::consts::*;
+use std::sync::Arc;
+
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+use crate::level::{Level, LevelArc, LevelRing, LoadedLevel, MAX_LEVEL_ARCS};
+use crate::sampling::pfract;
+use crate::settings::Settings;
+use crate::sim::{GameMode, Run, COLOR_COUNT, STARTING_LEVEL};
+use crate::{outlined, p_line, storage, DataMaterial, GameMaterial, GpuState};
+
+/// Where levels that weren't picked from the settings are saved.
+const CUSTOM_LEVEL: &str = "saves://levels/custom.level.ron";
+/// How close to the end of an arc, in logical pixels, a drag resizes it instead of moving it.
+const HANDLE_RADIUS: f32 = 10.0;
+const MIN_ARC_SIZE: f32 = 0.01;
+const MAX_ARC_SIZE: f32 = 0.95;
+/// Speed change per line of the scroll wheel.
+const SPEED_STEP: f32 = 0.1;
+const SELECTED: Color = Color::linear_rgb(1.0, 0.8, 0.0);
+
+/// Asset path the editor saves a level opened from `path` to. Levels that ship with the game
+/// are saved as a copy in the save directory, never over the game's assets.
+fn save_path(path: &str) -> String {
+    match path.split_once("://") {
+        Some(_) => String::from(path),
+        None => format!("{}://{path}", storage::ASSET_SOURCE),
+    }
+}
+
+/// The save file a [`save_path`] is loaded from.
+fn save_name(path: &str) -> &str {
+    path.split_once("://").map_or(path, |(_, name)| name)
+}
+
+enum Drag {
+    /// `grab` is where on the arc it was picked up, in turns from its start.
+    Move {
+        grab: f32,
+    },
+    Resize,
+}
+
+#[derive(Resource, Default)]
+pub struct Editor {
+    pub open: bool,
+    /// Playing the edited level from [`Editor::open`], F3 goes back to editing.
+    testing: bool,
+    /// Kept when the editor is closed, so play-tests and unsaved edits aren't lost.
+    level: Option<Level>,
+    path: String,
+    ring: u32,
+    arc: Option<usize>,
+    drag: Option<Drag>,
+    status: String,
+}
+
+#[derive(Component)]
+pub struct EditorText;
+
+pub fn setup_editor_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        EditorText,
+    ));
+}
+
+fn new_arc(start: f32) -> LevelArc {
+    LevelArc {
+        start,
+        size: 0.15,
+        speed: 1.0,
+        reverse: false,
+        hazard: false,
+        color: 0,
+    }
+}
+
+fn new_level() -> Level {
+    Level {
+        name: String::from("Custom"),
+        rings: vec![LevelRing {
+            arcs: vec![new_arc(0.0)],
+        }],
+    }
+}
+
+/// Ring and phase under the cursor, the inverse of the mapping in `game_shader.wgsl`.
+fn pick(cursor: Vec2, window: &Window, state: &GpuState) -> (u32, f32) {
+    let p = cursor - vec2(window.width(), window.height()) * 0.5 - state.position.xy();
+    let ring = (p.length() / state.ring_thick + 1.0) as u32;
+    (ring, pfract((p.y.atan2(p.x) + FRAC_PI_2) * FRAC_1_PI * 0.5))
+}
+
+/// Gizmo space position of `phase` on the middle of `ring`.
+fn ring_point(state: &GpuState, ring: u32, phase: f32) -> Vec2 {
+    let n = vec2((phase * TAU).sin(), (phase * TAU).cos());
+    let center = state.position.xy() * vec2(1.0, -1.0);
+    n * state.ring_thick * (ring as f32 - 0.5) + center
+}
+
+/// A run with the player just inside `ring`, so it's drawn as the next ring.
+fn preview(level: &Level, ring: u32, settings: &Settings) -> Run {
+    let mut config = settings.run_config();
+    config.mode = GameMode::Practice;
+    config.start_level = ring.saturating_sub(1).max(STARTING_LEVEL) - STARTING_LEVEL;
+    Run::with_level(config, Some(Arc::new(level.clone())))
+}
+
+/// F3 opens and closes the editor. While it's open the game stays paused on a preview of the
+/// level, rebuilt every frame from the edits.
+pub fn edit_level(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    window: Query<&Window>,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    game_material: Res<GameMaterial>,
+    mut editor: ResMut<Editor>,
+    mut run: ResMut<Run>,
+    mut settings: ResMut<Settings>,
+    loaded: Res<LoadedLevel>,
+    mut levels: ResMut<Assets<Level>>,
+    mut gizmos: Gizmos,
+    mut text: Query<&mut Text, With<EditorText>>,
+) {
+    let Some(gpu) = materials.get_mut(&game_material.0) else {
+        return;
+    };
+    let state = &mut gpu.state;
+    let mut text = text.single_mut();
+    text.sections[0].value = String::new();
+    let editor = &mut *editor;
+
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        editor.testing = false;
+        if editor.open {
+            editor.open = false;
+            *run = loaded.new_run(settings.run_config());
+            state.paused = 0;
+            return;
+        }
+        let path = save_path(settings.level.as_deref().unwrap_or(CUSTOM_LEVEL));
+        if editor.level.is_none() || editor.path != path {
+            editor.path = path;
+            editor.level = Some(match &loaded.level {
+                Some(level) if settings.level.is_some() => (**level).clone(),
+                _ => new_level(),
+            });
+            editor.ring = STARTING_LEVEL;
+            editor.status = String::new();
+        }
+        editor.open = true;
+        editor.arc = None;
+        editor.drag = None;
+    }
+    if !editor.open {
+        wheel.clear();
+        // Restarting from the results screen ends the play-test.
+        editor.testing &= run.sim.config.mode == GameMode::Practice && run.sim.level.is_some();
+        if editor.testing {
+            text.sections[0].value = String::from("PLAY-TEST\nF3 BACK TO THE EDITOR");
+        }
+        return;
+    }
+    let Some(level) = &mut editor.level else {
+        return;
+    };
+    state.paused = u32::MAX;
+
+    let ctrl = keyboard_input.pressed(KeyCode::ControlLeft)
+        || keyboard_input.pressed(KeyCode::ControlRight);
+    let last_ring = STARTING_LEVEL + level.rings.len() as u32;
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) || keyboard_input.just_pressed(KeyCode::KeyW) {
+        editor.ring = (editor.ring + 1).min(last_ring);
+        editor.arc = None;
+    }
+    let pressed_s = keyboard_input.just_pressed(KeyCode::KeyS) && !ctrl;
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) || pressed_s {
+        editor.ring = editor.ring.saturating_sub(1).max(STARTING_LEVEL);
+        editor.arc = None;
+    }
+
+    let window = window.single();
+    let cursor = window
+        .cursor_position()
+        .map(|cursor| pick(cursor, window, state));
+    let index = (editor.ring - STARTING_LEVEL) as usize;
+    if let (true, Some((ring, phase))) = (mouse.just_pressed(MouseButton::Left), cursor) {
+        if ring != editor.ring {
+            // The view moves to the new ring, so don't start a drag in it yet.
+            if (STARTING_LEVEL..=last_ring).contains(&ring) {
+                editor.ring = ring;
+                editor.arc = None;
+            }
+        } else if let Some(level_ring) = level.rings.get(index) {
+            let radius = state.ring_thick * (ring as f32 - 0.5);
+            let handle = HANDLE_RADIUS / (TAU * radius);
+            editor.arc = None;
+            editor.drag = None;
+            for (i, arc) in level_ring.arcs.iter().enumerate() {
+                let from_end = pfract(phase - arc.start - arc.size + 0.5) - 0.5;
+                let within = pfract(phase - arc.start);
+                if from_end.abs() < handle {
+                    editor.drag = Some(Drag::Resize);
+                } else if within < arc.size {
+                    editor.drag = Some(Drag::Move { grab: within });
+                } else {
+                    continue;
+                }
+                editor.arc = Some(i);
+                break;
+            }
+        }
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        editor.drag = None;
+    }
+
+    let index = (editor.ring - STARTING_LEVEL) as usize;
+    let selected = editor
+        .arc
+        .and_then(|i| level.rings.get_mut(index)?.arcs.get_mut(i));
+    if let Some(arc) = selected {
+        if let (Some(drag), Some((_, phase))) = (&editor.drag, cursor) {
+            match drag {
+                Drag::Move { grab } => arc.start = pfract(phase - grab),
+                Drag::Resize => {
+                    arc.size = pfract(phase - arc.start).clamp(MIN_ARC_SIZE, MAX_ARC_SIZE)
+                }
+            }
+        }
+        let lines: f32 = wheel
+            .read()
+            .map(|event| match event.unit {
+                MouseScrollUnit::Line => event.y,
+                MouseScrollUnit::Pixel => event.y / 20.0,
+            })
+            .sum();
+        if lines != 0.0 {
+            let velocity = arc.velocity() + lines * SPEED_STEP;
+            arc.speed = (velocity.abs() * 100.0).round() / 100.0;
+            arc.reverse = velocity < 0.0;
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyR) {
+            arc.reverse = !arc.reverse;
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyH) {
+            arc.hazard = !arc.hazard;
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyC) {
+            arc.color = (arc.color + 1) % COLOR_COUNT;
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyX)
+            || keyboard_input.just_pressed(KeyCode::Delete)
+        {
+            let arcs = &mut level.rings[index].arcs;
+            if arcs.len() > 1 {
+                arcs.remove(editor.arc.unwrap());
+                editor.arc = None;
+            } else if index + 1 == level.rings.len() && index > 0 {
+                level.rings.pop();
+                editor.ring -= 1;
+                editor.arc = None;
+            } else {
+                editor.status = String::from("RINGS NEED AT LEAST ONE ARC");
+            }
+        }
+    } else {
+        wheel.clear();
+    }
+
+    let index = (editor.ring - STARTING_LEVEL) as usize;
+    if keyboard_input.just_pressed(KeyCode::KeyA) {
+        let start = match cursor {
+            Some((ring, phase)) if ring == editor.ring => phase,
+            _ => 0.0,
+        };
+        if index == level.rings.len() {
+            level.rings.push(LevelRing { arcs: Vec::new() });
+        }
+        let arcs = &mut level.rings[index].arcs;
+        if arcs.len() < MAX_LEVEL_ARCS {
+            arcs.push(new_arc(start));
+            editor.arc = Some(arcs.len() - 1);
+        } else {
+            editor.status = format!("RINGS CAN HAVE AT MOST {MAX_LEVEL_ARCS} ARCS");
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        match level.validate() {
+            Ok(()) => {
+                let mut config = settings.run_config();
+                config.mode = GameMode::Practice;
+                config.start_level = editor.ring.min(last_ring - 1) - STARTING_LEVEL;
+                *run = Run::with_level(config, Some(Arc::new(level.clone())));
+                state.paused = 0;
+                editor.open = false;
+                editor.testing = true;
+                return;
+            }
+            Err(e) => editor.status = e.to_string().to_uppercase(),
+        }
+    }
+
+    if ctrl && keyboard_input.just_pressed(KeyCode::KeyS) {
+        let saved = level.validate().map_err(|e| e.to_string()).and_then(|()| {
+            let ron = ron::ser::to_string_pretty(level, Default::default()).unwrap();
+            storage::try_save_bytes(save_name(&editor.path), ron.as_bytes())
+                .map_err(|e| e.to_string())
+        });
+        editor.status = match &saved {
+            Ok(()) => format!("SAVED {}", editor.path),
+            Err(e) => format!("NOT SAVED: {e}").to_uppercase(),
+        };
+        if saved.is_ok() {
+            if settings.level.as_deref() == Some(editor.path.as_str()) {
+                // The asset server doesn't watch for changes, so update the loaded copy too.
+                if let Some(asset) = loaded.handle().and_then(|handle| levels.get_mut(handle)) {
+                    *asset = level.clone();
+                }
+            } else {
+                settings.level = Some(editor.path.clone());
+                settings.save();
+            }
+        }
+    }
+
+    *run = preview(level, editor.ring, &settings);
+
+    let thick = state.ring_thick;
+    if let Some(level_ring) = level.rings.get(index) {
+        for (i, arc) in level_ring.arcs.iter().enumerate() {
+            for phase in [arc.start, arc.start + arc.size] {
+                let p = ring_point(state, editor.ring, phase);
+                p_line(&mut gizmos, p, phase, thick * 0.5, thick * 0.5);
+            }
+            if editor.arc == Some(i) {
+                let steps = (arc.size * 64.0).ceil().max(2.0) as usize;
+                for step in 0..steps {
+                    let a = arc.start + arc.size * step as f32 / steps as f32;
+                    let b = arc.start + arc.size * (step + 1) as f32 / steps as f32;
+                    let (a, b) = (
+                        ring_point(state, editor.ring, a),
+                        ring_point(state, editor.ring, b),
+                    );
+                    outlined(&mut gizmos, a, b, SELECTED);
+                }
+            }
+        }
+    }
+
+    let value = &mut text.sections[0].value;
+    *value = format!(
+        "LEVEL EDITOR  {}\nRING {}{}\n",
+        editor.path,
+        editor.ring - STARTING_LEVEL,
+        if index == level.rings.len() {
+            "  (NEW, A ADDS AN ARC)"
+        } else {
+            ""
+        }
+    );
+    let selected = editor.arc.and_then(|i| level.rings.get(index)?.arcs.get(i));
+    if let Some(arc) = selected {
+        value.push_str(&format!(
+            "ARC {}  START {:.3}  SIZE {:.3}  SPEED {:.2}  COLOR {}{}\n",
+            editor.arc.unwrap(),
+            arc.start,
+            arc.size,
+            arc.velocity(),
+            arc.color,
+            if arc.hazard { "  HAZARD" } else { "" }
+        ));
+    }
+    value.push_str(
+        "\nCLICK SELECTS, DRAG MOVES, DRAGGING THE END RESIZES, SCROLL SETS THE SPEED\n\
+         W S RING  A ADD  X REMOVE  R REVERSE  H HAZARD  C COLOR\n\
+         T PLAY-TEST FROM THIS RING  CTRL+S SAVE  F3 CLOSE\n",
+    );
+    value.push_str(&editor.status);
+}
@@ -0,0 +1,450 @@
+//! The game as a reinforcement learning environment, with [`Env::reset`] and [`Env::step`] in the
+//! style of Gym. Observations come from the same [`Sim`] math `draw` uses: the player's
+//! [`Sim::player_phase`], the [`Sim::arc`] windows of the rings around them and how far the
+//! wavefront is.
+//!
+//! [`BatchEnv`] runs many games at once, and [`serve`] drives one over JSON lines, so training
+//! scripts in other languages can use it through the `gym` binary:
+//!
+//! ```text
+//! > {"cmd": "spec"}
+//! < {"features": ["angle", ...], "actions": ["wait", "jump", "dive", "change_color"]}
+//! > {"cmd": "reset", "seeds": [1, 2], "config": {"difficulty": "Hard"}, "frames_per_step": 4}
+//! < {"observations": [[...], [...]]}
+//! > {"cmd": "step", "actions": [1, 0]}
+//! < {"observations": [[...], [...]], "rewards": [1.0, 0.0], "dones": [false, false]}
+//! ```
+//!
+//! Observations are flattened to the order of `features`. Games that end are reset to a new seed
+//! right away, so the observation that comes with `done` is the first one of the next game.
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::sampling::pfract;
+use crate::sim::{JumpResult, RunConfig, Sim, SimInput, ARC_HAZARD};
+
+/// Seconds simulated per frame, the game's usual 60 fps.
+pub const FRAME_DT: f32 = 1.0 / 60.0;
+/// Rings observed, relative to the player's: the next three outward, and the one a dive lands on.
+pub const OBSERVED_RINGS: [i32; 4] = [1, 2, 3, -1];
+/// Arc windows observed per ring. Rings never have more arcs.
+pub const MAX_WINDOWS: usize = crate::level::MAX_LEVEL_ARCS;
+/// Steps before a game is cut off, for players that would never die.
+pub const DEFAULT_MAX_STEPS: u32 = 60 * 60 * 10;
+/// Reward for each ring moved outward. Dives cost as much.
+pub const RING_REWARD: f32 = 1.0;
+pub const MISS_PENALTY: f32 = 0.25;
+pub const DEATH_PENALTY: f32 = 5.0;
+/// Batches at least this big are stepped on several threads.
+const PARALLEL_BATCH: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Wait,
+    Jump,
+    Dive,
+    ChangeColor,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [
+        Action::Wait,
+        Action::Jump,
+        Action::Dive,
+        Action::ChangeColor,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Wait => "wait",
+            Action::Jump => "jump",
+            Action::Dive => "dive",
+            Action::ChangeColor => "change_color",
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    pub fn input(self) -> SimInput {
+        SimInput {
+            jump: self == Action::Jump,
+            dive: self == Action::Dive,
+            change_color: self == Action::ChangeColor,
+            ..Default::default()
+        }
+    }
+}
+
+/// An arc of an observed ring. Padding windows have a size of 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArcWindow {
+    /// Turns from the player's angle to the middle of the arc, from -0.5 to 0.5. Jumping lands
+    /// on the arc while this is within half its size.
+    pub offset: f32,
+    /// In turns.
+    pub size: f32,
+    pub hazard: bool,
+    /// See [`RunConfig::colors`].
+    pub color: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    /// The player's position on their ring, in turns.
+    pub angle: f32,
+    pub level: i32,
+    /// Rings between the wavefront and the player's next ring. The player dies once it's negative.
+    pub wavefront_distance: f32,
+    /// Reaches 1 once the player can move again after a miss.
+    pub cooldown: f32,
+    pub color: u32,
+    pub shield: bool,
+    /// Seconds of slow time left.
+    pub slow_time: f32,
+    /// Windows of each ring in [`OBSERVED_RINGS`], in the order of [`Sim::arc`]'s sub rings.
+    pub rings: [[ArcWindow; MAX_WINDOWS]; OBSERVED_RINGS.len()],
+}
+
+impl Observation {
+    pub fn new(sim: &Sim) -> Self {
+        let angle = sim.player_phase();
+        let rings = OBSERVED_RINGS.map(|relative| {
+            let mut windows = [ArcWindow::default(); MAX_WINDOWS];
+            let Some(ring) = sim.player_ring.checked_add_signed(relative) else {
+                return windows;
+            };
+            for (sub_ring, window) in (0..sim.arc_count(ring)).zip(&mut windows) {
+                let (start, size) = sim.arc(ring, sub_ring);
+                *window = ArcWindow {
+                    offset: pfract(start + size * 0.5 - angle + 0.5) - 0.5,
+                    size,
+                    hazard: sim.arc_kind(ring, sub_ring) == ARC_HAZARD,
+                    color: sim.arc_color(ring, sub_ring),
+                };
+            }
+            windows
+        });
+        Self {
+            angle,
+            level: sim.level(),
+            wavefront_distance: (sim.player_ring + 1) as f32 - sim.wavefront,
+            cooldown: sim.move_cooldown,
+            color: sim.player_color,
+            shield: sim.shield,
+            slow_time: sim.slow_time,
+            rings,
+        }
+    }
+
+    /// Names of the values in [`Observation::features`].
+    pub fn feature_names() -> Vec<String> {
+        let mut names: Vec<String> = [
+            "angle",
+            "level",
+            "wavefront_distance",
+            "cooldown",
+            "color",
+            "shield",
+            "slow_time",
+        ]
+        .map(String::from)
+        .into();
+        for relative in OBSERVED_RINGS {
+            for window in 0..MAX_WINDOWS {
+                for value in ["offset", "size", "hazard", "color"] {
+                    names.push(format!("ring{relative:+}_arc{window}_{value}"));
+                }
+            }
+        }
+        names
+    }
+
+    /// Flattened for training, in the order of [`Observation::feature_names`].
+    pub fn features(&self) -> Vec<f32> {
+        let mut features = vec![
+            self.angle,
+            self.level as f32,
+            self.wavefront_distance,
+            self.cooldown,
+            self.color as f32,
+            self.shield as u32 as f32,
+            self.slow_time,
+        ];
+        for window in self.rings.iter().flatten() {
+            features.extend([
+                window.offset,
+                window.size,
+                window.hazard as u32 as f32,
+                window.color as f32,
+            ]);
+        }
+        features
+    }
+}
+
+/// One headless game.
+#[derive(Clone, Debug)]
+pub struct Env {
+    pub config: RunConfig,
+    /// Frames simulated per [`Env::step`]. The action is taken on the first.
+    pub frames_per_step: u32,
+    pub max_steps: u32,
+    sim: Sim,
+    steps: u32,
+}
+
+impl Env {
+    pub fn new(config: RunConfig) -> Self {
+        Self {
+            config,
+            frames_per_step: 1,
+            max_steps: DEFAULT_MAX_STEPS,
+            sim: Sim::new(config),
+            steps: 0,
+        }
+    }
+
+    pub fn sim(&self) -> &Sim {
+        &self.sim
+    }
+
+    /// Starts a new game on `seed`, keeping the rest of the config.
+    pub fn reset(&mut self, seed: u32) -> Observation {
+        self.config.seed = seed;
+        self.sim = Sim::new(self.config);
+        self.steps = 0;
+        Observation::new(&self.sim)
+    }
+
+    /// Returns the observation after `action`, its reward and whether the game is done.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        let ring = self.sim.player_ring;
+        let mut reward = 0.0;
+        let mut input = action.input();
+        for _ in 0..self.frames_per_step.max(1) {
+            let was_dead = self.sim.dead;
+            if let Some(JumpResult::Missed | JumpResult::Hazard { .. }) =
+                self.sim.step(FRAME_DT, input)
+            {
+                reward -= MISS_PENALTY;
+            }
+            if self.sim.dead && !was_dead {
+                reward -= DEATH_PENALTY;
+            }
+            input = SimInput::default();
+            if self.sim.over() {
+                break;
+            }
+        }
+        reward += (self.sim.player_ring as f32 - ring as f32) * RING_REWARD;
+        self.steps += 1;
+        let done = self.sim.over() || self.steps >= self.max_steps;
+        (Observation::new(&self.sim), reward, done)
+    }
+}
+
+/// Result of one [`Env`] in a [`BatchEnv::step`].
+#[derive(Clone, Debug)]
+pub struct BatchStep {
+    pub observation: Observation,
+    pub reward: f32,
+    pub done: bool,
+}
+
+/// Many games stepped together. Each game that ends is reset to the seed after the last one
+/// handed out.
+#[derive(Clone, Debug)]
+pub struct BatchEnv {
+    pub envs: Vec<Env>,
+    next_seed: u32,
+}
+
+impl BatchEnv {
+    pub fn new(config: RunConfig, count: usize) -> Self {
+        Self {
+            envs: vec![Env::new(config); count],
+            next_seed: 0,
+        }
+    }
+
+    /// Resets every game, one per seed.
+    pub fn reset(&mut self, seeds: &[u32]) -> Vec<Observation> {
+        let template = self.envs.first().cloned();
+        let template = template.unwrap_or_else(|| Env::new(RunConfig::default()));
+        self.envs = vec![template; seeds.len()];
+        self.next_seed = seeds.iter().max().map_or(0, |seed| seed.wrapping_add(1));
+        self.envs
+            .iter_mut()
+            .zip(seeds)
+            .map(|(env, &seed)| env.reset(seed))
+            .collect()
+    }
+
+    /// Steps each game with its action. A finished game's observation is the first of its next
+    /// game.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<BatchStep> {
+        assert_eq!(actions.len(), self.envs.len(), "one action per game");
+        let step = |(env, &action): (&mut Env, &Action)| {
+            let (observation, reward, done) = env.step(action);
+            BatchStep {
+                observation,
+                reward,
+                done,
+            }
+        };
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let mut steps: Vec<BatchStep> = if self.envs.len() < PARALLEL_BATCH || threads == 1 {
+            self.envs.iter_mut().zip(actions).map(step).collect()
+        } else {
+            let chunk = self.envs.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = self
+                    .envs
+                    .chunks_mut(chunk)
+                    .zip(actions.chunks(chunk))
+                    .map(|(envs, actions)| {
+                        scope.spawn(move || {
+                            envs.iter_mut().zip(actions).map(step).collect::<Vec<_>>()
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().unwrap())
+                    .collect()
+            })
+        };
+        for (env, step) in self.envs.iter_mut().zip(&mut steps) {
+            if step.done {
+                step.observation = env.reset(self.next_seed);
+                self.next_seed = self.next_seed.wrapping_add(1);
+            }
+        }
+        steps
+    }
+}
+
+fn default_frames_per_step() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Spec,
+    Reset {
+        seeds: Vec<u32>,
+        #[serde(default)]
+        config: RunConfig,
+        #[serde(default = "default_frames_per_step")]
+        frames_per_step: u32,
+        #[serde(default)]
+        max_steps: Option<u32>,
+    },
+    Step {
+        actions: Vec<usize>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Response {
+    Spec {
+        features: Vec<String>,
+        actions: Vec<&'static str>,
+    },
+    Reset {
+        observations: Vec<Vec<f32>>,
+    },
+    Step {
+        observations: Vec<Vec<f32>>,
+        rewards: Vec<f32>,
+        dones: Vec<bool>,
+    },
+    Error {
+        error: String,
+    },
+}
+
+/// Answers JSON line requests from `input` on `output` until `input` ends. See the module docs.
+pub fn serve(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut batch: Option<BatchEnv> = None;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => handle(&mut batch, request),
+            Err(e) => Response::Error {
+                error: format!("bad request: {e}"),
+            },
+        };
+        serde_json::to_writer(&mut output, &response)?;
+        writeln!(output)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn handle(batch: &mut Option<BatchEnv>, request: Request) -> Response {
+    match request {
+        Request::Spec => Response::Spec {
+            features: Observation::feature_names(),
+            actions: Action::ALL.map(Action::name).into(),
+        },
+        Request::Reset {
+            seeds,
+            config,
+            frames_per_step,
+            max_steps,
+        } => {
+            let mut env = Env::new(config);
+            env.frames_per_step = frames_per_step;
+            env.max_steps = max_steps.unwrap_or(DEFAULT_MAX_STEPS);
+            let batch = batch.insert(BatchEnv {
+                envs: vec![env],
+                next_seed: 0,
+            });
+            let observations = batch.reset(&seeds);
+            Response::Reset {
+                observations: observations.iter().map(Observation::features).collect(),
+            }
+        }
+        Request::Step { actions } => {
+            let Some(batch) = batch else {
+                return Response::Error {
+                    error: "reset before stepping".into(),
+                };
+            };
+            if actions.len() != batch.envs.len() {
+                return Response::Error {
+                    error: format!("{} actions for {} games", actions.len(), batch.envs.len()),
+                };
+            }
+            let Some(actions) = actions
+                .into_iter()
+                .map(Action::from_index)
+                .collect::<Option<Vec<_>>>()
+            else {
+                return Response::Error {
+                    error: format!("actions go from 0 to {}", Action::ALL.len() - 1),
+                };
+            };
+            let steps = batch.step(&actions);
+            Response::Step {
+                observations: steps
+                    .iter()
+                    .map(|step| step.observation.features())
+                    .collect(),
+                rewards: steps.iter().map(|step| step.reward).collect(),
+                dones: steps.iter().map(|step| step.done).collect(),
+            }
+        }
+    }
+}
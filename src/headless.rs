@@ -0,0 +1,192 @@
+//! The full game without a window, rendering or audio output, for tests and tools. Build it with
+//! [`crate::build_app`] and [`crate::AppOptions::headless`], or drive it a frame at a time with
+//! [`Headless`].
+//!
+//! Audio is stubbed: sounds are queued on the usual channel, but nothing plays them or loads the
+//! audio files, so campaign chapters, which need their music loaded, never start.
+
+use std::time::Duration;
+
+use bevy::gizmos::GizmoPlugin;
+use bevy::input::{InputPlugin, InputSystem};
+use bevy::prelude::*;
+use bevy::render::render_resource::Shader;
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use bevy_asset_loader::dynamic_asset::DynamicAssets;
+use bevy_kira_audio::{prelude::AudioSource, Audio, AudioInstance};
+
+use crate::campaign::CampaignAssets;
+use crate::leaderboard::LeaderboardText;
+use crate::sim::{Run, RunConfig};
+use crate::{build_app, AppOptions, AudioAssets, DataMaterial, GameMaterial, GameText, Muted};
+
+/// Seconds between headless frames, however long they take to run.
+pub const FRAME_DT: f32 = 1.0 / 60.0;
+
+/// Stands in for `DefaultPlugins` and the game's render and audio plugins.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            AssetPlugin::default(),
+            InputPlugin,
+            WindowPlugin {
+                // Never opened, but the game reads its size.
+                primary_window: Some(Window {
+                    title: String::from("Sol"),
+                    ..default()
+                }),
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            },
+        ))
+        // Gizmos load their shaders even though nothing draws them.
+        .init_asset::<Shader>()
+        .add_plugins(GizmoPlugin)
+        .init_asset::<Mesh>()
+        .init_asset::<DataMaterial>()
+        .init_resource::<Audio>()
+        .init_asset::<AudioSource>()
+        .init_asset::<AudioInstance>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            FRAME_DT,
+        )))
+        .init_resource::<DynamicAssets>()
+        .insert_resource(AudioAssets {
+            tone: default(),
+            miss_tone: default(),
+            close: default(),
+            theme1: default(),
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        app.init_resource::<ScreenshotManager>();
+        app.add_systems(PreStartup, load_campaign);
+    }
+}
+
+/// Loaded outside of a loading state, which would wait on the audio.
+fn load_campaign(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CampaignAssets {
+        campaign: asset_server.load("sol.campaign.ron"),
+    });
+}
+
+/// Keys [`Headless::press`] holds down for one frame.
+#[derive(Resource, Default)]
+struct SyntheticKeys {
+    queued: Vec<KeyCode>,
+    held: Vec<KeyCode>,
+}
+
+fn press_synthetic_keys(
+    mut synthetic: ResMut<SyntheticKeys>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    let synthetic = &mut *synthetic;
+    for key in synthetic.held.drain(..) {
+        keys.release(key);
+    }
+    for key in synthetic.queued.drain(..) {
+        keys.press(key);
+        synthetic.held.push(key);
+    }
+}
+
+/// A headless game, advanced one [`FRAME_DT`] frame at a time.
+pub struct Headless {
+    pub app: App,
+}
+
+impl Default for Headless {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Headless {
+    /// The game as it starts, with default settings, after its first frame.
+    pub fn new() -> Self {
+        let mut app = build_app(&AppOptions {
+            headless: true,
+            ..default()
+        });
+        app.init_resource::<SyntheticKeys>()
+            .add_systems(PreUpdate, press_synthetic_keys.after(InputSystem));
+        app.finish();
+        app.cleanup();
+        app.update();
+        Self { app }
+    }
+
+    /// Restarts on a run with `config`.
+    pub fn start(&mut self, config: RunConfig) {
+        *self.app.world_mut().resource_mut::<Run>() = Run::new(config);
+        let handle = self.app.world().resource::<GameMaterial>().0.clone();
+        let mut materials = self.app.world_mut().resource_mut::<Assets<DataMaterial>>();
+        materials.get_mut(&handle).unwrap().state = default();
+    }
+
+    /// Presses `key` on the next frame, and releases it on the one after.
+    pub fn press(&mut self, key: KeyCode) {
+        let mut synthetic = self.app.world_mut().resource_mut::<SyntheticKeys>();
+        synthetic.queued.push(key);
+    }
+
+    pub fn frame(&mut self) {
+        self.app.update();
+    }
+
+    pub fn frames(&mut self, count: u32) {
+        for _ in 0..count {
+            self.frame();
+        }
+    }
+
+    /// Presses `key` and runs the frame that sees it.
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.frame();
+    }
+
+    pub fn run(&self) -> &Run {
+        self.app.world().resource::<Run>()
+    }
+
+    /// Paused from the game's own pause, not a stopped clock.
+    pub fn paused(&self) -> bool {
+        let world = self.app.world();
+        let handle = &world.resource::<GameMaterial>().0;
+        let materials = world.resource::<Assets<DataMaterial>>();
+        materials.get(handle).unwrap().state.paused != 0
+    }
+
+    pub fn muted(&self) -> bool {
+        self.app.world().resource::<Muted>().0
+    }
+
+    /// The message in the middle of the screen, like the results.
+    pub fn text(&mut self) -> String {
+        self.text_with::<GameText>()
+    }
+
+    /// The high scores shown once a run is over, and the online ones below them.
+    pub fn leaderboard_text(&mut self) -> String {
+        self.text_with::<LeaderboardText>()
+    }
+
+    fn text_with<M: Component>(&mut self) -> String {
+        let mut query = self.app.world_mut().query_filtered::<&Text, With<M>>();
+        let text = query.single(self.app.world());
+        text.sections
+            .iter()
+            .map(|section| &*section.value)
+            .collect()
+    }
+}
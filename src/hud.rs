@@ -0,0 +1,318 @@
+use bevy::prelude::*;
+
+use crate::boss::{boss_pattern, BOSS_PATTERNS};
+use crate::mods::Mods;
+use crate::progress::{format_time, Progress};
+use crate::settings::{HudSettings, Settings};
+use crate::sim::{GameMode, PowerUp, Run};
+
+/// How many rings ahead of the death line the wavefront meter starts filling.
+const WAVEFRONT_METER_RINGS: f32 = 6.0;
+const WAVEFRONT_METER_WIDTH: f32 = 160.0;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HudElement {
+    Level,
+    Misses,
+    Score,
+    Wavefront,
+    PowerUps,
+}
+
+impl HudElement {
+    pub const ALL: [HudElement; 5] = [
+        HudElement::Level,
+        HudElement::Misses,
+        HudElement::Score,
+        HudElement::Wavefront,
+        HudElement::PowerUps,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HudElement::Level => "LEVEL",
+            HudElement::Misses => "MISSES",
+            HudElement::Score => "SCORE",
+            HudElement::Wavefront => "WAVEFRONT",
+            HudElement::PowerUps => "POWER-UPS",
+        }
+    }
+
+    pub fn is_enabled(self, hud: &HudSettings) -> bool {
+        let mut hud = *hud;
+        *self.setting(&mut hud)
+    }
+
+    pub fn setting(self, hud: &mut HudSettings) -> &mut bool {
+        match self {
+            HudElement::Level => &mut hud.level,
+            HudElement::Misses => &mut hud.misses,
+            HudElement::Score => &mut hud.score,
+            HudElement::Wavefront => &mut hud.wavefront,
+            HudElement::PowerUps => &mut hud.power_ups,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct WavefrontFill;
+
+/// Game mode, color and boss ring notes. Always shown.
+#[derive(Component)]
+pub struct ModeText;
+
+/// Time attack timer and splits against the personal best.
+#[derive(Component)]
+pub struct SplitsText;
+
+const AHEAD: Color = Color::srgb(0.3, 1.0, 0.4);
+const BEHIND: Color = Color::srgb(1.0, 0.35, 0.3);
+
+/// Names the pattern too, which is what the player goes by if they can't see the color.
+pub fn color_name(color: u32) -> &'static str {
+    match color {
+        0 => "BLUE (SOLID)",
+        1 => "GREEN (STRIPES)",
+        _ => "PURPLE (DOTS)",
+    }
+}
+
+/// 0 when the wavefront is far away, 1 when it reaches the player.
+pub fn wavefront_proximity(wavefront: f32, player_ring: u32) -> f32 {
+    let dist = wavefront - player_ring as f32 - 1.0;
+    ((dist + WAVEFRONT_METER_RINGS) / WAVEFRONT_METER_RINGS).clamp(0.0, 1.0)
+}
+
+pub fn setup_hud(mut commands: Commands) {
+    let style = TextStyle {
+        font_size: 20.0,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                right: Val::Px(12.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::End,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: Color::srgb(0.6, 0.8, 1.0),
+                        ..default()
+                    },
+                ),
+                ModeText,
+            ));
+            parent.spawn((
+                TextBundle::from_section("", style.clone()).with_text_justify(JustifyText::Right),
+                SplitsText,
+            ));
+            for element in [
+                HudElement::Score,
+                HudElement::Level,
+                HudElement::Misses,
+                HudElement::PowerUps,
+            ] {
+                parent.spawn((
+                    TextBundle::from_sections(vec![
+                        TextSection {
+                            value: String::new(),
+                            style: style.clone(),
+                        };
+                        2
+                    ]),
+                    element,
+                ));
+            }
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(WAVEFRONT_METER_WIDTH),
+                            height: Val::Px(8.0),
+                            margin: UiRect::top(Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: Color::WHITE.with_alpha(0.15).into(),
+                        ..default()
+                    },
+                    HudElement::Wavefront,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        WavefrontFill,
+                    ));
+                });
+        });
+}
+
+pub fn update_hud(
+    run: Res<Run>,
+    settings: Res<Settings>,
+    progress: Res<Progress>,
+    mut elements: Query<(&HudElement, &mut Visibility, Option<&mut Text>)>,
+    mut fill: Query<(&mut Style, &mut BackgroundColor), With<WavefrontFill>>,
+    mut mode_text: Query<&mut Text, (With<ModeText>, Without<HudElement>)>,
+    mut splits_text: Query<&mut Text, (With<SplitsText>, Without<HudElement>, Without<ModeText>)>,
+) {
+    let sim = &run.sim;
+    let config = &sim.config;
+    let mut lines = Vec::new();
+    match config.mode {
+        GameMode::Normal => (),
+        GameMode::Practice => {
+            let mut line = format!(
+                "PRACTICE  START {}  SPEED {}%",
+                config.start_level, config.speed_percent
+            );
+            if config.invincible {
+                line.push_str("  INVINCIBLE");
+            }
+            if run.checkpoint.is_some() {
+                line.push_str("  CHECKPOINT SET");
+            }
+            lines.push(line);
+        }
+        GameMode::Zen => lines.push(String::from("ZEN  Q TO QUIT TO RESULTS")),
+        GameMode::TimeAttack => {
+            lines.push(format!(
+                "TIME ATTACK  REACH LEVEL {}",
+                config.time_attack_target
+            ));
+        }
+        GameMode::Lives => lines.push(format!(
+            "LIVES {}  EXTRA LIFE AT LEVEL {}",
+            sim.lives, sim.next_extra_life
+        )),
+        GameMode::Campaign => {
+            if let Some(goal) = config.goal {
+                lines.push(format!("CAMPAIGN  {}", goal.describe()));
+            }
+        }
+        GameMode::Versus => {
+            if let Some(second) = &run.second {
+                lines.push(format!(
+                    "VERSUS  P1 LEVEL {}  P2 LEVEL {}",
+                    sim.level(),
+                    second.level()
+                ));
+            }
+        }
+        // Shown by `race::sync_race`, which knows about the opponent.
+        GameMode::Race => (),
+    }
+    if config.colors {
+        lines.push(format!("ORB {}  X TO CHANGE", color_name(sim.player_color)));
+    }
+    if config.mods != Mods::default() {
+        lines.push(format!(
+            "MODS {}  SCORE {}%",
+            config.mods.codes(),
+            config.mods.score_percent()
+        ));
+    }
+    if config.adaptive {
+        let adaptive = &sim.adaptive;
+        lines.push(format!(
+            "ADAPTIVE  SPEED {:.0}%  ARCS {:.0}%",
+            adaptive.speed * 100.0,
+            adaptive.arc_size * 100.0
+        ));
+    }
+    if sim.is_boss(sim.player_ring + 1) {
+        let pattern = &BOSS_PATTERNS[boss_pattern(sim.player_ring + 1)];
+        lines.push(format!("BOSS RING AHEAD: {}", pattern.name));
+    }
+    mode_text.single_mut().sections[0].value = lines.join("\n");
+
+    let mut splits_text = splits_text.single_mut();
+    let style = splits_text.sections[0].style.clone();
+    splits_text.sections.truncate(1);
+    splits_text.sections[0].value = String::new();
+    if config.mode == GameMode::TimeAttack {
+        let personal_best = progress
+            .time_attack(config)
+            .map_or(&[][..], |r| &r.personal_best[..]);
+        splits_text.sections[0].value = format!("{}\n", format_time(sim.run_time));
+        for (i, &split) in sim.splits.iter().enumerate() {
+            let level = config.split_level(i);
+            splits_text.sections.push(TextSection::new(
+                format!("{level:>3}  {:>9}", format_time(split)),
+                style.clone(),
+            ));
+            let mut delta = TextSection::new("\n", style.clone());
+            if let Some(&best) = personal_best.get(i) {
+                let diff = split - best;
+                delta.value = format!(
+                    "  {}{:.2}\n",
+                    if diff < 0.0 { "-" } else { "+" },
+                    diff.abs()
+                );
+                delta.style.color = if diff < 0.0 { AHEAD } else { BEHIND };
+            }
+            splits_text.sections.push(delta);
+        }
+    }
+    for (element, mut visibility, text) in &mut elements {
+        *visibility = if element.is_enabled(&settings.hud) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        let Some(mut text) = text else {
+            continue;
+        };
+        match element {
+            HudElement::Level => {
+                text.sections[0].value = format!("LEVEL {}", sim.level());
+            }
+            HudElement::Misses => {
+                text.sections[0].value = format!("MISSES {}", sim.miss);
+            }
+            HudElement::Score => {
+                text.sections[0].value = format!("{}", sim.score.points);
+                text.sections[1].value = if sim.score.multiplier() > 1 {
+                    format!("  x{}", sim.score.multiplier())
+                } else {
+                    String::new()
+                };
+            }
+            HudElement::PowerUps => {
+                let mut active = Vec::new();
+                if sim.slow_time > 0.0 {
+                    active.push(format!("{} {:.1}", PowerUp::SlowTime.name(), sim.slow_time));
+                }
+                if sim.shield {
+                    active.push(String::from(PowerUp::Shield.name()));
+                }
+                text.sections[0].value = active.join("  ");
+            }
+            HudElement::Wavefront => (),
+        }
+    }
+
+    let proximity = wavefront_proximity(sim.wavefront, sim.player_ring);
+    let (mut style, mut color) = fill.single_mut();
+    style.width = Val::Percent(proximity * 100.0);
+    *color = Color::linear_rgb(1.0, 1.0 - proximity, 1.0 - proximity).into();
+}
@@ -0,0 +1,403 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::StandIns;
+use crate::online::{OnlineLeaderboard, OnlineStatus};
+use crate::sim::{Run, RunConfig};
+use crate::storage;
+
+const LEADERBOARD_FILE: &str = "leaderboard.ron";
+pub const MAX_ENTRIES: usize = 10;
+const MAX_HISTORY: usize = 50;
+const INITIALS_LEN: usize = 3;
+const HIGHLIGHT: Color = Color::srgb(1.0, 0.8, 0.2);
+
+/// Saved fields all have defaults, so saves from before a field was added still load.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunEntry {
+    pub id: u64,
+    pub config: RunConfig,
+    pub initials: String,
+    pub level: i32,
+    pub misses: u32,
+    pub score: u64,
+    /// Simulated seconds from the start of the run to death.
+    pub duration: f32,
+    /// Unix timestamp in seconds.
+    pub date: u64,
+    /// Replay file name in the save directory.
+    pub replay: Option<String>,
+}
+
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub next_id: u64,
+    /// Best runs of every run config, at most [`MAX_ENTRIES`] per [`RunConfig::ranked_as`].
+    pub entries: Vec<RunEntry>,
+    /// Most recent runs, newest last.
+    pub history: Vec<RunEntry>,
+    pub last_initials: String,
+}
+
+/// On-disk format. Older versions get migrated to the current [`Leaderboard`] on load, so a
+/// format change never silently empties the table.
+#[derive(Serialize, Deserialize)]
+pub enum LeaderboardFile {
+    V1(Leaderboard),
+}
+
+impl LeaderboardFile {
+    pub fn migrate(self) -> Leaderboard {
+        match self {
+            LeaderboardFile::V1(leaderboard) => leaderboard,
+        }
+    }
+}
+
+pub struct Insertion {
+    pub made_table: bool,
+    /// Replays no longer referenced by any entry.
+    pub orphaned_replays: Vec<String>,
+}
+
+impl Leaderboard {
+    pub fn load() -> Self {
+        storage::load::<LeaderboardFile>(LEADERBOARD_FILE)
+            .map(LeaderboardFile::migrate)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(LEADERBOARD_FILE, &LeaderboardFile::V1(self.clone()));
+    }
+
+    /// Best runs ranked with `config`, highest score first.
+    pub fn top(&self, config: &RunConfig) -> Vec<&RunEntry> {
+        let mut top: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|e| e.config.ranked_as() == config.ranked_as())
+            .collect();
+        top.sort_by(|a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id)));
+        top
+    }
+
+    pub fn qualifies(&self, config: &RunConfig, score: u64) -> bool {
+        let top = self.top(config);
+        top.len() < MAX_ENTRIES || top.last().is_some_and(|e| score > e.score)
+    }
+
+    pub fn insert(&mut self, entry: RunEntry) -> Insertion {
+        let mut removed = Vec::new();
+        let made_table = self.qualifies(&entry.config, entry.score);
+
+        self.history.push(entry.clone());
+        if self.history.len() > MAX_HISTORY {
+            removed.push(self.history.remove(0));
+        }
+
+        if made_table {
+            let config = entry.config;
+            self.entries.push(entry);
+            let lowest = self.top(&config).get(MAX_ENTRIES).map(|e| e.id);
+            if let Some(id) = lowest {
+                let i = self.entries.iter().position(|e| e.id == id).unwrap();
+                removed.push(self.entries.remove(i));
+            }
+        } else {
+            removed.push(entry);
+        }
+
+        let orphaned_replays = removed
+            .into_iter()
+            .filter_map(|e| e.replay)
+            .filter(|replay| {
+                !self
+                    .entries
+                    .iter()
+                    .chain(&self.history)
+                    .any(|e| e.replay.as_ref() == Some(replay))
+            })
+            .collect();
+
+        Insertion {
+            made_table,
+            orphaned_replays,
+        }
+    }
+
+    pub fn set_initials(&mut self, id: u64, initials: &str) {
+        for entry in self.entries.iter_mut().chain(&mut self.history) {
+            if entry.id == id {
+                entry.initials = initials.to_string();
+            }
+        }
+        self.last_initials = initials.to_string();
+    }
+}
+
+pub struct InitialsEntry {
+    letters: [u8; INITIALS_LEN],
+    cursor: usize,
+}
+
+impl InitialsEntry {
+    fn new(last: &str) -> Self {
+        let mut letters = [b'A'; INITIALS_LEN];
+        for (letter, c) in letters.iter_mut().zip(last.bytes()) {
+            if c.is_ascii_uppercase() {
+                *letter = c;
+            }
+        }
+        Self { letters, cursor: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.letters).unwrap()
+    }
+
+    fn change(&mut self, delta: i8) {
+        let letter = &mut self.letters[self.cursor];
+        *letter = b'A' + ((*letter - b'A') as i8 + delta).rem_euclid(26) as u8;
+    }
+}
+
+/// The finished run shown on the game over screen.
+#[derive(Resource, Default)]
+pub struct GameOver {
+    pub run_id: Option<u64>,
+    pub initials: Option<InitialsEntry>,
+}
+
+#[derive(Component)]
+pub struct LeaderboardText;
+
+pub fn setup_leaderboard(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(25.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        LeaderboardText,
+    ));
+}
+
+/// Records the run when the player dies, saving its replay and adding it to the leaderboard.
+/// Unranked modes, replays and the bot's runs aren't recorded.
+pub(crate) fn finish_run(
+    run: Res<Run>,
+    stand_ins: StandIns,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut game_over: ResMut<GameOver>,
+) {
+    if !run.sim.over() {
+        if game_over.run_id.is_some() {
+            *game_over = GameOver::default();
+        }
+        return;
+    }
+    if game_over.run_id.is_some() || !run.sim.config.mode.is_ranked() || stand_ins.play(&run) {
+        return;
+    }
+
+    let id = leaderboard.next_id;
+    leaderboard.next_id += 1;
+    let replay = format!("replays/{id}.solr");
+    storage::save_bytes(&replay, &run.replay.to_bytes());
+
+    let sim = &run.sim;
+    let insertion = leaderboard.insert(RunEntry {
+        id,
+        config: sim.config,
+        initials: String::from("---"),
+        level: sim.level(),
+        misses: sim.miss,
+        score: sim.score.points,
+        duration: sim.run_time,
+        date: storage::unix_now(),
+        replay: Some(replay),
+    });
+    for replay in &insertion.orphaned_replays {
+        storage::remove(replay);
+    }
+    leaderboard.save();
+
+    game_over.run_id = Some(id);
+    if insertion.made_table {
+        game_over.initials = Some(InitialsEntry::new(&leaderboard.last_initials));
+    }
+}
+
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+];
+
+/// Runs before `draw` and consumes the keys it uses, so typing initials doesn't also
+/// toggle mute or restart the game.
+pub fn enter_initials(
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut game_over: ResMut<GameOver>,
+) {
+    let GameOver {
+        run_id: Some(id),
+        initials: Some(initials),
+    } = &mut *game_over
+    else {
+        return;
+    };
+    let pad = |button| {
+        gamepads
+            .iter()
+            .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button)))
+    };
+    let mut key = |key| keyboard_input.clear_just_pressed(key);
+
+    for (i, letter_key) in LETTER_KEYS.into_iter().enumerate() {
+        if key(letter_key) {
+            initials.letters[initials.cursor] = b'A' + i as u8;
+            initials.cursor = (initials.cursor + 1).min(INITIALS_LEN - 1);
+        }
+    }
+    if key(KeyCode::ArrowUp) | pad(GamepadButtonType::DPadUp) {
+        initials.change(1);
+    }
+    if key(KeyCode::ArrowDown) | pad(GamepadButtonType::DPadDown) {
+        initials.change(-1);
+    }
+    if key(KeyCode::ArrowLeft) | key(KeyCode::Backspace) | pad(GamepadButtonType::DPadLeft) {
+        initials.cursor = initials.cursor.saturating_sub(1);
+    }
+    if key(KeyCode::ArrowRight) | pad(GamepadButtonType::DPadRight) {
+        initials.cursor = (initials.cursor + 1).min(INITIALS_LEN - 1);
+    }
+    key(KeyCode::Space);
+    if key(KeyCode::Enter) | pad(GamepadButtonType::South) {
+        let id = *id;
+        leaderboard.set_initials(id, initials.as_str());
+        leaderboard.save();
+        game_over.initials = None;
+    }
+}
+
+pub fn update_leaderboard_text(
+    run: Res<Run>,
+    leaderboard: Res<Leaderboard>,
+    game_over: Res<GameOver>,
+    online: Res<OnlineLeaderboard>,
+    mut text: Query<&mut Text, With<LeaderboardText>>,
+) {
+    let mut text = text.single_mut();
+    text.sections.truncate(1);
+    text.sections[0].value = String::new();
+    let Some(run_id) = game_over.run_id else {
+        return;
+    };
+
+    let style = text.sections[0].style.clone();
+    let config = &run.sim.config;
+    text.sections[0].value = format!(
+        "HIGH SCORES  {} {}{}{}{} SEED {}\n",
+        config.mode.name(),
+        config.difficulty.name(),
+        if config.colors { " COLORS" } else { "" },
+        if config.level.is_some() { " LEVEL" } else { "" },
+        if config.adaptive { " ADAPTIVE" } else { "" },
+        config.seed
+    );
+    for (i, entry) in leaderboard.top(config).into_iter().enumerate() {
+        let is_new = entry.id == run_id;
+        let initials = match &game_over.initials {
+            Some(initials) if is_new => initials.as_str(),
+            _ => &entry.initials,
+        };
+        let mut line = format!(
+            "{:>2}. {} {:>9}  LVL {:>4}  MISS {:>3}  {:>6.1}s  {}  {}\n",
+            i + 1,
+            initials,
+            entry.score,
+            entry.level,
+            entry.misses,
+            entry.duration,
+            storage::format_date(entry.date),
+            entry.config.mods.codes(),
+        );
+        if let (true, Some(initials)) = (is_new, &game_over.initials) {
+            let mut cursor = String::from("    ");
+            cursor.extend((0..INITIALS_LEN).map(|i| if i == initials.cursor { '^' } else { ' ' }));
+            line.push_str(&cursor);
+            line.push('\n');
+        }
+        let mut style = style.clone();
+        if is_new {
+            style.color = HIGHLIGHT;
+        }
+        text.sections.push(TextSection::new(line, style));
+    }
+    if game_over.initials.is_some() {
+        text.sections.push(TextSection::new(
+            "\nNEW HIGH SCORE! TYPE OR USE UP/DOWN FOR YOUR INITIALS, ENTER TO SAVE\n",
+            style,
+        ));
+        return;
+    }
+
+    let mut online_text = match &online.status {
+        OnlineStatus::Idle => String::new(),
+        OnlineStatus::Busy => String::from("\nONLINE  SUBMITTING...\n"),
+        OnlineStatus::Ranked(rank) => format!("\nONLINE  YOUR RANK #{rank}\n"),
+        OnlineStatus::Failed(e) => format!("\nONLINE  {}\n", e.to_string().to_uppercase()),
+    };
+    for entry in &online.rankings {
+        online_text.push_str(&format!(
+            "{:>2}. {} {:>9}  LVL {:>4}  MISS {:>3}  {:>6.1}s  {}\n",
+            entry.rank,
+            entry.initials,
+            entry.score,
+            entry.level,
+            entry.misses,
+            entry.duration,
+            entry.mods.codes(),
+        ));
+    }
+    text.sections.push(TextSection::new(online_text, style));
+}
@@ -0,0 +1,262 @@
+//! Hand-made levels: `.level.ron` assets that spell out every arc of a run's rings instead of
+//! leaving them to `hash_noise`. Rings past the end of a level go back to procedural arcs.
+
+use std::fmt;
+use std::sync::Arc;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::math::{vec4, UVec4, Vec4};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::sampling::pfract;
+use crate::settings::Settings;
+use crate::sim::{GameMode, Run, RunConfig, COLOR_COUNT, STARTING_LEVEL};
+
+/// Levels that can be picked in the settings menu, as asset paths.
+pub const LEVELS: [&str; 1] = ["levels/first_steps.level.ron"];
+/// Upper bound on arcs per ring, fixed by the shader's uniform array.
+pub const MAX_LEVEL_ARCS: usize = 6;
+/// Rings around the player that are uploaded to the shader. Rings outside of it are drawn
+/// procedurally, which is fine since they're too far away to see.
+pub const LEVEL_WINDOW: usize = 32;
+/// Rings inside the player that are part of the window.
+const LEVEL_WINDOW_INSIDE: u32 = 8;
+
+#[derive(Asset, TypePath, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub name: String,
+    /// Rings from [`STARTING_LEVEL`] outward.
+    pub rings: Vec<LevelRing>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelRing {
+    pub arcs: Vec<LevelArc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelArc {
+    /// Position at `t = 0`, in turns.
+    #[serde(default)]
+    pub start: f32,
+    /// In turns.
+    pub size: f32,
+    /// Turns per unit of `t`.
+    pub speed: f32,
+    /// Turns the other way.
+    #[serde(default)]
+    pub reverse: bool,
+    /// See [`crate::sim::ARC_HAZARD`].
+    #[serde(default)]
+    pub hazard: bool,
+    /// See [`RunConfig::colors`].
+    #[serde(default)]
+    pub color: u32,
+}
+
+impl LevelArc {
+    /// Turns per unit of `t`, negative when reversed.
+    pub fn velocity(&self) -> f32 {
+        if self.reverse {
+            -self.speed
+        } else {
+            self.speed
+        }
+    }
+
+    /// Start and size at `t`, in turns.
+    pub fn at(&self, t: f32) -> (f32, f32) {
+        (pfract(self.start + t * self.velocity()), self.size)
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid(String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(e) => write!(f, "failed to read level: {e}"),
+            LevelError::Parse(e) => write!(f, "failed to parse level: {e}"),
+            LevelError::Invalid(e) => write!(f, "invalid level: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(e: std::io::Error) -> Self {
+        LevelError::Io(e)
+    }
+}
+
+impl Level {
+    pub fn from_ron(bytes: &[u8]) -> Result<Self, LevelError> {
+        let level: Level = ron::de::from_bytes(bytes).map_err(LevelError::Parse)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Every ring needs a safe arc to land on, and at most [`MAX_LEVEL_ARCS`] arcs.
+    pub fn validate(&self) -> Result<(), LevelError> {
+        for (i, ring) in self.rings.iter().enumerate() {
+            let ring_number = i as u32 + STARTING_LEVEL;
+            let invalid = |e: &str| Err(LevelError::Invalid(format!("ring {ring_number}: {e}")));
+            if ring.arcs.len() > MAX_LEVEL_ARCS {
+                return invalid(&format!("more than {MAX_LEVEL_ARCS} arcs"));
+            }
+            if ring.arcs.iter().all(|arc| arc.hazard) {
+                return invalid("no safe arc");
+            }
+            for arc in &ring.arcs {
+                if !(arc.size > 0.0 && arc.size < 1.0) {
+                    return invalid("arc sizes have to be between 0 and 1");
+                }
+                if arc.color >= COLOR_COUNT {
+                    return invalid(&format!("arc colors have to be below {COLOR_COUNT}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The level's ring, if it has one.
+    pub fn ring(&self, ring: u32) -> Option<&LevelRing> {
+        self.rings.get(ring.checked_sub(STARTING_LEVEL)? as usize)
+    }
+
+    /// FNV-1a of the level's RON, stored in [`RunConfig::level`] so runs on different levels,
+    /// or different versions of one, are ranked apart.
+    pub fn id(&self) -> u64 {
+        let ron = ron::to_string(self).unwrap();
+        ron.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    /// The rings of [`LEVEL_WINDOW`] around `player_ring` packed for the shader: first ring,
+    /// ring count, arc count per ring four to a vector, and `(start, size, velocity, flags)` per
+    /// arc. Flags are the hazard bit and the color above it.
+    pub fn gpu_window(
+        &self,
+        player_ring: u32,
+    ) -> (
+        u32,
+        u32,
+        [UVec4; LEVEL_WINDOW / 4],
+        [[Vec4; MAX_LEVEL_ARCS]; LEVEL_WINDOW],
+    ) {
+        let first = player_ring
+            .saturating_sub(LEVEL_WINDOW_INSIDE)
+            .max(STARTING_LEVEL);
+        let mut counts = [UVec4::ZERO; LEVEL_WINDOW / 4];
+        let mut arcs = [[Vec4::ZERO; MAX_LEVEL_ARCS]; LEVEL_WINDOW];
+        let mut ring_count = 0;
+        while ring_count < LEVEL_WINDOW {
+            let Some(ring) = self.ring(first + ring_count as u32) else {
+                break;
+            };
+            counts[ring_count / 4][ring_count % 4] = ring.arcs.len() as u32;
+            for (j, arc) in ring.arcs.iter().enumerate() {
+                let flags = arc.hazard as u32 | arc.color << 1;
+                arcs[ring_count][j] = vec4(arc.start, arc.size, arc.velocity(), flags as f32);
+            }
+            ring_count += 1;
+        }
+        (first, ring_count as u32, counts, arcs)
+    }
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    type Asset = Level;
+    type Settings = ();
+    type Error = LevelError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Level, LevelError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Level::from_ron(&bytes)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// The level picked in the settings, once it has loaded.
+#[derive(Resource, Default)]
+pub struct LoadedLevel {
+    path: Option<String>,
+    handle: Option<Handle<Level>>,
+    pub level: Option<Arc<Level>>,
+}
+
+impl LoadedLevel {
+    pub fn handle(&self) -> Option<&Handle<Level>> {
+        self.handle.as_ref()
+    }
+
+    pub fn new_run(&self, config: RunConfig) -> Run {
+        Run::with_level(config, self.level.clone())
+    }
+}
+
+/// Starts loading the level picked in the settings. When it finishes loading, or is hot
+/// reloaded, a run the player hasn't made any progress in is restarted on it. Campaign runs play
+/// their stage's level instead.
+pub(crate) fn load_level(
+    asset_server: Res<AssetServer>,
+    levels: Res<Assets<Level>>,
+    mut events: EventReader<AssetEvent<Level>>,
+    settings: Res<Settings>,
+    mut loaded: ResMut<LoadedLevel>,
+    mut run: ResMut<Run>,
+) {
+    // A level that's already loaded, say when switching back to it, sends no new event.
+    let mut changed = false;
+    if loaded.path != settings.level {
+        loaded.path.clone_from(&settings.level);
+        loaded.handle = settings.level.clone().map(|path| asset_server.load(path));
+        loaded.level = None;
+        changed = loaded.handle.as_ref().is_some_and(|h| levels.contains(h));
+    }
+    let id = loaded.handle.as_ref().map(Handle::id);
+    for event in events.read() {
+        if let Some(id) = id {
+            changed |= event.is_loaded_with_dependencies(id) || event.is_modified(id);
+        }
+    }
+    if !changed {
+        return;
+    }
+    let level = loaded.handle.as_ref().and_then(|handle| levels.get(handle));
+    loaded.level = level.cloned().map(Arc::new);
+    let sim = &run.sim;
+    let untouched =
+        sim.highest_ring == STARTING_LEVEL + sim.config.start_level && sim.miss == 0 && !sim.over();
+    if untouched && settings.mode != GameMode::Campaign {
+        *run = loaded.new_run(settings.run_config());
+    }
+}
+
+/// Menu name of a level path.
+pub fn level_name(path: &str) -> String {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let stem = file.strip_suffix(".level.ron").unwrap_or(file);
+    stem.replace('_', " ").to_uppercase()
+}
@@ -2,6 +2,7 @@
 #![allow(non_snake_case)] // For game name
 
 use std::f32::consts::*;
+use std::path::PathBuf;
 use std::time::Duration;
 
 use bevy::asset::AssetMetaCheck;
@@ -14,7 +15,7 @@ use bevy::prelude::*;
 use bevy::render::render_resource::{AsBindGroup, ShaderRef, ShaderType};
 
 use bevy::sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle};
-use bevy::window::{PresentMode, WindowMode};
+use bevy::window::{Cursor, PresentMode, WindowMode};
 use bevy::winit::{UpdateMode, WinitSettings};
 use bevy_asset_loader::{
     asset_collection::AssetCollection,
@@ -22,19 +23,87 @@ use bevy_asset_loader::{
 };
 use bevy_kira_audio::{prelude::AudioSource, Audio, AudioControl, AudioPlugin};
 use bevy_kira_audio::{AudioInstance, AudioTween};
+pub mod adaptive;
+pub mod autoplay;
+pub mod boss;
+mod campaign;
+mod cli;
+mod editor;
+pub mod gym;
+pub mod headless;
+mod hud;
+pub mod leaderboard;
+pub mod level;
+pub mod mods;
+pub mod online;
+pub mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod race;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod replay;
 pub mod sampling;
+pub mod score;
+mod settings;
+pub mod sim;
+mod split;
+mod storage;
+use autoplay::Autoplay;
+use campaign::{Campaign, CampaignAssets, CampaignLoader, ChapterAssets, CurrentChapter, NextRun};
+use editor::Editor;
 use iyes_progress::{ProgressCounter, ProgressPlugin};
+use leaderboard::{GameOver, Leaderboard};
+use level::{Level, LevelLoader, LoadedLevel};
+use online::OnlineLeaderboard;
+use progress::Progress;
+use replay::Replay;
 #[cfg(feature = "hot_reload")]
 use ridiculous_bevy_hot_reloading::{hot_reloading_macros::make_hot, HotReloadPlugin};
-use sampling::{gain_from_db, hash_noise, pfract};
+use sampling::gain_from_db;
+use settings::Settings;
+use sim::{Difficulty, GameMode, JumpResult, Run, Sim, SimInput};
 
 #[cfg(not(target_arch = "wasm32"))]
 use bevy::render::view::screenshot::ScreenshotManager;
 
-const GAME_SPEED: f32 = 0.08;
-const STARTING_LEVEL: u32 = 10;
 const STEP_ANIM_SPEED: f32 = 16.0;
-const COOLDOWN_ANIM_SPEED: f32 = 1.0;
+
+/// Keys and gamepad buttons a player jumps, dives and changes color with.
+struct Controls {
+    jump: &'static [KeyCode],
+    dive: &'static [KeyCode],
+    change_color: &'static [KeyCode],
+    gamepad: bool,
+}
+
+const SOLO_CONTROLS: Controls = Controls {
+    jump: &[KeyCode::ArrowUp, KeyCode::KeyW, KeyCode::Space],
+    dive: &[KeyCode::ArrowDown, KeyCode::KeyS],
+    change_color: &[KeyCode::KeyX],
+    gamepad: false,
+};
+
+/// In [`GameMode::Versus`] the arrows and gamepads go to the second player.
+const VERSUS_CONTROLS: [Controls; 2] = [
+    Controls {
+        jump: &[KeyCode::KeyW, KeyCode::Space],
+        dive: &[KeyCode::KeyS],
+        change_color: &[KeyCode::KeyX],
+        gamepad: false,
+    },
+    Controls {
+        jump: &[KeyCode::ArrowUp],
+        dive: &[KeyCode::ArrowDown],
+        change_color: &[KeyCode::ShiftRight],
+        gamepad: true,
+    },
+];
+
+/// Stereo position of each versus player's sounds, 0 being hard left.
+const VERSUS_PANNING: [f64; 2] = [0.2, 0.8];
+
+/// Orb color of each player when arcs aren't colored. Alpha is the orb's opacity.
+const PLAYER_TINTS: [Vec4; 2] = [Vec4::ONE, vec4(1.0, 0.55, 0.15, 1.0)];
 
 #[cfg(feature = "hot_reload")]
 #[no_mangle] // Needed so libloading can find this entry point
@@ -46,6 +115,8 @@ fn main() {
 enum GameLoading {
     #[default]
     AssetLoading,
+    /// Loading the campaign chapter picked in the settings, see [`campaign::pick_chapter`].
+    ChapterLoading,
     Loaded,
 }
 
@@ -53,61 +124,202 @@ const MAGENTA: Color = Color::linear_rgb(1.0, 0.0, 1.0);
 const RED: Color = Color::linear_rgb(1.0, 0.0, 0.0);
 const GREEN: Color = Color::linear_rgb(0.0, 1.0, 0.0);
 
+/// How [`build_app`] sets the game up, from the command line. Options override the saved
+/// settings.
+#[derive(Clone, Debug, Default)]
+pub struct AppOptions {
+    /// No window, rendering, audio output or save files, see [`headless`]. With a replay or the
+    /// bot to play, quits once the run is over and prints the results.
+    pub headless: bool,
+    /// Seed of every run, instead of 0.
+    pub seed: Option<u32>,
+    /// Practices from this level, relative to [`sim::STARTING_LEVEL`].
+    pub start_level: Option<u32>,
+    pub difficulty: Option<Difficulty>,
+    /// Starts in borderless fullscreen instead of a window.
+    pub fullscreen: bool,
+    pub mute: bool,
+    /// Played back in place of the player's input, on the rings it was recorded on.
+    pub replay: Option<Replay>,
+    /// Where to save the replay of each run once it's over.
+    pub record: Option<PathBuf>,
+    /// A bot with this [`Autoplay::skill`] plays every run.
+    pub autoplay: Option<f32>,
+}
+
 pub fn app() {
-    App::new()
-        .insert_resource(Msaa::Off)
-        .insert_resource(ClearColor(Color::srgb(0.05, 0.05, 0.05)))
-        .insert_resource(WinitSettings {
-            focused_mode: UpdateMode::Continuous,
-            unfocused_mode: UpdateMode::Continuous,
-        })
-        .add_plugins(
-            DefaultPlugins
-                .set(AssetPlugin {
-                    // Wasm builds will check for meta files (that don't exist) if this isn't set.
-                    meta_check: AssetMetaCheck::Never,
-                    ..default()
-                })
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: String::from("Sol"),
-                        present_mode: PresentMode::AutoNoVsync,
-                        fit_canvas_to_parent: true,
+    build_app(&AppOptions::default()).run();
+}
+
+pub fn build_app(options: &AppOptions) -> App {
+    let mut app = App::new();
+    storage::add_asset_source(&mut app);
+    if options.headless {
+        storage::disable();
+        app.add_plugins(headless::HeadlessPlugin)
+            .insert_state(GameLoading::Loaded);
+    } else {
+        app.insert_resource(Msaa::Off)
+            .insert_resource(ClearColor(Color::srgb(0.05, 0.05, 0.05)))
+            .insert_resource(WinitSettings {
+                focused_mode: UpdateMode::Continuous,
+                unfocused_mode: UpdateMode::Continuous,
+            })
+            .add_plugins(
+                DefaultPlugins
+                    .set(AssetPlugin {
+                        // Wasm builds will check for meta files (that don't exist) if this isn't set.
+                        meta_check: AssetMetaCheck::Never,
+                        ..default()
+                    })
+                    .set(WindowPlugin {
+                        primary_window: Some(Window {
+                            title: String::from("Sol"),
+                            present_mode: PresentMode::AutoNoVsync,
+                            fit_canvas_to_parent: true,
+                            mode: if options.fullscreen {
+                                WindowMode::BorderlessFullscreen
+                            } else {
+                                WindowMode::Windowed
+                            },
+                            cursor: Cursor {
+                                visible: !options.fullscreen,
+                                ..default()
+                            },
+                            ..default()
+                        }),
                         ..default()
                     }),
+            )
+            .add_plugins((
+                Material2dPlugin::<DataMaterial>::default(),
+                LogDiagnosticsPlugin::default(),
+                FrameTimeDiagnosticsPlugin,
+                AudioPlugin,
+                //bevy_framepace::debug::DiagnosticsPlugin, // Crashes
+                #[cfg(feature = "hot_reload")]
+                HotReloadPlugin {
+                    auto_watch: true,
+                    bevy_dylib: true,
                     ..default()
-                }),
-        )
-        .init_state::<GameLoading>()
-        .add_plugins(ProgressPlugin::new(GameLoading::AssetLoading))
+                },
+                //bevy_framepace::FramepacePlugin,
+            ))
+            .init_state::<GameLoading>()
+            .add_plugins(ProgressPlugin::new(GameLoading::AssetLoading))
+            .add_loading_state(
+                LoadingState::new(GameLoading::AssetLoading)
+                    .continue_to_state(GameLoading::Loaded)
+                    .load_collection::<AudioAssets>()
+                    .load_collection::<CampaignAssets>(),
+            );
+    }
+
+    let mut settings = Settings::load();
+    if let Some(seed) = options.seed {
+        settings.seed = seed;
+    }
+    if let Some(start_level) = options.start_level {
+        settings.mode = GameMode::Practice;
+        settings.practice.start_level = start_level;
+    }
+    if let Some(difficulty) = options.difficulty {
+        settings.difficulty = difficulty;
+    }
+    let run = match &options.replay {
+        Some(replay) => {
+            // Keeps a hand-made level or campaign chapter from replacing the replayed run.
+            settings.mode = replay.config.mode;
+            settings.level = None;
+            app.insert_resource(cli::Playback::new(replay.clone()));
+            Run::new(replay.config)
+        }
+        None => Run::new(settings.run_config()),
+    };
+    if let Some(skill) = options.autoplay {
+        let seed = options.seed.unwrap_or_default();
+        app.insert_resource(cli::Autoplayer(Autoplay::new(skill, seed)));
+    }
+    if let Some(path) = &options.record {
+        app.insert_resource(cli::Recording(path.clone()))
+            .add_systems(Update, cli::record_run.after(draw));
+    }
+    if options.headless && (options.replay.is_some() || options.autoplay.is_some()) {
+        app.add_systems(
+            Update,
+            cli::finish_headless_run.after(draw).after(cli::record_run),
+        );
+    }
+    app.insert_resource(run)
+        .insert_resource(OnlineLeaderboard::from_settings(&settings))
+        .insert_resource(settings)
+        .insert_resource(Leaderboard::load())
+        .insert_resource(Progress::load())
+        .init_resource::<GameOver>()
+        .init_resource::<LoadedLevel>()
+        .init_resource::<Editor>()
+        .init_resource::<CurrentChapter>()
+        .insert_resource(Muted(options.mute))
+        .init_asset::<Level>()
+        .register_asset_loader(LevelLoader)
+        .init_asset::<Campaign>()
+        .register_asset_loader(CampaignLoader)
         .add_loading_state(
-            LoadingState::new(GameLoading::AssetLoading)
+            LoadingState::new(GameLoading::ChapterLoading)
                 .continue_to_state(GameLoading::Loaded)
-                .load_collection::<AudioAssets>(),
+                .load_collection::<ChapterAssets>(),
+        )
+        .add_systems(
+            Startup,
+            (
+                setup,
+                hud::setup_hud,
+                settings::setup_settings_menu,
+                leaderboard::setup_leaderboard,
+                progress::setup_progress_text,
+                editor::setup_editor_text,
+                campaign::setup_chapter_text,
+                split::setup_split_views,
+                #[cfg(not(target_arch = "wasm32"))]
+                race::setup_race,
+            ),
+        )
+        .add_systems(
+            OnEnter(GameLoading::Loaded),
+            (start_music, campaign::start_chapter).chain(),
         )
-        .add_plugins((
-            Material2dPlugin::<DataMaterial>::default(),
-            LogDiagnosticsPlugin::default(),
-            FrameTimeDiagnosticsPlugin,
-            AudioPlugin,
-            //bevy_framepace::debug::DiagnosticsPlugin, // Crashes
-            #[cfg(feature = "hot_reload")]
-            HotReloadPlugin {
-                auto_watch: true,
-                bevy_dylib: true,
-                ..default()
-            },
-            //bevy_framepace::FramepacePlugin,
-        ))
-        .add_systems(Startup, setup)
-        .add_systems(OnEnter(GameLoading::Loaded), start_music)
-        .add_systems(Update, draw.run_if(in_state(GameLoading::Loaded)))
         .add_systems(
             Update,
-            loading_ui.run_if(in_state(GameLoading::AssetLoading)),
+            (
+                level::load_level,
+                campaign::pick_chapter,
+                leaderboard::enter_initials,
+                draw,
+                #[cfg(not(target_arch = "wasm32"))]
+                race::sync_race,
+                campaign::update_chapter,
+                editor::edit_level,
+                split::update_split_views,
+                leaderboard::finish_run,
+                progress::record_progress,
+                campaign::record_stage,
+                online::sync_online,
+                settings::settings_menu,
+                hud::update_hud,
+                leaderboard::update_leaderboard_text,
+                progress::update_progress_text,
+            )
+                .chain()
+                .run_if(in_state(GameLoading::Loaded)),
         )
-        //.add_systems(Update, update_cursor_latency_test)
-        .run();
+        .add_systems(
+            Update,
+            loading_ui.run_if(not(in_state(GameLoading::Loaded))),
+        );
+    //app.add_systems(Update, update_cursor_latency_test);
+    #[cfg(feature = "remote")]
+    app.add_plugins(remote::RemotePlugin::default());
+    app
 }
 
 pub fn update_cursor_latency_test(windows: Query<&Window>, mut gizmos: Gizmos) {
@@ -144,14 +356,36 @@ pub struct AudioAssets {
     #[asset(path = "audio/theme1.flac")]
     pub theme1: Handle<AudioSource>,
 }
+/// The game's sound is off, toggled with M.
+#[derive(Resource, Default)]
+pub struct Muted(pub bool);
 #[derive(Resource)]
 pub struct OrbAudioHandle(pub Handle<AudioInstance>);
+/// The looping music, swapped out by campaign chapters.
+#[derive(Resource)]
+pub struct MusicHandle(pub Handle<AudioInstance>);
 
-fn start_music(mut commands: Commands, asset_server: Res<AssetServer>, audio: Res<Audio>) {
-    audio
-        .play(asset_server.load("audio/theme1.flac"))
-        .looped()
-        .with_volume(gain_from_db(-8.0) as f64);
+fn start_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    music: Option<Res<MusicHandle>>,
+    muted: Res<Muted>,
+) {
+    // Loaded is entered again after every campaign chapter loads.
+    if music.is_some() {
+        return;
+    }
+    if muted.0 {
+        audio.pause();
+    }
+    commands.insert_resource(MusicHandle(
+        audio
+            .play(asset_server.load("audio/theme1.flac"))
+            .looped()
+            .with_volume(gain_from_db(-8.0) as f64)
+            .handle(),
+    ));
     commands.insert_resource(OrbAudioHandle(
         audio
             .play(asset_server.load("audio/close.flac"))
@@ -161,6 +395,12 @@ fn start_music(mut commands: Commands, asset_server: Res<AssetServer>, audio: Re
     ));
 }
 
+/// The [`DataMaterial`] the game state lives in. It's drawn full screen outside of
+/// split-screen, see [`split`].
+#[derive(Resource)]
+pub struct GameMaterial(pub Handle<DataMaterial>);
+#[derive(Component)]
+struct GameQuad;
 #[derive(Component)]
 struct GameText;
 #[derive(Component)]
@@ -174,19 +414,26 @@ fn setup(
 ) {
     // FXAA is a bit silly here but with everything moving so much it doesn't really matter.
     // This allows for simpler math in the game shader while avoiding multi sampling in the shader for every fragment.
-    commands.spawn(Camera2dBundle::default()).insert(Fxaa {
-        enabled: true,
-        edge_threshold: Sensitivity::Ultra,
-        edge_threshold_min: Sensitivity::Ultra,
-    });
+    commands
+        .spawn((Camera2dBundle::default(), IsDefaultUiCamera))
+        .insert(Fxaa {
+            enabled: true,
+            edge_threshold: Sensitivity::Ultra,
+            edge_threshold_min: Sensitivity::Ultra,
+        });
 
     // quad
-    commands.spawn(MaterialMesh2dBundle {
-        mesh: meshes.add(Triangle2d::default()).into(),
-        transform: Transform::from_translation(vec3(0.0, 0.0, -100.0)),
-        material: materials.add(DataMaterial::default()),
-        ..default()
-    });
+    let material = materials.add(DataMaterial::default());
+    commands.insert_resource(GameMaterial(material.clone()));
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes.add(Triangle2d::default()).into(),
+            transform: Transform::from_translation(vec3(0.0, 0.0, -100.0)),
+            material,
+            ..default()
+        },
+        GameQuad,
+    ));
 
     let style = TextStyle {
         font_size: 40.0,
@@ -238,28 +485,43 @@ fn setup(
 #[cfg_attr(feature = "hot_reload", make_hot)]
 fn draw(
     time: Res<Time>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut materials: ResMut<Assets<DataMaterial>>,
+    input: (
+        Res<ButtonInput<KeyCode>>,
+        Res<Gamepads>,
+        Res<ButtonInput<GamepadButton>>,
+    ),
+    mut materials: (ResMut<Assets<DataMaterial>>, Res<GameMaterial>),
     mut window: Query<(Entity, &mut Window)>,
     mut text: (
         Query<&mut Text, With<GameText>>,
         Query<&mut Text, (With<DebugText>, Without<GameText>)>,
     ),
+    mut game: (
+        ResMut<Run>,
+        NextRun,
+        Res<Editor>,
+        Option<ResMut<cli::Playback>>,
+        Option<ResMut<cli::Autoplayer>>,
+        Res<Settings>,
+    ),
 
     audio: Res<bevy_kira_audio::Audio>,
     audio_assets: Res<AudioAssets>,
     close_audio: Option<Res<OrbAudioHandle>>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
-    mut audio_muted: Local<bool>,
+    mut audio_muted: ResMut<Muted>,
     mut used_debug: Local<bool>,
     mut debug_screenshot_on_jump: Local<bool>,
     mut draw_debug: Local<bool>,
     mut gizmos: Gizmos,
     #[cfg(not(target_arch = "wasm32"))] mut screenshot_manager: ResMut<ScreenshotManager>,
 ) {
-    let (_, gpu) = materials.iter_mut().next().unwrap();
+    let (keyboard_input, gamepads, gamepad_buttons) = (&input.0, &input.1, &input.2);
+    let gpu = materials.0.get_mut(&materials.1 .0).unwrap();
     let (window_entity, mut window) = window.iter_mut().next().unwrap();
     let (mut text, mut debug_text) = (text.0.single_mut(), text.1.single_mut());
+    let (run, next_run) = (&mut game.0, &game.1);
+    let editing = game.2.open;
     if keyboard_input.just_pressed(KeyCode::F11) || keyboard_input.just_pressed(KeyCode::KeyF) {
         if window.mode != WindowMode::BorderlessFullscreen {
             window.mode = WindowMode::BorderlessFullscreen;
@@ -274,8 +536,8 @@ fn draw(
         window.cursor.visible = true;
     }
     if keyboard_input.just_pressed(KeyCode::KeyM) {
-        *audio_muted = !*audio_muted;
-        if *audio_muted {
+        audio_muted.0 = !audio_muted.0;
+        if audio_muted.0 {
             audio.pause();
         } else {
             audio.resume();
@@ -302,11 +564,6 @@ fn draw(
         debug_text.sections[1].value = String::from("DEBUG SCREENSHOT ON JUMP\n");
     }
 
-    if state.player_ring == 0 {
-        state.player_ring = STARTING_LEVEL;
-    }
-    let rel_player_level = state.player_ring as i32 - STARTING_LEVEL as i32;
-
     state.resolution = window
         .physical_size()
         .as_vec2()
@@ -315,27 +572,28 @@ fn draw(
     state.scale_factor = window.scale_factor();
     state.time += time.delta_seconds();
     state.frame = state.frame.wrapping_add(1);
-    if state.paused == 0 {
-        if state.t * 7.0 > (state.player_ring + 1) as f32 {
-            state.t += time.delta_seconds() * GAME_SPEED * 0.3;
-            state.player_dead = 1;
-        } else {
-            state.t += time.delta_seconds() * GAME_SPEED;
+
+    let practice = run.sim.config.mode == GameMode::Practice && !editing;
+    if practice {
+        if keyboard_input.just_pressed(KeyCode::KeyC) && !run.sim.dead {
+            run.checkpoint = Some(run.sim.clone());
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyR) {
+            run.retry();
+            state.paused = 0;
         }
     }
 
     text.sections[0].value = String::new();
     text.sections[1].value = String::new();
     text.sections[2].value = String::new();
-    if state.player_dead != 0 || state.paused != 0 {
+    if (run.sim.over() || state.paused != 0) && !editing {
         if keyboard_input.just_pressed(KeyCode::Enter) {
             *state = Default::default();
+            **run = next_run.run();
             return;
         }
-        text.sections[0].value = format!(
-            "LEVEL        {:>9}\nMISSED JUMPS {:>9}",
-            rel_player_level, state.player_miss
-        );
+        text.sections[0].value = results_text(run);
         if *used_debug {
             text.sections[0].value.push_str("\nDEBUG MODE")
         }
@@ -344,24 +602,71 @@ fn draw(
             Color::WHITE.with_alpha(((state.time * 5.0).sin() * 0.5 + 0.5) * 0.85 + 0.15);
         if state.paused != 0 {
             text.sections[2].value = "\n\nPRESS UP OR SPACE TO RESUME".to_string();
-            text.sections[2].style.color =
-                Color::WHITE.with_alpha(((state.time * 5.0).cos() * 0.5 + 0.5) * 0.85 + 0.15);
+        } else if practice {
+            text.sections[2].value = "\n\nPRESS R TO RETRY FROM CHECKPOINT".to_string();
         }
+        text.sections[2].style.color =
+            Color::WHITE.with_alpha(((state.time * 5.0).cos() * 0.5 + 0.5) * 0.85 + 0.15);
     }
 
-    let mut pressed_up = false;
-
-    if keyboard_input.just_pressed(KeyCode::ArrowUp)
-        || keyboard_input.just_pressed(KeyCode::KeyW)
-        || keyboard_input.just_pressed(KeyCode::Space)
-    {
-        if state.paused == 0 {
-            pressed_up = true;
-        } else {
+    let versus = run.second.is_some();
+    let controls = if versus {
+        &VERSUS_CONTROLS[..]
+    } else {
+        std::slice::from_ref(&SOLO_CONTROLS)
+    };
+    let mut inputs = [SimInput::default(); 2];
+    for (input, controls) in inputs.iter_mut().zip(controls) {
+        let pressed = |keys: &[KeyCode], button| {
+            keys.iter().any(|&key| keyboard_input.just_pressed(key))
+                || controls.gamepad
+                    && gamepads.iter().any(|gamepad| {
+                        gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button))
+                    })
+        };
+        input.jump = pressed(controls.jump, GamepadButtonType::South);
+        input.dive = pressed(controls.dive, GamepadButtonType::DPadDown);
+        input.change_color = pressed(controls.change_color, GamepadButtonType::West);
+    }
+    inputs[0].quit = keyboard_input.just_pressed(KeyCode::KeyQ);
+    if state.paused != 0 {
+        if inputs.iter().any(|input| input.jump) && !editing {
             state.paused = 0;
         }
+        inputs = [SimInput::default(); 2];
+    } else if let Some(autoplayer) = &mut game.4 {
+        inputs[0] = SimInput {
+            quit: inputs[0].quit,
+            ..autoplayer.0.input(&run.sim)
+        };
     }
 
+    if *debug_screenshot_on_jump || *draw_debug {
+        state.debug_draw = u32::MAX;
+        debug_text.sections[2].value = String::new();
+    } else {
+        state.debug_draw = 0;
+    }
+
+    // The simulation as it was when the jump was tested, for the debug view.
+    let mut before_jump = None;
+    let mut jump_results = [None; 2];
+    let before: Vec<_> = run.players().map(SoundState::new).collect();
+    // The clock stands still while paused over the remote control, and those frames would only
+    // fill the replay with empty ones.
+    if state.paused == 0 && time.delta_seconds() > 0.0 {
+        if state.debug_draw != 0 {
+            let mut sim = run.sim.clone();
+            sim.step(time.delta_seconds(), SimInput::default());
+            before_jump = Some(sim);
+        }
+        jump_results = match &mut game.3 {
+            Some(playback) if playback.plays(run) => playback.step(run, time.delta_seconds()),
+            _ => run.step_players(time.delta_seconds(), inputs),
+        };
+    }
+    let jump_result = jump_results[0];
+
     if (keyboard_input.just_pressed(KeyCode::KeyP)
         || keyboard_input.just_pressed(KeyCode::Escape)
         || keyboard_input.just_pressed(KeyCode::Tab))
@@ -370,110 +675,206 @@ fn draw(
         state.paused = u32::MAX;
     }
 
-    let jump = pressed_up && state.move_cooldown == 1.0;
+    let sim = &run.sim;
+    state.t = sim.t;
+    state.wavefront = sim.wavefront;
+    state.colors = sim.config.colors as u32;
+    (state.boss_patterns, state.boss_keys) = boss::gpu_patterns();
+    state.seed = sim.config.seed;
+    state.mechanics = sim.config.mechanics.bits();
+    state.arc_scale = sim.arc_scale();
+    state.mods = sim.config.mods.bits();
+    let jump = jump_result.is_some();
 
-    if *debug_screenshot_on_jump || *draw_debug {
-        state.debug_draw = u32::MAX;
-        debug_text.sections[2].value = String::new();
+    state.player_count = run.players().count() as u32;
+    for (i, sim) in run.players().enumerate() {
+        let player = &mut state.players[i];
+        match jump_results[i] {
+            Some(JumpResult::Landed { .. }) => {
+                player.step_anim = 0.0;
+                player.step_dir = 1.0;
+            }
+            Some(JumpResult::Dived { .. }) => {
+                player.step_anim = 0.0;
+                player.step_dir = -1.0;
+            }
+            _ => (),
+        }
+        player.show(sim, PLAYER_TINTS[i]);
+    }
+    if versus && !game.5.split_screen {
+        state.share_field(run);
     } else {
-        state.debug_draw = 0;
+        // Split-screen views follow each player in `split::update_split_views`.
+        state.follow_player(run, 0);
     }
-
-    let ring_thick = (25.0 - (state.player_ring as f32) * 0.2).max(6.0);
-    state.ring_thick = ring_thick;
-
-    {
-        let ring_speed = get_ring_speed(state.player_ring, state.player_sub_ring, 0);
-        let ring_start =
-            pfract(state.t * (ring_speed * (state.player_ring + 1) as f32) + state.player_offset)
-                * TAU;
-        let norm_pos = vec3(-ring_start.sin(), -ring_start.cos(), 0.0);
-        let ring_center_offset = norm_pos * ring_thick * 0.5;
-        let step_anim_offset = norm_pos * ring_thick * -(1.0 - state.step_anim);
-        let position = norm_pos * state.player_ring as f32 * ring_thick - ring_center_offset
-            + step_anim_offset;
-
-        state.position = vec4(position.x, -position.y, 0.0, 0.0);
-    }
-
-    let ring = state.player_ring;
-    let next_ring = ring + 1;
-    let ring_speed = get_ring_speed(ring, state.player_sub_ring, 0);
-    let ring_start = pfract(state.t * (ring_speed * next_ring as f32));
-    let this_p = pfract(ring_start + state.player_offset);
-    let this_n = vec2((this_p * TAU).sin(), (this_p * TAU).cos());
+    let ring_thick = state.ring_thick;
 
     let dbg_thick = ring_thick * 0.4;
-    if state.debug_draw != 0 {
+    if let Some(before_jump) = &before_jump {
+        let this_p = before_jump.player_phase();
+        let next_ring = before_jump.player_ring + 1;
+        let inner_ring = before_jump.player_ring - 1;
         p_line(&mut gizmos, Vec2::ZERO, this_p, dbg_thick, dbg_thick * 10.0);
         p_line(&mut gizmos, Vec2::ZERO, this_p + 0.25, dbg_thick, dbg_thick);
-    }
-
-    let mut missed_all = true;
-    let max_arcs = get_max_arcs(next_ring);
-    if jump || state.debug_draw != 0 {
-        if state.debug_draw != 0 {
-            debug_text.sections[2].value = format!("player: {:.3}\n", this_p);
-        }
-        for sub_ring in 0..max_arcs {
-            let next_speed = get_ring_speed(next_ring, sub_ring, 0);
-            let next_size = get_arc_size(next_ring, sub_ring, 0);
-            let next_p = pfract(state.t * (next_speed * (next_ring + 1) as f32));
-
-            if state.debug_draw != 0 {
+        debug_text.sections[2].value = format!("player: {:.3}\n", this_p);
+        for (ring, label) in [(next_ring, "bar"), (inner_ring, "dive")] {
+            for sub_ring in 0..before_jump.arc_count(ring) {
+                let (next_p, next_size) = before_jump.arc(ring, sub_ring);
                 for t in [next_p, next_p + next_size] {
                     let n = vec2((t * TAU).sin(), (t * TAU).cos());
                     let offset = state.position.xy() * vec2(1.0, -1.0);
                     let ring_thick_offset = n * ring_thick * 0.5;
-                    let p = n * ring_thick * next_ring as f32;
+                    let p = n * ring_thick * ring as f32;
                     let ws_p = p - ring_thick_offset + offset;
                     p_line(&mut gizmos, ws_p, t, dbg_thick * 3.0, dbg_thick * 3.0);
                 }
                 debug_text.sections[2].value.push_str(&format!(
-                    "bar{}: {:.3}..{:.3}\n",
+                    "{}{}: {:.3}..{:.3}\n",
+                    label,
                     sub_ring,
                     next_p,
                     next_p + next_size
                 ))
             }
+        }
+        if jump {
+            let this_n = vec2((this_p * TAU).sin(), (this_p * TAU).cos());
+            let endp = this_n * dbg_thick * 10.0;
+            outlined(&mut gizmos, Vec2::ZERO, endp, MAGENTA);
+            if let Some(JumpResult::Missed | JumpResult::Hazard { .. }) = jump_result {
+                outlined(&mut gizmos, endp + vec2(6., 6.), endp + vec2(-6., -6.), RED);
+                outlined(&mut gizmos, endp + vec2(-6., 6.), endp + vec2(6., -6.), RED);
+            } else {
+                outlined(&mut gizmos, endp, endp + vec2(8., 8.), GREEN);
+                outlined(&mut gizmos, endp, endp + vec2(-5., 5.), GREEN);
+            }
+        }
+    }
 
-            let within = pfract(this_p - next_p);
+    for (i, sim) in run.players().enumerate() {
+        let panning = if versus { VERSUS_PANNING[i] } else { 0.5 };
+        play_jump_sounds(&audio, &audio_assets, panning, jump_results[i], sim.level());
+        before[i].play_changes(&audio, &audio_assets, panning, sim, jump_results[i]);
+        if sim.bosses_passed != before[i].bosses_passed {
+            state.boss_flash = 1.0;
+        }
+    }
+    state.boss_flash = (state.boss_flash - time.delta_seconds() * 0.7).max(0.0);
 
-            if jump && within < next_size {
-                state.player_offset = within;
-                state.player_ring += 1;
-                state.step_anim = 0.0;
-                state.player_sub_ring = sub_ring;
-                missed_all = false;
-                break;
+    if let Some(close_audio) = close_audio {
+        if let Some(close) = audio_instances.get_mut(&close_audio.0) {
+            // The player closest to the wavefront.
+            let inner_ring = run.players().map(|sim| sim.player_ring).min().unwrap();
+            let v = (state.wavefront - inner_ring as f32 + 3.0).clamp(0.0, 3.0) / 3.0;
+            close.set_volume(
+                (v * 0.1) as f64,
+                AudioTween::linear(Duration::from_secs_f32(0.1)),
+            );
+            if run.players().any(|sim| sim.dead) {
+                close.set_volume(0.0, AudioTween::linear(Duration::from_secs_f32(0.01)));
             }
         }
     }
-    if jump {
-        let endp = this_n * dbg_thick * 10.0;
-        if state.debug_draw != 0 {
-            outlined(&mut gizmos, Vec2::ZERO, endp, MAGENTA)
+
+    for player in &mut state.players {
+        player.step_anim = (player.step_anim + time.delta_seconds() * STEP_ANIM_SPEED).min(1.0);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if *debug_screenshot_on_jump && jump && state.debug_draw != 0 {
+        let path = format!("./screenshot_{}_debug.jpg", state.frame);
+        screenshot_manager
+            .save_screenshot_to_disk(window_entity, path)
+            .unwrap();
+    }
+}
+
+/// Offset that puts `sim`'s orb at the middle of the screen, partway through its step animation.
+fn follow(sim: &Sim, ring_thick: f32, step_anim: f32, step_dir: f32) -> Vec4 {
+    let ring_start = sim.player_phase() * TAU;
+    let norm_pos = vec3(-ring_start.sin(), -ring_start.cos(), 0.0);
+    let ring_center_offset = norm_pos * ring_thick * 0.5;
+    let step_anim_offset = norm_pos * ring_thick * -(1.0 - step_anim) * step_dir;
+    let position =
+        norm_pos * sim.player_ring as f32 * ring_thick - ring_center_offset + step_anim_offset;
+    vec4(position.x, -position.y, 0.0, 0.0)
+}
+
+/// The results shown once the run is over, or while paused.
+fn results_text(run: &Run) -> String {
+    let sim = &run.sim;
+    let mut text = String::new();
+    if let Some(second) = &run.second {
+        if sim.over() {
+            text = match run.winner() {
+                Some(player) => format!("PLAYER {} WINS\n", player + 1),
+                None => String::from("DRAW\n"),
+            };
         }
-        if missed_all {
-            if state.debug_draw != 0 {
-                outlined(&mut gizmos, endp + vec2(6., 6.), endp + vec2(-6., -6.), RED);
-                outlined(&mut gizmos, endp + vec2(-6., 6.), endp + vec2(6., -6.), RED);
-            }
-            state.move_cooldown = 0.0;
-            state.player_miss += 1;
-            audio
-                .play(audio_assets.miss_tone.clone())
-                .with_playback_rate(0.9)
-                .with_volume(0.6);
-            audio
-                .play(audio_assets.miss_tone.clone())
-                .with_playback_rate(0.8)
-                .with_volume(0.6);
-        } else {
-            if state.debug_draw != 0 {
-                outlined(&mut gizmos, endp, endp + vec2(8., 8.), GREEN);
-                outlined(&mut gizmos, endp, endp + vec2(-5., 5.), GREEN);
+        text += &format!(
+            "             {:>9}{:>9}\nLEVEL        {:>9}{:>9}\nMISSED JUMPS {:>9}{:>9}",
+            "P1",
+            "P2",
+            sim.level(),
+            second.level(),
+            sim.miss,
+            second.miss
+        );
+    } else {
+        if sim.finished {
+            text = String::from("RESULTS\n");
+        }
+        text += &format!(
+            "LEVEL        {:>9}\nMISSED JUMPS {:>9}\nSCORE        {:>9}\nBEST COMBO   {:>9}\nBOSS RINGS   {:>9}",
+            sim.level(),
+            sim.miss,
+            sim.score.points,
+            sim.score.best_combo,
+            sim.bosses_passed
+        );
+    }
+    text
+}
+
+fn play_jump_sounds(
+    audio: &Audio,
+    audio_assets: &AudioAssets,
+    panning: f64,
+    jump_result: Option<JumpResult>,
+    rel_player_level: i32,
+) {
+    let play = |source: &Handle<AudioSource>, rate: f64, volume: f64| {
+        let mut sound = audio.play(source.clone());
+        sound
+            .with_playback_rate(rate)
+            .with_volume(volume)
+            .with_panning(panning);
+        sound
+    };
+    match jump_result {
+        Some(JumpResult::Missed) => {
+            play(&audio_assets.miss_tone, 0.9, 0.6);
+            play(&audio_assets.miss_tone, 0.8, 0.6);
+        }
+        Some(JumpResult::Shielded) => {
+            play(&audio_assets.miss_tone, 1.2, 0.3).reverse();
+        }
+        Some(JumpResult::Hazard { .. }) => {
+            play(&audio_assets.miss_tone, 1.5, 0.5).reverse();
+            play(
+                &audio_assets.tone,
+                1.059463f64.powi(6) * 0.25,
+                gain_from_db(-4.0) as f64,
+            );
+        }
+        Some(JumpResult::Dived { .. }) => {
+            let vol = gain_from_db(-8.0) as f64;
+            for interval in [0, -5] {
+                play(&audio_assets.tone, 1.059463f64.powi(interval) * 0.25, vol);
             }
+        }
+        Some(JumpResult::Landed { .. }) => {
             let intervals = [0, 1, 3, 5, 7, 8, 11, 12];
             let intervals2 = [0, 1, 3, 5, 7, 8, 12];
             let intervals3 = [0, 3, 5, 7, 8, 12];
@@ -481,84 +882,110 @@ fn draw(
 
             if rel_player_level >= 8 {
                 let interval = intervals2[rel_player_level as usize % intervals2.len()];
-                audio
-                    .play(audio_assets.tone.clone())
-                    .with_playback_rate(1.059463f64.powi(interval) * 0.5)
-                    .with_volume(vol);
+                play(&audio_assets.tone, 1.059463f64.powi(interval) * 0.5, vol);
                 if rel_player_level >= 100 {
-                    audio
-                        .play(audio_assets.tone.clone())
-                        .with_playback_rate(1.059463f64.powi(interval) * 0.5)
-                        .with_volume(vol * 1.5)
-                        .reverse();
+                    play(
+                        &audio_assets.tone,
+                        1.059463f64.powi(interval) * 0.5,
+                        vol * 1.5,
+                    )
+                    .reverse();
                 }
                 let interval = intervals3[(rel_player_level + 4) as usize % intervals3.len()];
-                audio
-                    .play(audio_assets.tone.clone())
-                    .with_playback_rate(1.059463f64.powi(interval))
-                    .with_volume(vol * 0.6);
+                play(&audio_assets.tone, 1.059463f64.powi(interval), vol * 0.6);
             } else {
                 let interval = intervals[rel_player_level as usize % intervals.len()];
-                audio
-                    .play(audio_assets.tone.clone())
-                    .with_playback_rate(1.059463f64.powi(interval) * 0.5)
-                    .with_volume(vol);
+                play(&audio_assets.tone, 1.059463f64.powi(interval) * 0.5, vol);
             }
             if rel_player_level >= 16 {
                 let interval = intervals2[rel_player_level as usize % intervals2.len()];
-                audio
-                    .play(audio_assets.tone.clone())
-                    .with_playback_rate(1.059463f64.powi(interval) * 0.25)
-                    .with_volume(vol * 0.6);
+                play(
+                    &audio_assets.tone,
+                    1.059463f64.powi(interval) * 0.25,
+                    vol * 0.6,
+                );
             }
             if rel_player_level >= 24 {
                 let interval = intervals3[(rel_player_level + 6) as usize % intervals3.len()];
-                audio
-                    .play(audio_assets.tone.clone())
-                    .with_playback_rate(1.059463f64.powi(interval) * 2.0)
-                    .with_volume(vol * 0.11);
+                play(
+                    &audio_assets.tone,
+                    1.059463f64.powi(interval) * 2.0,
+                    vol * 0.11,
+                );
             }
         }
+        None => (),
     }
-
-    if let Some(close_audio) = close_audio {
-        if let Some(close) = audio_instances.get_mut(&close_audio.0) {
-            let v = (state.t * 7.0 - state.player_ring as f32 + 3.0).clamp(0.0, 3.0) / 3.0;
-            close.set_volume(
-                (v * 0.1) as f64,
-                AudioTween::linear(Duration::from_secs_f32(0.1)),
+    if let Some(JumpResult::Landed {
+        power_up: Some(_), ..
+    }) = jump_result
+    {
+        for interval in [0, 4, 7, 12] {
+            play(
+                &audio_assets.tone,
+                1.059463f64.powi(interval),
+                gain_from_db(-14.0) as f64,
             );
-            if state.player_dead != 0 {
-                close.set_volume(0.0, AudioTween::linear(Duration::from_secs_f32(0.01)));
-            }
         }
     }
-
-    state.step_anim = (state.step_anim + time.delta_seconds() * STEP_ANIM_SPEED).min(1.0);
-    state.move_cooldown =
-        (state.move_cooldown + time.delta_seconds() * COOLDOWN_ANIM_SPEED).min(1.0);
-
-    #[cfg(not(target_arch = "wasm32"))]
-    if *debug_screenshot_on_jump && jump && state.debug_draw != 0 {
-        let path = format!("./screenshot_{}_debug.jpg", state.frame);
-        screenshot_manager
-            .save_screenshot_to_disk(window_entity, path)
-            .unwrap();
-    }
 }
 
-fn get_max_arcs(ring: u32) -> u32 {
-    ((ring as i32 - 16).max(0) as u32 / 4).clamp(2, 6)
+/// What a player's sounds depend on from before a frame, to tell what changed during it.
+struct SoundState {
+    lives: u32,
+    player_color: u32,
+    bosses_passed: u32,
 }
 
-fn get_arc_size(ring: u32, level: u32, seed: u32) -> f32 {
-    (hash_noise(ring, level, seed) * 0.2 + 0.2) / (((ring + 1) as f32) * 0.13 + 2.0)
-}
+impl SoundState {
+    fn new(sim: &Sim) -> Self {
+        Self {
+            lives: sim.lives,
+            player_color: sim.player_color,
+            bosses_passed: sim.bosses_passed,
+        }
+    }
 
-fn get_ring_speed(ring: u32, level: u32, seed: u32) -> f32 {
-    ((hash_noise(ring, level, seed) * 1.0 + 0.8) / ((ring + 1) as f32))
-        * (1.0 + ring as f32 * 0.0)
-        * if ring % 2 == 0 { -1.0 } else { 1.0 }
+    /// Sounds for passing a boss, changing color and losing a life.
+    fn play_changes(
+        &self,
+        audio: &Audio,
+        audio_assets: &AudioAssets,
+        panning: f64,
+        sim: &Sim,
+        jump_result: Option<JumpResult>,
+    ) {
+        if sim.bosses_passed != self.bosses_passed {
+            for (interval, rate) in [(0, 0.25), (7, 0.25), (12, 0.5), (16, 0.5)] {
+                audio
+                    .play(audio_assets.tone.clone())
+                    .with_playback_rate(1.059463f64.powi(interval) * rate)
+                    .with_volume(gain_from_db(-6.0) as f64)
+                    .with_panning(panning);
+            }
+            audio
+                .play(audio_assets.miss_tone.clone())
+                .with_playback_rate(0.5)
+                .with_volume(0.4)
+                .with_panning(panning)
+                .reverse();
+        }
+        if sim.player_color != self.player_color {
+            audio
+                .play(audio_assets.tone.clone())
+                .with_playback_rate(1.059463f64.powi(sim.player_color as i32 * 4) * 2.0)
+                .with_volume(gain_from_db(-16.0) as f64)
+                .with_panning(panning);
+        }
+        let hazard = matches!(jump_result, Some(JumpResult::Hazard { .. }));
+        if sim.lives < self.lives && !sim.dead && !hazard {
+            audio
+                .play(audio_assets.miss_tone.clone())
+                .with_playback_rate(0.5)
+                .with_volume(0.8)
+                .with_panning(panning);
+        }
+    }
 }
 
 fn p_line(gizmos: &mut Gizmos, pos: Vec2, t: f32, start: f32, end: f32) {
@@ -578,9 +1005,12 @@ fn outlined(gizmos: &mut Gizmos, a: Vec2, b: Vec2, color: Color) {
 }
 
 #[derive(Clone, ShaderType, Default, Debug)]
+#[cfg_attr(feature = "remote", derive(serde::Serialize))]
 struct GpuState {
     position: Vec4,
     resolution: Vec4,
+    /// Physical position of the view's top left corner in the window.
+    viewport: Vec4,
 
     scale_factor: f32,
     ring_thick: f32,
@@ -592,7 +1022,7 @@ struct GpuState {
     player_offset: f32,
     player_color_idx: u32,
 
-    step_anim: f32,
+    player_count: u32,
     move_cooldown: f32,
     player_sub_ring: u32,
     player_dead: u32,
@@ -600,11 +1030,136 @@ struct GpuState {
     player_miss: u32,
     paused: u32,
     debug_draw: u32,
-    spare2: u32,
+    seed: u32,
+
+    wavefront: f32,
+    invulnerable: f32,
+    colors: u32,
+
+    highest_ring: u32,
+    shield: u32,
+    slow_time: f32,
+    boss_flash: f32,
+
+    level_first_ring: u32,
+    level_ring_count: u32,
+    hue: f32,
+    hue_speed: f32,
+
+    mechanics: u32,
+    arc_scale: f32,
+    mods: u32,
+    palette_arcs: Vec4,
+    palette_next_arcs: Vec4,
+    palette_rings: Vec4,
+    /// The first `player_count` are drawn.
+    players: [GpuPlayer; 2],
+
+    boss_patterns: [Vec4; 3],
+    boss_keys: [Vec4; 3 * boss::MAX_BOSS_KEYS],
+    level_counts: [UVec4; level::LEVEL_WINDOW / 4],
+    level_arcs: [[Vec4; level::MAX_LEVEL_ARCS]; level::LEVEL_WINDOW],
+}
+
+impl GpuPlayer {
+    fn show(&mut self, sim: &Sim, tint: Vec4) {
+        self.tint = tint;
+        self.ring = sim.player_ring;
+        self.color_idx = sim.player_color;
+        self.dead = sim.dead as u32;
+        self.shield = sim.shield as u32;
+        self.move_cooldown = sim.move_cooldown;
+        self.invulnerable = sim.invulnerable;
+    }
+}
+
+impl GpuState {
+    /// Forgets what's left of the last run's pause and animations, for a run started outside of
+    /// [`draw`]. Everything else is rebuilt every frame.
+    #[cfg(feature = "remote")]
+    fn new_run(&mut self) {
+        self.paused = 0;
+        self.boss_flash = 0.0;
+        self.players = default();
+    }
+
+    /// Centers the view on a player's orb, with rings sized for how far out they are.
+    fn follow_player(&mut self, run: &Run, player: usize) {
+        let sim = run.players().nth(player).unwrap();
+        self.player_ring = sim.player_ring;
+        self.player_offset = sim.player_offset;
+        self.player_sub_ring = sim.player_sub_ring;
+        self.player_dead = sim.dead as u32;
+        self.player_miss = sim.miss;
+        self.move_cooldown = sim.move_cooldown;
+        self.invulnerable = sim.invulnerable;
+        self.player_color_idx = sim.player_color;
+        self.highest_ring = sim.highest_ring;
+        self.shield = sim.shield as u32;
+        self.slow_time = sim.slow_time;
+        self.level_ring_count = 0;
+        if let Some(level) = &sim.level {
+            (
+                self.level_first_ring,
+                self.level_ring_count,
+                self.level_counts,
+                self.level_arcs,
+            ) = level.gpu_window(sim.player_ring);
+        }
+
+        self.size_rings(run, (25.0 - (self.player_ring as f32) * 0.2).max(6.0));
+        self.position = self.players[player].position;
+    }
+
+    /// Centers the view on the field with both versus orbs on it, which is what's shown
+    /// unless [`Settings::split_screen`] is on.
+    fn share_field(&mut self, run: &Run) {
+        self.follow_player(run, 0);
+        let outer_ring = run.players().map(|sim| sim.player_ring).max().unwrap();
+        if let Some(level) = &run.sim.level {
+            // The outer player's window, since the other one is busy further in anyway.
+            (
+                self.level_first_ring,
+                self.level_ring_count,
+                self.level_counts,
+                self.level_arcs,
+            ) = level.gpu_window(outer_ring);
+        }
+        // Both orbs and a few rings past the outer one need to fit on screen.
+        let half_screen = self.resolution.z.min(self.resolution.w) * 0.5;
+        let ring_thick = (25.0 - (outer_ring as f32) * 0.2).max(6.0);
+        self.size_rings(run, ring_thick.min(half_screen / (outer_ring + 3) as f32));
+        self.position = Vec4::ZERO;
+    }
+
+    fn size_rings(&mut self, run: &Run, ring_thick: f32) {
+        self.ring_thick = ring_thick;
+        for (gpu_player, sim) in self.players.iter_mut().zip(run.players()) {
+            gpu_player.position =
+                follow(sim, ring_thick, gpu_player.step_anim, gpu_player.step_dir);
+        }
+    }
+}
+
+/// A player's orb.
+#[derive(Clone, ShaderType, Default, Debug)]
+#[cfg_attr(feature = "remote", derive(serde::Serialize))]
+struct GpuPlayer {
+    /// What [`GpuState::position`] is set to to follow this player, see [`follow`].
+    position: Vec4,
+    tint: Vec4,
+    ring: u32,
+    color_idx: u32,
+    dead: u32,
+    shield: u32,
+    move_cooldown: f32,
+    invulnerable: f32,
+    step_anim: f32,
+    step_dir: f32,
 }
 
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone, Default)]
-struct DataMaterial {
+pub struct DataMaterial {
     #[uniform(0)]
     state: GpuState,
 }
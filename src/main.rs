@@ -1,6 +1,102 @@
+//! `Sol [OPTIONS]` runs the game. Options override the saved settings, and are listed in `USAGE`.
+//!
+//! `--headless --replay FILE` plays the replay as fast as it can and prints the results. Hot
+//! reloading builds take no options.
+#![cfg_attr(feature = "hot_reload", allow(unused))]
+
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use lib_Sol::replay::Replay;
+use lib_Sol::sim::{Difficulty, GameMode};
+use lib_Sol::AppOptions;
+
+/// Skill of the bot `--autoplay` lets play, see [`lib_Sol::autoplay::Autoplay::skill`].
+const AUTOPLAY_SKILL: f32 = 0.9;
+
+/// Printed along with what's wrong with the options.
+const USAGE: &str = "\
+usage: Sol [OPTIONS]
+  --seed SEED           seed of every run
+  --start-level LEVEL   practice from LEVEL
+  --difficulty NAME     easy, normal or hard
+  --windowed            start in a window, the default
+  --fullscreen          start in borderless fullscreen
+  --mute                start with the sound off
+  --replay FILE         play back a replay, like the ones saved in replays/
+  --record FILE         save the replay of each run to FILE once it's over
+  --autoplay            let a bot play
+  --skill SKILL         let a bot of SKILL, from 0 to 1, play
+  --headless            no window or sound, needs a replay or a bot to play";
+
 fn main() {
     #[cfg(feature = "hot_reload")]
     ridiculous_bevy_hot_reloading::dyn_load_main("main", None);
     #[cfg(not(feature = "hot_reload"))]
-    lib_Sol::app();
+    lib_Sol::build_app(&options()).run();
+}
+
+fn options() -> AppOptions {
+    let mut options = AppOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = Some(parse(args.next(), "--seed takes a number")),
+            "--start-level" => {
+                options.start_level = Some(parse(args.next(), "--start-level takes a level"))
+            }
+            "--difficulty" => options.difficulty = Some(difficulty(args.next())),
+            "--windowed" => options.fullscreen = false,
+            "--fullscreen" => options.fullscreen = true,
+            "--mute" => options.mute = true,
+            "--replay" => options.replay = Some(replay(args.next())),
+            "--record" => options.record = Some(parse(args.next(), "--record takes a file")),
+            "--autoplay" => {
+                options.autoplay.get_or_insert(AUTOPLAY_SKILL);
+            }
+            "--skill" => options.autoplay = Some(parse(args.next(), "--skill takes 0 to 1")),
+            "--headless" => options.headless = true,
+            _ => fail(format!("unknown option {arg}")),
+        }
+    }
+    if options.headless && options.replay.is_none() && options.autoplay.is_none() {
+        fail("--headless needs --replay, --autoplay or --skill, or no one plays");
+    }
+    options
+}
+
+fn difficulty(name: Option<String>) -> Difficulty {
+    let name: String = parse(name, "--difficulty takes easy, normal or hard");
+    [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard]
+        .into_iter()
+        .find(|difficulty| difficulty.name().eq_ignore_ascii_case(&name))
+        .unwrap_or_else(|| fail(format!("unknown difficulty {name}")))
+}
+
+fn replay(path: Option<String>) -> Replay {
+    let path: PathBuf = parse(path, "--replay takes a file");
+    let bytes = std::fs::read(&path)
+        .unwrap_or_else(|e| fail(format!("can't read replay {}: {e}", path.display())));
+    let replay = Replay::from_bytes(&bytes)
+        .unwrap_or_else(|e| fail(format!("can't load replay {}: {e}", path.display())));
+    // Neither is in the replay: the second player's input, or the level's arcs.
+    if replay.config.mode == GameMode::Versus {
+        fail("versus replays only have the first player, so they can't be played back");
+    }
+    if replay.config.level.is_some() {
+        fail("replays on hand-made levels and campaign chapters can't be played back");
+    }
+    replay
+}
+
+fn parse<T: std::str::FromStr>(value: Option<String>, usage: &str) -> T {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| fail(usage))
+}
+
+/// Reports a mistake in the options and quits, without starting the game.
+fn fail(message: impl Display) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(2)
 }
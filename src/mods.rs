@@ -0,0 +1,136 @@
+//! Run modifiers that make a run harder in exchange for a score bonus. Mods are part of the
+//! [`crate::sim::RunConfig`], so replays and leaderboard entries keep them, but runs with and
+//! without mods share leaderboards since the bonus evens them out.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mod {
+    /// Arcs fade out as they come up on the player's next ring. Only changes the shader.
+    Hidden,
+    /// Every arc turns the other way.
+    Mirror,
+    /// The game runs at 1.5x speed.
+    DoubleTime,
+    /// Arcs are a quarter smaller.
+    Narrow,
+    /// The orb doesn't flash while a miss locks it out. Only changes the shader.
+    NoCooldownFlash,
+}
+
+impl Mod {
+    pub const ALL: [Mod; 5] = [
+        Mod::Hidden,
+        Mod::Mirror,
+        Mod::DoubleTime,
+        Mod::Narrow,
+        Mod::NoCooldownFlash,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mod::Hidden => "HIDDEN",
+            Mod::Mirror => "MIRROR",
+            Mod::DoubleTime => "DOUBLE TIME",
+            Mod::Narrow => "NARROW",
+            Mod::NoCooldownFlash => "NO COOLDOWN FLASH",
+        }
+    }
+
+    /// Short name for the leaderboards.
+    pub fn code(self) -> &'static str {
+        match self {
+            Mod::Hidden => "HD",
+            Mod::Mirror => "MR",
+            Mod::DoubleTime => "DT",
+            Mod::Narrow => "NR",
+            Mod::NoCooldownFlash => "NF",
+        }
+    }
+
+    /// Percent added to the points of every landing.
+    pub fn score_bonus(self) -> u32 {
+        match self {
+            Mod::Hidden => 8,
+            Mod::Mirror => 2,
+            Mod::DoubleTime => 20,
+            Mod::Narrow => 12,
+            Mod::NoCooldownFlash => 4,
+        }
+    }
+
+    /// Bit in [`Mods::bits`], matching the `MOD_` constants in the shader.
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mods {
+    pub hidden: bool,
+    pub mirror: bool,
+    pub double_time: bool,
+    pub narrow: bool,
+    pub no_cooldown_flash: bool,
+}
+
+impl Mods {
+    pub fn get(&self, m: Mod) -> bool {
+        match m {
+            Mod::Hidden => self.hidden,
+            Mod::Mirror => self.mirror,
+            Mod::DoubleTime => self.double_time,
+            Mod::Narrow => self.narrow,
+            Mod::NoCooldownFlash => self.no_cooldown_flash,
+        }
+    }
+
+    pub fn toggle(&mut self, m: Mod) {
+        let on = match m {
+            Mod::Hidden => &mut self.hidden,
+            Mod::Mirror => &mut self.mirror,
+            Mod::DoubleTime => &mut self.double_time,
+            Mod::Narrow => &mut self.narrow,
+            Mod::NoCooldownFlash => &mut self.no_cooldown_flash,
+        };
+        *on = !*on;
+    }
+
+    pub fn enabled(self) -> impl Iterator<Item = Mod> {
+        Mod::ALL.into_iter().filter(move |&m| self.get(m))
+    }
+
+    /// Packed for the shader.
+    pub fn bits(self) -> u32 {
+        self.enabled().fold(0, |bits, m| bits | m.bit())
+    }
+
+    /// Percent of the usual points each landing is worth. Bonuses add up.
+    pub fn score_percent(self) -> u32 {
+        100 + self.enabled().map(Mod::score_bonus).sum::<u32>()
+    }
+
+    /// Multiplier on the game speed.
+    pub fn speed(self) -> f32 {
+        if self.double_time {
+            1.5
+        } else {
+            1.0
+        }
+    }
+
+    /// Multiplier on every arc's size.
+    pub fn arc_size(self) -> f32 {
+        if self.narrow {
+            0.75
+        } else {
+            1.0
+        }
+    }
+
+    /// Codes of the enabled mods, like `+HD+DT`. Empty without mods.
+    pub fn codes(self) -> String {
+        self.enabled().map(|m| format!("+{}", m.code())).collect()
+    }
+}
@@ -0,0 +1,504 @@
+//! Online leaderboard. Runs are submitted with their replay, which the server plays back through
+//! [`Sim`](crate::sim::Sim) to check the claimed score before ranking it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool, Task};
+use serde::{Deserialize, Serialize};
+
+use crate::leaderboard::{GameOver, Leaderboard};
+use crate::mods::Mods;
+use crate::replay::Replay;
+use crate::settings::Settings;
+use crate::sim::{Run, RunConfig};
+
+pub const RANKINGS_LIMIT: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Submission {
+    pub initials: String,
+    pub score: u64,
+    /// Hex encoded replay file.
+    pub replay: String,
+}
+
+impl Submission {
+    pub fn new(initials: &str, score: u64, replay: &Replay) -> Self {
+        Self {
+            initials: initials.to_string(),
+            score,
+            replay: replay
+                .to_bytes()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+        }
+    }
+
+    pub fn decode_replay(&self) -> Result<Replay, String> {
+        if self.replay.len() % 2 != 0 {
+            return Err(String::from("odd length replay"));
+        }
+        let bytes = (0..self.replay.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&self.replay[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        Replay::from_bytes(&bytes).map_err(|e| e.to_string())
+    }
+
+    /// Plays the replay back and checks it matches the claimed score.
+    /// Returns the ranked entry on success, with `rank` left at 0.
+    pub fn verify(&self) -> Result<(RunConfig, OnlineEntry), String> {
+        let replay = self.decode_replay()?;
+        if !replay.config.mode.is_ranked() {
+            return Err(format!("{} runs aren't ranked", replay.config.mode.name()));
+        }
+        if replay.config.level.is_some() {
+            return Err(String::from(
+                "runs on hand-made levels aren't ranked online",
+            ));
+        }
+        let sim = replay.play();
+        if !sim.over() {
+            return Err(String::from("replay ends before the run is over"));
+        }
+        if !replay.adjustments_match(&sim) {
+            return Err(String::from(
+                "replay's adaptive difficulty adjustments don't play back the same",
+            ));
+        }
+        if sim.score.points != self.score {
+            return Err(format!(
+                "claimed score {} but replay scores {}",
+                self.score, sim.score.points
+            ));
+        }
+        let initials = self.initials.chars().take(3).collect();
+        Ok((
+            replay.config,
+            OnlineEntry {
+                rank: 0,
+                initials,
+                score: sim.score.points,
+                level: sim.level(),
+                misses: sim.miss,
+                duration: sim.run_time,
+                mods: replay.config.mods,
+            },
+        ))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct OnlineEntry {
+    pub rank: u32,
+    pub initials: String,
+    pub score: u64,
+    pub level: i32,
+    pub misses: u32,
+    pub duration: f32,
+    #[serde(default)]
+    pub mods: Mods,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubmitResponse {
+    pub rank: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RankingsRequest {
+    pub config: RunConfig,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OnlineError {
+    /// No server configured, or it couldn't be reached.
+    Unavailable(String),
+    /// The server refused the submission.
+    Rejected(String),
+    BadResponse(String),
+}
+
+impl fmt::Display for OnlineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OnlineError::Unavailable(e) => write!(f, "offline ({e})"),
+            OnlineError::Rejected(e) => write!(f, "rejected ({e})"),
+            OnlineError::BadResponse(e) => write!(f, "bad response ({e})"),
+        }
+    }
+}
+
+impl std::error::Error for OnlineError {}
+
+/// Calls block, so the game runs them on the [`IoTaskPool`].
+pub trait LeaderboardService: Send + Sync {
+    fn submit(&self, submission: &Submission) -> Result<SubmitResponse, OnlineError>;
+    fn rankings(&self, request: &RankingsRequest) -> Result<Vec<OnlineEntry>, OnlineError>;
+}
+
+pub struct OfflineLeaderboard;
+
+impl LeaderboardService for OfflineLeaderboard {
+    fn submit(&self, _submission: &Submission) -> Result<SubmitResponse, OnlineError> {
+        Err(OnlineError::Unavailable(String::from("no server")))
+    }
+
+    fn rankings(&self, _request: &RankingsRequest) -> Result<Vec<OnlineEntry>, OnlineError> {
+        Err(OnlineError::Unavailable(String::from("no server")))
+    }
+}
+
+/// Talks plain HTTP/1.1 to `addr` (`host:port`).
+#[cfg(not(target_arch = "wasm32"))]
+pub struct HttpLeaderboard {
+    pub addr: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpLeaderboard {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into() }
+    }
+
+    fn post<T: Serialize, R: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<R, OnlineError> {
+        let body = serde_json::to_vec(body).unwrap();
+        let (status, body) = http::post(&self.addr, path, &body)
+            .map_err(|e| OnlineError::Unavailable(e.to_string()))?;
+        match status {
+            200 => {
+                serde_json::from_slice(&body).map_err(|e| OnlineError::BadResponse(e.to_string()))
+            }
+            422 => Err(OnlineError::Rejected(
+                String::from_utf8_lossy(&body).into_owned(),
+            )),
+            _ => Err(OnlineError::BadResponse(format!(
+                "status {status}: {}",
+                String::from_utf8_lossy(&body).trim()
+            ))),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LeaderboardService for HttpLeaderboard {
+    fn submit(&self, submission: &Submission) -> Result<SubmitResponse, OnlineError> {
+        self.post("/runs", submission)
+    }
+
+    fn rankings(&self, request: &RankingsRequest) -> Result<Vec<OnlineEntry>, OnlineError> {
+        self.post("/rankings", request)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod http {
+    //! Just enough HTTP/1.1 for the leaderboard client and [`MockServer`].
+
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn post(addr: &str, path: &str, body: &[u8]) -> io::Result<(u16, Vec<u8>)> {
+        let socket_addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let mut stream = TcpStream::connect_timeout(&socket_addr, TIMEOUT)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        write!(
+            stream,
+            "POST {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(body)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line)?;
+        let status = parse_status(&status_line)
+            .ok_or_else(|| invalid_data(format!("bad status line {:?}", status_line.trim_end())))?;
+        let body = read_body(&mut reader)?;
+        Ok((status, body))
+    }
+
+    /// The status code of an `HTTP/1.x` status line.
+    fn parse_status(line: &str) -> Option<u16> {
+        let mut parts = line.split_whitespace();
+        if !parts.next()?.starts_with("HTTP/1.") {
+            return None;
+        }
+        let status = parts.next()?;
+        if status.len() != 3 {
+            return None;
+        }
+        status.parse().ok()
+    }
+
+    fn invalid_data(message: impl Into<String>) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message.into())
+    }
+
+    /// Reads headers up to the blank line, then `Content-Length` bytes of body. Chunked bodies
+    /// and ones without a length aren't supported.
+    fn read_body(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+        let mut content_length = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            if line == "\r\n" {
+                break;
+            }
+            let Some((name, value)) = line.split_once(':') else {
+                continue;
+            };
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                return Err(invalid_data(format!(
+                    "unsupported transfer encoding {}",
+                    value.trim()
+                )));
+            }
+            if name.eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse();
+                content_length = Some(length.map_err(|_| invalid_data("bad Content-Length"))?);
+            }
+        }
+        let content_length = content_length.ok_or_else(|| invalid_data("no Content-Length"))?;
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+
+    /// In-process stand-in for the leaderboard server, listening on localhost.
+    /// Verifies submissions the same way the real server does.
+    pub struct MockServer {
+        pub addr: String,
+        runs: Arc<Mutex<Vec<(RunConfig, OnlineEntry)>>>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl MockServer {
+        pub fn start() -> io::Result<Self> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let addr = listener.local_addr()?.to_string();
+            let runs = Arc::new(Mutex::new(Vec::new()));
+            let stop = Arc::new(AtomicBool::new(false));
+            let (thread_runs, thread_stop) = (runs.clone(), stop.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if thread_stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let _ = handle(stream, &thread_runs);
+                    }
+                }
+            });
+            Ok(Self { addr, runs, stop })
+        }
+
+        /// Every accepted run, in submission order.
+        pub fn runs(&self) -> Vec<(RunConfig, OnlineEntry)> {
+            self.runs.lock().unwrap().clone()
+        }
+    }
+
+    impl Drop for MockServer {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            // Wake the accept loop so it sees the flag.
+            let _ = TcpStream::connect(&self.addr);
+        }
+    }
+
+    fn handle(stream: TcpStream, runs: &Mutex<Vec<(RunConfig, OnlineEntry)>>) -> io::Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let body = read_body(&mut reader)?;
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+
+        let (status, response) = match path {
+            "/runs" => match serde_json::from_slice::<Submission>(&body)
+                .map_err(|e| e.to_string())
+                .and_then(|s| s.verify())
+            {
+                Ok((config, entry)) => {
+                    let mut runs = runs.lock().unwrap();
+                    let rank = 1 + runs
+                        .iter()
+                        .filter(|(c, e)| *c == config.ranked_as() && e.score >= entry.score)
+                        .count() as u32;
+                    runs.push((config.ranked_as(), entry));
+                    (200, serde_json::to_vec(&SubmitResponse { rank }).unwrap())
+                }
+                Err(e) => (422, e.into_bytes()),
+            },
+            "/rankings" => match serde_json::from_slice::<RankingsRequest>(&body) {
+                Ok(request) => {
+                    let runs = runs.lock().unwrap();
+                    let mut entries: Vec<_> = runs
+                        .iter()
+                        .filter(|(c, _)| *c == request.config.ranked_as())
+                        .map(|(_, e)| e.clone())
+                        .collect();
+                    entries.sort_by_key(|e| std::cmp::Reverse(e.score));
+                    entries.truncate(request.limit);
+                    for (i, entry) in entries.iter_mut().enumerate() {
+                        entry.rank = i as u32 + 1;
+                    }
+                    (200, serde_json::to_vec(&entries).unwrap())
+                }
+                Err(e) => (400, e.to_string().into_bytes()),
+            },
+            _ => (404, b"not found".to_vec()),
+        };
+
+        let mut stream = reader.into_inner();
+        write!(
+            stream,
+            "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            if status == 200 { "OK" } else { "Error" },
+            response.len()
+        )?;
+        stream.write_all(&response)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use http::MockServer;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum OnlineStatus {
+    #[default]
+    Idle,
+    Busy,
+    Ranked(u32),
+    Failed(OnlineError),
+}
+
+struct OnlineUpdate {
+    run_id: u64,
+    status: OnlineStatus,
+    rankings: Vec<OnlineEntry>,
+}
+
+#[derive(Resource)]
+pub struct OnlineLeaderboard {
+    /// `None` without a server configured, which leaves the online leaderboard out entirely.
+    service: Option<Arc<dyn LeaderboardService>>,
+    pub status: OnlineStatus,
+    pub rankings: Vec<OnlineEntry>,
+    submitted: Option<u64>,
+    task: Option<Task<OnlineUpdate>>,
+}
+
+impl OnlineLeaderboard {
+    pub fn new(service: Arc<dyn LeaderboardService>) -> Self {
+        Self {
+            service: Some(service),
+            ..Self::disabled()
+        }
+    }
+
+    /// Submits nothing and shows no online line, for when no server is configured.
+    pub fn disabled() -> Self {
+        Self {
+            service: None,
+            status: OnlineStatus::Idle,
+            rankings: Vec::new(),
+            submitted: None,
+            task: None,
+        }
+    }
+
+    pub(crate) fn from_settings(settings: &Settings) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(addr) = &settings.leaderboard_server {
+            return Self::new(Arc::new(HttpLeaderboard::new(addr.clone())));
+        }
+        let _ = settings;
+        Self::disabled()
+    }
+}
+
+/// Submits the finished run once its initials are in, then fetches the rankings for its config.
+pub(crate) fn sync_online(
+    run: Res<Run>,
+    leaderboard: Res<Leaderboard>,
+    game_over: Res<GameOver>,
+    mut online: ResMut<OnlineLeaderboard>,
+) {
+    let Some(service) = online.service.clone() else {
+        return;
+    };
+    if let Some(task) = &mut online.task {
+        if let Some(update) = block_on(future::poll_once(task)) {
+            online.task = None;
+            if game_over.run_id == Some(update.run_id) {
+                online.status = update.status;
+                online.rankings = update.rankings;
+            }
+        }
+    }
+
+    let Some(run_id) = game_over.run_id else {
+        if online.submitted.is_some() {
+            online.submitted = None;
+            online.status = OnlineStatus::Idle;
+            online.rankings.clear();
+        }
+        return;
+    };
+    if game_over.initials.is_some()
+        || online.submitted == Some(run_id)
+        || online.task.is_some()
+        || run.sim.config.level.is_some()
+    {
+        return;
+    }
+    let Some(entry) = leaderboard.history.iter().find(|e| e.id == run_id) else {
+        return;
+    };
+
+    let submission = Submission::new(&entry.initials, entry.score, &run.replay);
+    let request = RankingsRequest {
+        config: run.sim.config,
+        limit: RANKINGS_LIMIT,
+    };
+    online.submitted = Some(run_id);
+    online.status = OnlineStatus::Busy;
+    online.task = Some(IoTaskPool::get().spawn(async move {
+        let status = match service.submit(&submission) {
+            Ok(response) => OnlineStatus::Ranked(response.rank),
+            Err(e) => OnlineStatus::Failed(e),
+        };
+        let rankings = service.rankings(&request).unwrap_or_default();
+        OnlineUpdate {
+            run_id,
+            status,
+            rankings,
+        }
+    }));
+}
@@ -0,0 +1,357 @@
+//! Progression for modes that don't have leaderboards.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::StandIns;
+use crate::sim::{GameMode, Run, RunConfig, Sim};
+use crate::storage;
+
+const PROGRESS_FILE: &str = "progress.ron";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ZenStats {
+    pub runs: u32,
+    pub best_level: i32,
+    pub best_score: u64,
+    pub rings_climbed: u64,
+    /// Simulated seconds over every run.
+    pub time_played: f32,
+}
+
+/// Time attack personal best for one run config, and so one seed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeAttackRecord {
+    pub config: RunConfig,
+    pub attempts: u32,
+    /// Splits of the fastest finished run.
+    pub personal_best: Vec<f32>,
+    /// Fastest time for each segment over every attempt.
+    pub best_segments: Vec<f32>,
+}
+
+impl TimeAttackRecord {
+    pub fn new(config: RunConfig) -> Self {
+        Self {
+            config,
+            attempts: 0,
+            personal_best: Vec::new(),
+            best_segments: Vec::new(),
+        }
+    }
+
+    /// Adds an attempt, keeping its splits if it's a new personal best.
+    pub fn add(&mut self, splits: &[f32], finished: bool) {
+        self.attempts += 1;
+        let mut previous = 0.0;
+        for (i, &split) in splits.iter().enumerate() {
+            let segment = split - previous;
+            previous = split;
+            match self.best_segments.get_mut(i) {
+                Some(best) => *best = best.min(segment),
+                None => self.best_segments.push(segment),
+            }
+        }
+        let is_best = finished
+            && self
+                .personal_best
+                .last()
+                .is_none_or(|&best| splits.last().is_some_and(|&time| time < best));
+        if is_best {
+            self.personal_best = splits.to_vec();
+        }
+    }
+
+    /// The record as a LiveSplit splits file, timed in game time.
+    pub fn to_lss(&self) -> String {
+        let config = &self.config;
+        let mut lss = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        lss.push_str("<Run version=\"1.7.0\">\n  <GameIcon />\n  <GameName>Sol</GameName>\n");
+        lss.push_str(&format!(
+            "  <CategoryName>Level {} {} Seed {}</CategoryName>\n",
+            config.time_attack_target,
+            config.difficulty.name(),
+            config.seed
+        ));
+        lss.push_str("  <Offset>00:00:00</Offset>\n");
+        lss.push_str(&format!(
+            "  <AttemptCount>{}</AttemptCount>\n  <AttemptHistory />\n  <Segments>\n",
+            self.attempts
+        ));
+        for i in 0..config.time_attack_splits() {
+            let game_time = |time: Option<&f32>| match time {
+                Some(&time) => format!("<GameTime>{}</GameTime>", lss_time(time)),
+                None => String::new(),
+            };
+            let level = config.split_level(i);
+            lss.push_str("    <Segment>\n");
+            lss.push_str(&format!(
+                "      <Name>Level {level}</Name>\n      <Icon />\n"
+            ));
+            lss.push_str("      <SplitTimes>\n        <SplitTime name=\"Personal Best\">");
+            lss.push_str(&game_time(self.personal_best.get(i)));
+            lss.push_str("</SplitTime>\n      </SplitTimes>\n      <BestSegmentTime>");
+            lss.push_str(&game_time(self.best_segments.get(i)));
+            lss.push_str("</BestSegmentTime>\n      <SegmentHistory />\n    </Segment>\n");
+        }
+        lss.push_str("  </Segments>\n  <AutoSplitterSettings />\n</Run>\n");
+        lss
+    }
+}
+
+/// Boss rings passed in every mode but practice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BossStats {
+    pub passed: u64,
+    pub best_run: u32,
+}
+
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    pub zen: ZenStats,
+    pub time_attack: Vec<TimeAttackRecord>,
+    pub bosses: BossStats,
+    /// Stages cleared in each campaign chapter, by chapter name.
+    pub campaign: BTreeMap<String, usize>,
+}
+
+/// On-disk format. Older versions get migrated to the current [`Progress`] on load, so a format
+/// change never silently resets it.
+#[derive(Serialize, Deserialize)]
+pub enum ProgressFile {
+    V1(Progress),
+}
+
+impl ProgressFile {
+    pub fn migrate(self) -> Progress {
+        match self {
+            ProgressFile::V1(progress) => progress,
+        }
+    }
+}
+
+impl Progress {
+    pub fn load() -> Self {
+        storage::load::<ProgressFile>(PROGRESS_FILE)
+            .map(ProgressFile::migrate)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(PROGRESS_FILE, &ProgressFile::V1(self.clone()));
+    }
+
+    pub fn time_attack(&self, config: &RunConfig) -> Option<&TimeAttackRecord> {
+        self.time_attack.iter().find(|r| r.config == *config)
+    }
+
+    fn time_attack_mut(&mut self, config: &RunConfig) -> &mut TimeAttackRecord {
+        let i = match self.time_attack.iter().position(|r| r.config == *config) {
+            Some(i) => i,
+            None => {
+                self.time_attack.push(TimeAttackRecord::new(*config));
+                self.time_attack.len() - 1
+            }
+        };
+        &mut self.time_attack[i]
+    }
+}
+
+/// Formats seconds as `m:ss.ss`.
+pub fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:05.2}", minutes, seconds - minutes * 60.0)
+}
+
+/// Formats seconds as LiveSplit's `hh:mm:ss.fffffff`.
+fn lss_time(seconds: f32) -> String {
+    let ticks = (seconds as f64 * 1e7).round() as u64;
+    let secs = ticks / 10_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:07}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        ticks % 10_000_000
+    )
+}
+
+fn splits_file(config: &RunConfig) -> String {
+    format!(
+        "splits/sol-{}-seed{}.lss",
+        config.difficulty.name().to_lowercase(),
+        config.seed
+    )
+}
+
+#[derive(Component)]
+pub struct ProgressText;
+
+pub fn setup_progress_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            right: Val::Px(12.0),
+            ..default()
+        }),
+        ProgressText,
+    ));
+}
+
+/// Adds the run to its mode's stats once it's over, unless a replay or the bot played it.
+pub(crate) fn record_progress(
+    run: Res<Run>,
+    stand_ins: StandIns,
+    mut progress: ResMut<Progress>,
+    mut recorded: Local<bool>,
+) {
+    let sim = &run.sim;
+    if !sim.over() || stand_ins.play(&run) {
+        *recorded = false;
+        return;
+    }
+    if *recorded {
+        return;
+    }
+    *recorded = true;
+
+    if sim.config.mode != GameMode::Practice {
+        let bosses = &mut progress.bosses;
+        bosses.passed += sim.bosses_passed as u64;
+        bosses.best_run = bosses.best_run.max(sim.bosses_passed);
+    }
+    match sim.config.mode {
+        GameMode::Zen => {
+            let zen = &mut progress.zen;
+            zen.runs += 1;
+            zen.best_level = zen.best_level.max(sim.level());
+            zen.best_score = zen.best_score.max(sim.score.points);
+            zen.rings_climbed += (sim.level() - sim.config.start_level as i32).max(0) as u64;
+            zen.time_played += sim.run_time;
+        }
+        GameMode::TimeAttack => {
+            progress
+                .time_attack_mut(&sim.config)
+                .add(&sim.splits, sim.finished);
+        }
+        GameMode::Normal
+        | GameMode::Lives
+        | GameMode::Campaign
+        | GameMode::Versus
+        | GameMode::Race => (),
+        GameMode::Practice => return,
+    }
+    progress.save();
+}
+
+/// Shows the stats of the mode and boss progression once a run is over. In time attack, E exports the splits.
+pub fn update_progress_text(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    run: Res<Run>,
+    progress: Res<Progress>,
+    mut text: Query<&mut Text, With<ProgressText>>,
+    mut exported: Local<Option<String>>,
+) {
+    let mut text = text.single_mut();
+    let sim = &run.sim;
+    if !sim.over() {
+        text.sections[0].value = String::new();
+        *exported = None;
+        return;
+    }
+    text.sections[0].value = match sim.config.mode {
+        GameMode::Zen => {
+            let zen = &progress.zen;
+            let best = |new_best| if new_best { "  NEW BEST" } else { "" };
+            let minutes = zen.time_played as u32 / 60;
+            format!(
+                "ZEN PROGRESS\n\
+                 RUNS          {:>9}\n\
+                 BEST LEVEL    {:>9}{}\n\
+                 BEST SCORE    {:>9}{}\n\
+                 RINGS CLIMBED {:>9}\n\
+                 TIME PLAYED   {:>6}:{:02}\n",
+                zen.runs,
+                zen.best_level,
+                best(sim.level() == zen.best_level),
+                zen.best_score,
+                best(sim.score.points == zen.best_score && zen.best_score > 0),
+                zen.rings_climbed,
+                minutes / 60,
+                minutes % 60,
+            )
+        }
+        GameMode::TimeAttack => {
+            let Some(record) = progress.time_attack(&sim.config) else {
+                return;
+            };
+            if keyboard_input.just_pressed(KeyCode::KeyE) {
+                let file = splits_file(&sim.config);
+                storage::save_bytes(&file, record.to_lss().as_bytes());
+                *exported = Some(file);
+            }
+            time_attack_results(sim, record, exported.as_deref())
+        }
+        GameMode::Normal
+        | GameMode::Practice
+        | GameMode::Lives
+        | GameMode::Campaign
+        | GameMode::Versus
+        | GameMode::Race => String::new(),
+    };
+    let bosses = &progress.bosses;
+    if bosses.passed > 0 {
+        text.sections[0].value += &format!(
+            "\nBOSS RINGS PASSED {:>5}\nMOST IN ONE RUN   {:>5}\n",
+            bosses.passed, bosses.best_run
+        );
+    }
+}
+
+fn time_attack_results(sim: &Sim, record: &TimeAttackRecord, exported: Option<&str>) -> String {
+    let config = &sim.config;
+    let mut value = format!(
+        "TIME ATTACK  LEVEL {}  {} SEED {}\n",
+        config.time_attack_target,
+        config.difficulty.name(),
+        config.seed
+    );
+    match sim.splits.last() {
+        Some(&time) if sim.finished => {
+            value.push_str(&format!("TIME           {:>9}", format_time(time)));
+            if record.personal_best == sim.splits {
+                value.push_str("  NEW PERSONAL BEST");
+            }
+            value.push('\n');
+        }
+        _ => value.push_str(&format!("DID NOT FINISH  LEVEL {}\n", sim.level())),
+    }
+    if let Some(&best) = record.personal_best.last() {
+        value.push_str(&format!("PERSONAL BEST  {:>9}\n", format_time(best)));
+    }
+    let sum_of_best: f32 = record.best_segments.iter().sum();
+    if record.best_segments.len() == config.time_attack_splits() {
+        value.push_str(&format!("SUM OF BEST    {:>9}\n", format_time(sum_of_best)));
+    }
+    value.push_str(&format!("ATTEMPTS       {:>9}\n", record.attempts));
+    match exported {
+        Some(file) => value.push_str(&format!("\nSPLITS EXPORTED TO saves/{file}\n")),
+        None => value.push_str("\nPRESS E TO EXPORT SPLITS FOR LIVESPLIT\n"),
+    }
+    value
+}
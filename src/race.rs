@@ -0,0 +1,605 @@
+//! Online head-to-head races. Two clients meet in a room on a [`Relay`], which starts both on the
+//! first one's [`RunConfig`]. From then on each client sends the frames of its run, with their
+//! `dt`, and simulates the opponent from them, so both sides agree on every ring reached without
+//! trusting each other's state. The opponent shows up as a ghost orb.
+//!
+//! Packets are JSON over UDP. Every packet repeats the frames the opponent hasn't acknowledged
+//! yet, so lost packets are made up for by the next one, and the ghost is predicted ahead to hide
+//! latency.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::ReplayFrame;
+use crate::sampling::hash_noise;
+use crate::settings::Settings;
+use crate::sim::{GameMode, Run, RunConfig, Sim, SimInput};
+use crate::{follow, DataMaterial, GameMaterial, PLAYER_TINTS};
+
+/// Seconds between resent joins while waiting for an opponent.
+const JOIN_INTERVAL: f32 = 0.25;
+/// Seconds between input packets, which double as heartbeats.
+const SEND_INTERVAL: f32 = 1.0 / 30.0;
+/// Most frames in one packet. A client further behind catches up over several.
+const MAX_PACKET_FRAMES: usize = 120;
+/// Silence after which the relay drops a client, and a client gives up on the race.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+/// Most the ghost is predicted ahead of the frames received for it.
+const MAX_PREDICTION: f32 = 1.0;
+/// Same room as everyone else unless the settings pick one.
+const DEFAULT_ROOM: &str = "sol";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum Packet {
+    /// Client to relay, until it answers with `Start` or `Full`.
+    Join { room: String, config: RunConfig },
+    /// Relay to both clients once a room has two. `player` is 0 for whoever joined first.
+    Start { config: RunConfig, player: u8 },
+    /// Relay to a third client joining a room.
+    Full,
+    /// Client to the opponent through the relay. `frames` start at frame `first` of the run,
+    /// each as its `dt` and [`SimInput::to_bits`]. `ack` is how many of the opponent's frames
+    /// arrived. `sent_at` is seconds on the sender's clock, and `echo` the latest `sent_at` it
+    /// got from the opponent, for round trip times.
+    Inputs {
+        first: u32,
+        frames: Vec<(f32, u8)>,
+        ack: u32,
+        sent_at: f64,
+        echo: f64,
+    },
+    /// Client to relay when leaving a race.
+    Leave,
+    /// Relay to a client whose opponent left or timed out.
+    Left,
+}
+
+impl Packet {
+    fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RaceStatus {
+    /// Waiting in the room for an opponent.
+    Waiting,
+    Racing,
+    /// Someone else is already racing in the room.
+    Full,
+    /// The opponent left or stopped answering. The run goes on without them.
+    Disconnected(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RaceResult {
+    Won,
+    Lost,
+    Draw,
+}
+
+/// One side of a race.
+pub struct RaceClient {
+    socket: UdpSocket,
+    relay: SocketAddr,
+    room: String,
+    pub status: RaceStatus,
+    /// The config both runs use. The joining config until the race starts.
+    pub config: RunConfig,
+    /// 0 for the player who joined first.
+    pub player: usize,
+    /// The opponent's run, as far as their frames have arrived.
+    opponent: Sim,
+    /// Opponent frames played into `opponent`.
+    received: u32,
+    /// Local frames the opponent has acknowledged.
+    acked: u32,
+    /// Local frames seen in the last [`RaceClient::update`].
+    frames: u32,
+    /// Round trip time through the relay, in seconds.
+    pub rtt: Option<f32>,
+    echo: f64,
+    /// Silence from the opponent before the race counts as disconnected.
+    pub timeout: Duration,
+    clock: Instant,
+    last_heard: Instant,
+    last_sent: Option<Instant>,
+}
+
+impl RaceClient {
+    /// Starts looking for an opponent in `room`. The race starts on `config` if this client is
+    /// the first to join.
+    pub fn join(relay: impl ToSocketAddrs, room: &str, config: RunConfig) -> io::Result<Self> {
+        let relay = relay
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address"))?;
+        let bind = if relay.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_nonblocking(true)?;
+        let now = Instant::now();
+        Ok(Self {
+            socket,
+            relay,
+            room: String::from(room),
+            status: RaceStatus::Waiting,
+            config,
+            player: 0,
+            opponent: Sim::new(config),
+            received: 0,
+            acked: 0,
+            frames: 0,
+            rtt: None,
+            echo: 0.0,
+            timeout: TIMEOUT,
+            clock: now,
+            last_heard: now,
+            last_sent: None,
+        })
+    }
+
+    /// Local frames seen so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// The opponent's run, up to the last frame that arrived.
+    pub fn opponent(&self) -> &Sim {
+        &self.opponent
+    }
+
+    /// Receives what arrived and sends the local run's `frames` the opponent hasn't
+    /// acknowledged. Call once per frame.
+    pub fn update(&mut self, frames: &[ReplayFrame]) {
+        self.frames = frames.len() as u32;
+        let mut buf = [0; 65536];
+        while let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+            if from != self.relay {
+                continue;
+            }
+            if let Some(packet) = Packet::from_bytes(&buf[..len]) {
+                self.receive(packet);
+            }
+        }
+
+        let now = Instant::now();
+        let since_sent = self
+            .last_sent
+            .map_or(f32::INFINITY, |sent| (now - sent).as_secs_f32());
+        match self.status {
+            RaceStatus::Waiting if since_sent >= JOIN_INTERVAL => {
+                self.send(&Packet::Join {
+                    room: self.room.clone(),
+                    config: self.config,
+                });
+            }
+            RaceStatus::Racing if now - self.last_heard > self.timeout => {
+                self.status = RaceStatus::Disconnected(String::from("OPPONENT TIMED OUT"));
+            }
+            RaceStatus::Racing if since_sent >= SEND_INTERVAL => {
+                let first = self.acked.min(self.frames) as usize;
+                let last = frames.len().min(first + MAX_PACKET_FRAMES);
+                self.send(&Packet::Inputs {
+                    first: first as u32,
+                    frames: frames[first..last]
+                        .iter()
+                        .map(|frame| (frame.dt, frame.input.to_bits()))
+                        .collect(),
+                    ack: self.received,
+                    sent_at: self.clock.elapsed().as_secs_f64(),
+                    echo: self.echo,
+                });
+            }
+            _ => (),
+        }
+    }
+
+    fn send(&mut self, packet: &Packet) {
+        // Lost sends are made up for by later ones.
+        let _ = self.socket.send_to(&packet.to_bytes(), self.relay);
+        self.last_sent = Some(Instant::now());
+    }
+
+    fn receive(&mut self, packet: Packet) {
+        match (packet, &self.status) {
+            (Packet::Start { config, player }, RaceStatus::Waiting) => {
+                self.status = RaceStatus::Racing;
+                self.config = config;
+                self.player = player as usize;
+                self.opponent = Sim::new(config);
+                self.last_heard = Instant::now();
+                self.last_sent = None;
+            }
+            (Packet::Full, RaceStatus::Waiting) => self.status = RaceStatus::Full,
+            (
+                Packet::Inputs {
+                    first,
+                    frames,
+                    ack,
+                    sent_at,
+                    echo,
+                },
+                RaceStatus::Racing,
+            ) => {
+                self.last_heard = Instant::now();
+                self.acked = self.acked.max(ack);
+                if sent_at > self.echo {
+                    self.echo = sent_at;
+                }
+                if echo > 0.0 {
+                    self.rtt = Some((self.clock.elapsed().as_secs_f64() - echo) as f32);
+                }
+                // Frames arrive in order within a packet, but packets may skip or repeat some.
+                let skip = self.received.saturating_sub(first) as usize;
+                if first > self.received {
+                    return;
+                }
+                for &(dt, bits) in frames.iter().skip(skip) {
+                    self.opponent.step(dt, SimInput::from_bits(bits));
+                    self.received += 1;
+                }
+            }
+            (Packet::Left, RaceStatus::Racing) => {
+                self.status = RaceStatus::Disconnected(String::from("OPPONENT LEFT"));
+            }
+            _ => (),
+        }
+    }
+
+    /// Where the opponent probably is by now: their run continued without input up to `local`'s
+    /// run time, since their latest frames are still on the way.
+    pub fn ghost(&self, local: &Sim) -> Sim {
+        let mut ghost = self.opponent.clone();
+        let until = local.run_time.min(ghost.run_time + MAX_PREDICTION);
+        while !ghost.over() && ghost.run_time < until {
+            ghost.step(1.0 / 60.0, SimInput::default());
+        }
+        ghost
+    }
+
+    /// Once both runs are over. Whoever stayed ahead of the wavefront longer wins, and then
+    /// whoever got further.
+    pub fn result(&self, local: &Sim) -> Option<RaceResult> {
+        let opponent = &self.opponent;
+        if self.status != RaceStatus::Racing || !local.over() || !opponent.over() {
+            return None;
+        }
+        let key = |sim: &Sim| (sim.run_time, sim.level());
+        Some(match key(local).partial_cmp(&key(opponent)) {
+            Some(std::cmp::Ordering::Greater) => RaceResult::Won,
+            Some(std::cmp::Ordering::Less) => RaceResult::Lost,
+            _ => RaceResult::Draw,
+        })
+    }
+}
+
+impl Drop for RaceClient {
+    fn drop(&mut self) {
+        if matches!(self.status, RaceStatus::Waiting | RaceStatus::Racing) {
+            self.send(&Packet::Leave);
+        }
+    }
+}
+
+struct Peer {
+    addr: SocketAddr,
+    last_heard: Instant,
+}
+
+struct Room {
+    config: RunConfig,
+    peers: Vec<Peer>,
+}
+
+/// Pairs up clients by room and forwards their packets to each other. Can be made to drop and
+/// delay packets, to try races on a bad connection.
+pub struct Relay {
+    socket: UdpSocket,
+    rooms: HashMap<String, Room>,
+    /// Chance of dropping each forwarded packet.
+    pub loss: f32,
+    /// Added to every forwarded packet.
+    pub delay: Duration,
+    queue: VecDeque<(Instant, SocketAddr, Vec<u8>)>,
+    forwarded: u32,
+}
+
+impl Relay {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(Duration::from_millis(5)))?;
+        Ok(Self {
+            socket,
+            rooms: HashMap::new(),
+            loss: 0.0,
+            delay: Duration::ZERO,
+            queue: VecDeque::new(),
+            forwarded: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Relays until `stop` is set.
+    pub fn run(mut self, stop: &AtomicBool) {
+        let mut buf = [0; 65536];
+        while !stop.load(Ordering::Relaxed) {
+            if let Ok((len, from)) = self.socket.recv_from(&mut buf) {
+                if let Some(packet) = Packet::from_bytes(&buf[..len]) {
+                    self.receive(packet, &buf[..len], from);
+                }
+            }
+            let now = Instant::now();
+            while self.queue.front().is_some_and(|(due, ..)| *due <= now) {
+                let (_, to, bytes) = self.queue.pop_front().unwrap();
+                let _ = self.socket.send_to(&bytes, to);
+            }
+            self.drop_silent(now);
+        }
+    }
+
+    /// Runs on its own thread until the returned handle is dropped.
+    pub fn spawn(self) -> io::Result<RelayHandle> {
+        let addr = self.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || self.run(&thread_stop));
+        Ok(RelayHandle { addr, stop })
+    }
+
+    fn receive(&mut self, packet: Packet, bytes: &[u8], from: SocketAddr) {
+        let room_of = |rooms: &HashMap<String, Room>| {
+            rooms
+                .iter()
+                .find(|(_, room)| room.peers.iter().any(|peer| peer.addr == from))
+                .map(|(name, _)| name.clone())
+        };
+        match packet {
+            Packet::Join { room, config } => {
+                if let Some(joined) = room_of(&self.rooms).filter(|joined| *joined != room) {
+                    self.leave(&joined, from);
+                }
+                let entry = self.rooms.entry(room).or_insert_with(|| Room {
+                    config,
+                    peers: Vec::new(),
+                });
+                let full = entry.peers.len() == 2;
+                match entry.peers.iter_mut().find(|peer| peer.addr == from) {
+                    Some(peer) => peer.last_heard = Instant::now(),
+                    None if full => {
+                        self.send(&Packet::Full, from);
+                        return;
+                    }
+                    None => entry.peers.push(Peer {
+                        addr: from,
+                        last_heard: Instant::now(),
+                    }),
+                }
+                // Joins are resent until started, so this also repeats lost starts.
+                if entry.peers.len() == 2 {
+                    let config = entry.config;
+                    let addrs: Vec<_> = entry.peers.iter().map(|peer| peer.addr).collect();
+                    for (player, addr) in addrs.into_iter().enumerate() {
+                        let start = Packet::Start {
+                            config,
+                            player: player as u8,
+                        };
+                        self.send(&start, addr);
+                    }
+                }
+            }
+            Packet::Inputs { .. } => {
+                let Some(room) = room_of(&self.rooms).and_then(|name| self.rooms.get_mut(&name))
+                else {
+                    return;
+                };
+                let mut to = None;
+                for peer in &mut room.peers {
+                    if peer.addr == from {
+                        peer.last_heard = Instant::now();
+                    } else {
+                        to = Some(peer.addr);
+                    }
+                }
+                if let Some(to) = to {
+                    self.forward(bytes.to_vec(), to);
+                }
+            }
+            Packet::Leave => {
+                if let Some(room) = room_of(&self.rooms) {
+                    self.leave(&room, from);
+                }
+            }
+            Packet::Start { .. } | Packet::Full | Packet::Left => (),
+        }
+    }
+
+    /// Closes the room, telling whoever is left.
+    fn leave(&mut self, room: &str, from: SocketAddr) {
+        let Some(room) = self.rooms.remove(room) else {
+            return;
+        };
+        for peer in room.peers.iter().filter(|peer| peer.addr != from) {
+            self.send(&Packet::Left, peer.addr);
+        }
+    }
+
+    fn drop_silent(&mut self, now: Instant) {
+        let silent: Vec<_> = self
+            .rooms
+            .iter()
+            .flat_map(|(name, room)| {
+                room.peers
+                    .iter()
+                    .filter(|peer| now - peer.last_heard > TIMEOUT)
+                    .map(move |peer| (name.clone(), peer.addr))
+            })
+            .collect();
+        for (room, addr) in silent {
+            self.leave(&room, addr);
+        }
+    }
+
+    fn send(&self, packet: &Packet, to: SocketAddr) {
+        let _ = self.socket.send_to(&packet.to_bytes(), to);
+    }
+
+    fn forward(&mut self, bytes: Vec<u8>, to: SocketAddr) {
+        self.forwarded += 1;
+        if hash_noise(self.forwarded, 0, 0) < self.loss {
+            return;
+        }
+        self.queue
+            .push_back((Instant::now() + self.delay, to, bytes));
+    }
+}
+
+/// A [`Relay`] running on its own thread, stopped on drop.
+pub struct RelayHandle {
+    pub addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for RelayHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The race of the current [`GameMode::Race`] run, if a relay is set in the settings.
+#[derive(Resource, Default)]
+pub struct Race {
+    client: Option<RaceClient>,
+    /// Why joining failed. Tried again on the next run.
+    error: Option<String>,
+}
+
+#[derive(Component)]
+pub struct RaceText;
+
+pub fn setup_race(mut commands: Commands) {
+    commands.init_resource::<Race>();
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(6.0),
+            left: Val::Px(0.0),
+            right: Val::Px(0.0),
+            ..default()
+        }),
+        RaceText,
+    ));
+}
+
+/// Keeps the race going alongside the run: holds the run at its start until the race starts,
+/// restarts the race with the run, and draws the opponent's ghost orb.
+pub(crate) fn sync_race(
+    mut race: ResMut<Race>,
+    mut run: ResMut<Run>,
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    game_material: Res<GameMaterial>,
+    mut text: Query<&mut Text, With<RaceText>>,
+) {
+    let mut text = text.single_mut();
+    text.sections[0].value = String::new();
+    if run.sim.config.mode != GameMode::Race {
+        *race = Race::default();
+        return;
+    }
+    let Some(relay) = &settings.race_relay else {
+        text.sections[0].value = String::from("SET race_relay IN settings.ron TO RACE ONLINE");
+        return;
+    };
+    // Restarting leaves the race for a new one. The run is held at its start while waiting.
+    if race.client.as_ref().is_some_and(|client| {
+        client.status != RaceStatus::Waiting && (run.replay.frames.len() as u32) < client.frames()
+    }) {
+        *race = Race::default();
+    }
+    if let Some(error) = &race.error {
+        text.sections[0].value = format!("COULDN'T JOIN A RACE: {error}");
+        return;
+    }
+    if race.client.is_none() {
+        let room = settings.race_room.as_deref().unwrap_or(DEFAULT_ROOM);
+        let mut config = run.sim.config;
+        config.level = None;
+        match RaceClient::join(relay.as_str(), room, config) {
+            Ok(client) => race.client = Some(client),
+            Err(e) => {
+                race.error = Some(e.to_string().to_uppercase());
+                return;
+            }
+        }
+    }
+    let client = race.client.as_mut().unwrap();
+    let was_waiting = client.status == RaceStatus::Waiting;
+    client.update(&run.replay.frames);
+    match &client.status {
+        RaceStatus::Waiting => {
+            *run = Run::new(client.config);
+            text.sections[0].value = String::from("WAITING FOR AN OPPONENT");
+            return;
+        }
+        RaceStatus::Full => {
+            text.sections[0].value = String::from("THE RACE ROOM IS FULL");
+            return;
+        }
+        RaceStatus::Disconnected(reason) => {
+            text.sections[0].value = reason.clone();
+            return;
+        }
+        RaceStatus::Racing => (),
+    }
+    if was_waiting {
+        *run = Run::new(client.config);
+        client.update(&run.replay.frames);
+    }
+
+    let ghost = client.ghost(&run.sim);
+    let ping = client
+        .rtt
+        .map_or(String::new(), |rtt| format!("  PING {:.0}MS", rtt * 1000.0));
+    text.sections[0].value = match client.result(&run.sim) {
+        Some(RaceResult::Won) => String::from("YOU WON THE RACE"),
+        Some(RaceResult::Lost) => String::from("YOU LOST THE RACE"),
+        Some(RaceResult::Draw) => String::from("THE RACE IS A DRAW"),
+        None if run.sim.over() => String::from("WAITING FOR THE OPPONENT TO FINISH"),
+        None => format!("OPPONENT LEVEL {}{ping}", ghost.level()),
+    };
+
+    let Some(gpu) = materials.get_mut(&game_material.0) else {
+        return;
+    };
+    let state = &mut gpu.state;
+    state.player_count = 2;
+    state.players[1].show(&ghost, PLAYER_TINTS[1].truncate().extend(0.5));
+    state.players[1].position = follow(&ghost, state.ring_thick, 1.0, 0.0);
+}
@@ -0,0 +1,290 @@
+//! Control of the running game over a local TCP socket, for QA scripts and accessibility tools.
+//! Only built with the `remote` feature. The game listens on `SOL_REMOTE_ADDR`, by default
+//! `127.0.0.1:7879`, for one JSON request per line, and answers each with one line:
+//!
+//! ```text
+//! > {"cmd": "press", "keys": ["Space"]}        < {"ok": true}
+//! > {"cmd": "state"}                           < {"state": {"t": 1.2, ...}}
+//! > {"cmd": "pause"}                           < {"ok": true}
+//! > {"cmd": "step", "frames": 10}              < {"ok": true}
+//! > {"cmd": "resume"}                          < {"ok": true}
+//! > {"cmd": "seed", "seed": 42}                < {"ok": true}
+//! > {"cmd": "screenshot", "path": "shot.png"}  < {"ok": true}
+//! ```
+//!
+//! Pressed keys are released the frame after, so they do anything a player's key press would,
+//! menus included. While paused the game's clock stops and the keyboard is ignored. Presses wait
+//! for the next frame that runs, so pressing then stepping lands them on the first stepped frame.
+//! Steps are [`FRAME_DT`] long and answered once they're done.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::{Run, RunConfig};
+use crate::{DataMaterial, GameMaterial, GpuState};
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7879";
+/// Seconds each stepped frame lasts.
+pub const FRAME_DT: f64 = 1.0 / 60.0;
+
+pub struct RemotePlugin {
+    pub addr: String,
+}
+
+impl Default for RemotePlugin {
+    fn default() -> Self {
+        Self {
+            addr: std::env::var("SOL_REMOTE_ADDR").unwrap_or_else(|_| DEFAULT_ADDR.into()),
+        }
+    }
+}
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(&self.addr) {
+            Ok(listener) => listener,
+            Err(e) => {
+                warn!("remote control can't listen on {}: {e}", self.addr);
+                return;
+            }
+        };
+        let addr = listener.local_addr().unwrap();
+        info!("remote control listening on {addr}");
+        let (requests, receiver) = channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let requests = requests.clone();
+                std::thread::spawn(move || serve(stream, requests));
+            }
+        });
+        app.insert_resource(Remote {
+            addr,
+            requests: Mutex::new(receiver),
+            paused: false,
+            running: true,
+            step: None,
+            strategy: None,
+            queued: Vec::new(),
+            held: Vec::new(),
+        })
+        .add_systems(First, remote_control.before(TimeSystem))
+        .add_systems(PreUpdate, press_keys.after(InputSystem));
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Press {
+        keys: Vec<KeyCode>,
+    },
+    State,
+    Pause,
+    Resume,
+    Step {
+        #[serde(default = "one_frame")]
+        frames: u32,
+    },
+    Seed {
+        seed: u32,
+    },
+    Screenshot {
+        path: String,
+    },
+}
+
+fn one_frame() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Reply<'a> {
+    Ok { ok: bool },
+    State { state: &'a GpuState },
+    Error { error: String },
+}
+
+impl Reply<'_> {
+    fn error(error: impl Into<String>) -> Self {
+        Reply::Error {
+            error: error.into(),
+        }
+    }
+
+    fn line(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+const OK: Reply<'static> = Reply::Ok { ok: true };
+
+type Requests = Sender<(Request, Sender<String>)>;
+
+/// Passes a connection's requests on to the game, and its replies back.
+fn serve(stream: TcpStream, requests: Requests) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let (reply, answer) = channel();
+                if requests.send((request, reply)).is_err() {
+                    return Ok(());
+                }
+                match answer.recv() {
+                    Ok(answer) => answer,
+                    Err(_) => return Ok(()),
+                }
+            }
+            Err(e) => Reply::error(format!("bad request: {e}")).line(),
+        };
+        writeln!(writer, "{reply}")?;
+    }
+    Ok(())
+}
+
+#[derive(Resource)]
+pub struct Remote {
+    /// Where the game listens, useful when bound to port 0.
+    pub addr: SocketAddr,
+    requests: Mutex<Receiver<(Request, Sender<String>)>>,
+    paused: bool,
+    /// Whether this frame runs, which paused frames only do while stepping.
+    running: bool,
+    /// Stepped frames left after this one, and where to answer once they're done.
+    step: Option<(u32, Sender<String>)>,
+    /// How the clock was updated before stepping, put back once the steps are done.
+    strategy: Option<TimeUpdateStrategy>,
+    /// Keys to press on the next frame that runs.
+    queued: Vec<KeyCode>,
+    /// Keys pressed last frame, to release.
+    held: Vec<KeyCode>,
+}
+
+/// Answers requests, before the clock is updated so pausing and stepping apply to this frame.
+pub(crate) fn remote_control(
+    mut remote: ResMut<Remote>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut run: ResMut<Run>,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    game_material: Res<GameMaterial>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshots: Option<ResMut<ScreenshotManager>>,
+) {
+    let remote = &mut *remote;
+    if let Some((frames, reply)) = &mut remote.step {
+        if *frames > 0 {
+            *frames -= 1;
+            return;
+        }
+        let _ = reply.send(OK.line());
+        remote.step = None;
+        virtual_time.pause();
+        *strategy = remote.strategy.take().unwrap_or_default();
+    }
+
+    let requests = remote.requests.get_mut().unwrap();
+    while let Ok((request, reply)) = requests.try_recv() {
+        let answer = match request {
+            Request::Press { keys } => {
+                remote.queued.extend(keys);
+                Some(OK.line())
+            }
+            Request::State => match materials.get(&game_material.0) {
+                Some(material) => Some(
+                    Reply::State {
+                        state: &material.state,
+                    }
+                    .line(),
+                ),
+                None => Some(Reply::error("no game state yet").line()),
+            },
+            Request::Pause => {
+                remote.paused = true;
+                virtual_time.pause();
+                Some(OK.line())
+            }
+            Request::Resume => {
+                remote.paused = false;
+                virtual_time.unpause();
+                Some(OK.line())
+            }
+            Request::Step { frames } => {
+                if !remote.paused {
+                    Some(Reply::error("pause before stepping").line())
+                } else if frames == 0 {
+                    Some(OK.line())
+                } else {
+                    virtual_time.unpause();
+                    let stepping =
+                        TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(FRAME_DT));
+                    remote.strategy = Some(std::mem::replace(&mut *strategy, stepping));
+                    remote.step = Some((frames - 1, reply.clone()));
+                    None
+                }
+            }
+            Request::Seed { seed } => {
+                let config = RunConfig {
+                    seed,
+                    ..run.sim.config
+                };
+                *run = Run::with_level(config, run.sim.level.clone());
+                if let Some(material) = materials.get_mut(&game_material.0) {
+                    material.state.new_run();
+                }
+                Some(OK.line())
+            }
+            Request::Screenshot { path } => {
+                let saved = match (window.get_single(), screenshots.as_mut()) {
+                    (Ok(window), Some(screenshots)) => screenshots
+                        .save_screenshot_to_disk(window, path)
+                        .map_err(|e| e.to_string()),
+                    _ => Err(String::from("nothing is rendered")),
+                };
+                Some(match saved {
+                    Ok(()) => OK.line(),
+                    Err(e) => Reply::error(e).line(),
+                })
+            }
+        };
+        match answer {
+            Some(answer) => {
+                let _ = reply.send(answer);
+            }
+            // Answered once the steps are done, and later requests wait for them.
+            None => break,
+        }
+    }
+    remote.running = !remote.paused || remote.step.is_some();
+}
+
+/// Presses queued keys and releases the ones from last frame. Paused frames see no keys at all.
+pub(crate) fn press_keys(mut remote: ResMut<Remote>, mut keys: ResMut<ButtonInput<KeyCode>>) {
+    for key in remote.held.drain(..) {
+        keys.release(key);
+    }
+    if !remote.running {
+        keys.reset_all();
+        return;
+    }
+    let remote = &mut *remote;
+    for key in remote.queued.drain(..) {
+        keys.press(key);
+        remote.held.push(key);
+    }
+}
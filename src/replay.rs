@@ -0,0 +1,172 @@
+//! Replays store the frame time and input of every simulated frame, so feeding them back
+//! through [`Sim::step`] reproduces the run exactly. The adaptive difficulty adjustments made
+//! during the run are stored too, to show how it was tuned without playing it back.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::adaptive::Adjustment;
+use crate::level::Level;
+use crate::sim::{RunConfig, Sim, SimInput};
+
+const MAGIC: &[u8; 4] = b"SOLR";
+/// Version 1 had no adjustments.
+const VERSION: u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReplayFrame {
+    pub dt: f32,
+    pub input: SimInput,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub config: RunConfig,
+    pub frames: Vec<ReplayFrame>,
+    /// See [`crate::adaptive::Adaptive::log`].
+    pub adjustments: Vec<Adjustment>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    BadConfig(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(v) => write!(f, "unsupported replay version {v}"),
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+            ReplayError::BadConfig(e) => write!(f, "invalid run config: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(config: RunConfig) -> Self {
+        Self {
+            config,
+            frames: Vec::new(),
+            adjustments: Vec::new(),
+        }
+    }
+
+    /// Runs the whole replay through a fresh simulation.
+    pub fn play(&self) -> Sim {
+        self.play_with_level(None)
+    }
+
+    /// Replays of runs on a hand-made level only play back the same on that level, which has
+    /// to match [`RunConfig::level`].
+    pub fn play_with_level(&self, level: Option<Arc<Level>>) -> Sim {
+        let mut sim = Sim::with_level(self.config, level);
+        for frame in &self.frames {
+            sim.step(frame.dt, frame.input);
+        }
+        sim
+    }
+
+    /// Whether playing back gives the same adjustments as the stored ones.
+    pub fn adjustments_match(&self, sim: &Sim) -> bool {
+        sim.adaptive.log == self.adjustments
+    }
+
+    /// Layout: magic, version (u16), config length (u32) and RON config,
+    /// frame count (u32), then `dt` (f32) and input bits (u8) per frame, then adjustment count
+    /// (u32) and time, skill, speed, arc size and cooldown (f32) per adjustment. All little
+    /// endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let config = ron::to_string(&self.config).unwrap();
+        let mut bytes = Vec::with_capacity(
+            18 + config.len() + self.frames.len() * 5 + self.adjustments.len() * 20,
+        );
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(config.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(config.as_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.dt.to_le_bytes());
+            bytes.push(frame.input.to_bits());
+        }
+        bytes.extend_from_slice(&(self.adjustments.len() as u32).to_le_bytes());
+        for a in &self.adjustments {
+            for value in [a.time, a.skill, a.speed, a.arc_size, a.cooldown] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version == 0 || version > VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let config_len = u32::from_le_bytes(reader.array()?) as usize;
+        let config = std::str::from_utf8(reader.take(config_len)?)
+            .map_err(|e| ReplayError::BadConfig(e.to_string()))?;
+        let config = ron::from_str(config).map_err(|e| ReplayError::BadConfig(e.to_string()))?;
+        let frame_count = u32::from_le_bytes(reader.array()?) as usize;
+        let mut frames = Vec::with_capacity(frame_count.min(reader.0.len() / 5));
+        for _ in 0..frame_count {
+            let dt = f32::from_le_bytes(reader.array()?);
+            let [input] = reader.array()?;
+            frames.push(ReplayFrame {
+                dt,
+                input: SimInput::from_bits(input),
+            });
+        }
+        let mut adjustments = Vec::new();
+        if version >= 2 {
+            let count = u32::from_le_bytes(reader.array()?) as usize;
+            adjustments.reserve(count.min(reader.0.len() / 20));
+            for _ in 0..count {
+                let mut values = [0.0; 5];
+                for value in &mut values {
+                    *value = f32::from_le_bytes(reader.array()?);
+                }
+                let [time, skill, speed, arc_size, cooldown] = values;
+                adjustments.push(Adjustment {
+                    time,
+                    skill,
+                    speed,
+                    arc_size,
+                    cooldown,
+                });
+            }
+        }
+        Ok(Self {
+            config,
+            frames,
+            adjustments,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < n {
+            return Err(ReplayError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
@@ -0,0 +1,40 @@
+use crate::sim::{get_max_arcs, STARTING_LEVEL};
+
+const BASE_POINTS: u32 = 10;
+const COMBO_PER_MULTIPLIER: u32 = 5;
+const MAX_MULTIPLIER: u32 = 8;
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Score {
+    pub points: u64,
+    pub combo: u32,
+    pub best_combo: u32,
+}
+
+impl Score {
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.combo / COMBO_PER_MULTIPLIER).min(MAX_MULTIPLIER)
+    }
+
+    /// Points for landing on `ring`, before the combo multiplier.
+    /// Each level past the start adds 10% and each extra arc tier adds 50%.
+    pub fn ring_value(ring: u32) -> u32 {
+        let level = ring.saturating_sub(STARTING_LEVEL);
+        let tier = get_max_arcs(ring) - 2;
+        BASE_POINTS * (10 + level) * (2 + tier) / 20
+    }
+
+    /// Call after landing on `ring`. Returns the points awarded, scaled by `percent` for
+    /// [`crate::mods::Mods::score_percent`].
+    pub fn hit(&mut self, ring: u32, percent: u32) -> u32 {
+        self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
+        let points = Self::ring_value(ring) * self.multiplier() * percent / 100;
+        self.points += points as u64;
+        points
+    }
+
+    pub fn miss(&mut self) {
+        self.combo = 0;
+    }
+}
@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::campaign::{cleared, Campaign, CampaignAssets};
+use crate::editor::Editor;
+use crate::hud::HudElement;
+use crate::level::{level_name, LEVELS};
+use crate::mods::{Mod, Mods};
+use crate::progress::Progress;
+use crate::sim::{Difficulty, GameMode, Run, RunConfig};
+use crate::{storage, DataMaterial, GameMaterial};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub hud: HudSettings,
+    pub difficulty: Difficulty,
+    pub mode: GameMode,
+    pub practice: PracticeSettings,
+    /// See [`RunConfig::colors`].
+    pub colors: bool,
+    /// `host:port` of the online leaderboard. Stays offline when unset.
+    pub leaderboard_server: Option<String>,
+    /// Asset path of the hand-made level to play, see [`crate::level`].
+    pub level: Option<String>,
+    /// Campaign chapter to play, see [`crate::campaign`].
+    pub chapter: usize,
+    /// See [`RunConfig::adaptive`].
+    pub adaptive: bool,
+    pub mods: Mods,
+    /// `host:port` of the relay for online races, see [`crate::race`].
+    pub race_relay: Option<String>,
+    /// Room to race in on the relay. Everyone without one meets in the same room.
+    pub race_room: Option<String>,
+    /// Gives each versus player their own half of the window, see [`crate::split`]. Otherwise
+    /// both orbs share one field.
+    pub split_screen: bool,
+    /// Seed of every run, only set from the command line.
+    #[serde(skip)]
+    pub seed: u32,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(SETTINGS_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        storage::save(SETTINGS_FILE, self);
+    }
+
+    /// Config for the next run.
+    pub fn run_config(&self) -> RunConfig {
+        let mut config = RunConfig {
+            mode: self.mode,
+            difficulty: self.difficulty,
+            colors: self.colors,
+            adaptive: self.adaptive,
+            mods: self.mods,
+            seed: self.seed,
+            ..default()
+        };
+        if self.mode == GameMode::Practice {
+            config.start_level = self.practice.start_level;
+            config.speed_percent = self.practice.speed_percent;
+            config.invincible = self.practice.invincible;
+        }
+        config
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PracticeSettings {
+    pub start_level: u32,
+    pub speed_percent: u32,
+    pub invincible: bool,
+}
+
+impl Default for PracticeSettings {
+    fn default() -> Self {
+        Self {
+            start_level: 0,
+            speed_percent: 100,
+            invincible: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudSettings {
+    pub level: bool,
+    pub misses: bool,
+    pub score: bool,
+    pub wavefront: bool,
+    pub power_ups: bool,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        Self {
+            level: true,
+            misses: true,
+            score: true,
+            wavefront: true,
+            power_ups: true,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct SettingsText;
+
+pub fn setup_settings_menu(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(12.0),
+            ..default()
+        }),
+        SettingsText,
+    ));
+}
+
+/// While paused or dead, the number keys toggle HUD elements, D picks the difficulty and
+/// G picks the game mode, K toggles color matching, A toggles adaptive difficulty, L picks a
+/// level and the [`MOD_KEYS`] toggle mods. Brackets, minus/equals and I set up practice mode,
+/// while in campaign mode brackets pick an unlocked chapter and in versus mode B toggles
+/// split-screen.
+const MOD_KEYS: [(KeyCode, &str); 5] = [
+    (KeyCode::KeyH, "H"),
+    (KeyCode::KeyV, "V"),
+    (KeyCode::KeyT, "T"),
+    (KeyCode::KeyN, "N"),
+    (KeyCode::KeyO, "O"),
+];
+
+pub fn settings_menu(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    materials: Res<Assets<DataMaterial>>,
+    game_material: Res<GameMaterial>,
+    run: Res<Run>,
+    editor: Res<Editor>,
+    campaign: (Res<CampaignAssets>, Res<Assets<Campaign>>, Res<Progress>),
+    mut settings: ResMut<Settings>,
+    mut text: Query<&mut Text, With<SettingsText>>,
+) {
+    let Some(gpu) = materials.get(&game_material.0) else {
+        return;
+    };
+    let mut text = text.single_mut();
+    text.sections[0].value = String::new();
+    if gpu.state.paused == 0 && !run.sim.over() || editor.open {
+        return;
+    }
+
+    let keys = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+    ];
+    let mut changed = false;
+    for (key, element) in keys.into_iter().zip(HudElement::ALL) {
+        if keyboard_input.just_pressed(key) {
+            let setting = element.setting(&mut settings.hud);
+            *setting = !*setting;
+            changed = true;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyD) {
+        settings.difficulty = settings.difficulty.next();
+        changed = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        settings.mode = settings.mode.next();
+        changed = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+        settings.colors = !settings.colors;
+        changed = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyA) {
+        settings.adaptive = !settings.adaptive;
+        changed = true;
+    }
+    for ((key, _), m) in MOD_KEYS.into_iter().zip(Mod::ALL) {
+        if keyboard_input.just_pressed(key) {
+            settings.mods.toggle(m);
+            changed = true;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyL) {
+        // Cycles through the levels, then back to procedural rings.
+        let current = LEVELS
+            .iter()
+            .position(|path| settings.level.as_deref() == Some(*path));
+        let next = current.map_or(0, |i| i + 1);
+        settings.level = LEVELS.get(next).map(|path| path.to_string());
+        changed = true;
+    }
+    if settings.mode == GameMode::Practice {
+        let practice = &mut settings.practice;
+        if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+            practice.start_level = practice.start_level.saturating_sub(5);
+            changed = true;
+        }
+        if keyboard_input.just_pressed(KeyCode::BracketRight) {
+            practice.start_level += 5;
+            changed = true;
+        }
+        if keyboard_input.just_pressed(KeyCode::Minus) {
+            practice.speed_percent = practice.speed_percent.saturating_sub(10).max(20);
+            changed = true;
+        }
+        if keyboard_input.just_pressed(KeyCode::Equal) {
+            practice.speed_percent = (practice.speed_percent + 10).min(200);
+            changed = true;
+        }
+        if keyboard_input.just_pressed(KeyCode::KeyI) {
+            practice.invincible = !practice.invincible;
+            changed = true;
+        }
+    }
+    if settings.mode == GameMode::Versus && keyboard_input.just_pressed(KeyCode::KeyB) {
+        settings.split_screen = !settings.split_screen;
+        changed = true;
+    }
+    let (campaign_assets, campaigns, progress) = campaign;
+    let campaign = campaigns.get(&campaign_assets.campaign);
+    if let Some(campaign) = campaign.filter(|_| settings.mode == GameMode::Campaign) {
+        let chapter = settings.chapter;
+        if keyboard_input.just_pressed(KeyCode::BracketLeft) && chapter > 0 {
+            settings.chapter -= 1;
+            changed = true;
+        }
+        if keyboard_input.just_pressed(KeyCode::BracketRight)
+            && campaign.unlocked(chapter + 1, &progress)
+        {
+            settings.chapter += 1;
+            changed = true;
+        }
+    }
+    if changed {
+        settings.save();
+    }
+
+    let on_off = |on| if on { "ON" } else { "OFF" };
+    let value = &mut text.sections[0].value;
+    *value = String::from("HUD\n");
+    for (i, element) in HudElement::ALL.into_iter().enumerate() {
+        value.push_str(&format!(
+            "{} {:<10} {}\n",
+            i + 1,
+            element.name(),
+            on_off(element.is_enabled(&settings.hud))
+        ));
+    }
+    value.push_str(&format!(
+        "\nD DIFFICULTY {} (NEXT RUN)\n",
+        settings.difficulty.name()
+    ));
+    value.push_str(&format!(
+        "G MODE       {} (NEXT RUN)\n",
+        settings.mode.name()
+    ));
+    value.push_str(&format!(
+        "K COLORS     {} (NEXT RUN)\n",
+        on_off(settings.colors)
+    ));
+    value.push_str(&format!(
+        "A ADAPTIVE   {} (NEXT RUN)\n",
+        on_off(settings.adaptive)
+    ));
+    value.push_str(&format!(
+        "L LEVEL      {} (NEXT RUN)\n",
+        settings
+            .level
+            .as_deref()
+            .map_or(String::from("PROCEDURAL"), level_name)
+    ));
+    value.push_str(&format!(
+        "\nMODS         {}% SCORE (NEXT RUN)\n",
+        settings.mods.score_percent()
+    ));
+    for ((_, key), m) in MOD_KEYS.into_iter().zip(Mod::ALL) {
+        value.push_str(&format!(
+            "{key} {:<17} {:>3}  +{}%\n",
+            m.name(),
+            on_off(settings.mods.get(m)),
+            m.score_bonus()
+        ));
+    }
+    let practice = &settings.practice;
+    if settings.mode == GameMode::Practice {
+        value.push_str(&format!(
+            "[ ] START LEVEL {}\n- = SPEED       {}%\nI   INVINCIBLE  {}\n\
+             C SETS A CHECKPOINT, R RETRIES FROM IT\n",
+            practice.start_level,
+            practice.speed_percent,
+            on_off(practice.invincible)
+        ));
+    }
+    if settings.mode == GameMode::Versus {
+        value.push_str(&format!(
+            "B SPLIT SCREEN {}\n",
+            on_off(settings.split_screen)
+        ));
+        value.push_str(
+            "P1  W/SPACE JUMP  S DIVE  X COLOR\n\
+             P2  UP JUMP  DOWN DIVE  RIGHT SHIFT COLOR, OR A GAMEPAD\n",
+        );
+    }
+    if settings.mode == GameMode::Race {
+        value.push_str(&format!(
+            "RELAY {}  ROOM {}\n",
+            settings.race_relay.as_deref().unwrap_or("NOT SET"),
+            settings.race_room.as_deref().unwrap_or("DEFAULT")
+        ));
+    }
+    if let Some(campaign) = campaign.filter(|_| settings.mode == GameMode::Campaign) {
+        value.push_str("[ ] CHAPTER\n");
+        for (i, chapter) in campaign.chapters.iter().enumerate() {
+            let status = if !campaign.unlocked(i, &progress) {
+                String::from("LOCKED")
+            } else {
+                format!("{}/{}", cleared(chapter, &progress), chapter.stages.len())
+            };
+            let picked = if i == settings.chapter { ">" } else { " " };
+            value.push_str(&format!(
+                "{picked} {} {:<10} {status}\n",
+                i + 1,
+                chapter.name.to_uppercase()
+            ));
+        }
+    }
+}
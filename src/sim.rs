@@ -0,0 +1,859 @@
+//! Game rules, kept apart from input, rendering and audio so a run can be replayed headlessly.
+
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::adaptive::Adaptive;
+use crate::boss::{boss_arc, boss_pattern, is_boss_ring, BOSS_PATTERNS};
+use crate::level::{Level, LevelRing};
+use crate::mods::Mods;
+use crate::replay::{Replay, ReplayFrame};
+use crate::sampling::{hash_noise, pfract};
+use crate::score::Score;
+
+pub const GAME_SPEED: f32 = 0.08;
+pub const STARTING_LEVEL: u32 = 10;
+pub const COOLDOWN_ANIM_SPEED: f32 = 1.0;
+/// How many rings the wavefront advances per unit of `t`.
+pub const WAVEFRONT_SPEED: f32 = 7.0;
+/// In zen mode, how many rings behind the player the wavefront is pushed after each jump.
+pub const ZEN_WAVEFRONT_PUSHBACK: f32 = 3.0;
+/// Default [`RunConfig::time_attack_target`].
+pub const TIME_ATTACK_TARGET: i32 = 50;
+/// Levels between time attack splits.
+pub const SPLIT_LEVELS: i32 = 10;
+pub const STARTING_LIVES: u32 = 3;
+pub const MAX_LIVES: u32 = 5;
+/// Levels between extra lives.
+pub const EXTRA_LIFE_LEVELS: i32 = 25;
+/// How many rings behind the player the wavefront is pushed after taking a life.
+pub const LIFE_PUSHBACK: f32 = 4.0;
+/// Seconds the wavefront can't take a life after taking one.
+pub const INVULNERABLE_TIME: f32 = 2.0;
+/// [`get_ring_color`] of an arc that can be landed on.
+pub const ARC_SAFE: u32 = 0;
+/// [`get_ring_color`] of an arc that counts as a miss, and costs a life in
+/// [`GameMode::Lives`].
+pub const ARC_HAZARD: u32 = 1;
+/// Arc and orb colors when [`RunConfig::colors`] is on.
+pub const COLOR_COUNT: u32 = 3;
+/// No power-ups before this ring.
+pub const POWER_UP_FIRST_RING: u32 = 14;
+/// Chance of each safe arc holding a power-up.
+pub const POWER_UP_CHANCE: f32 = 0.08;
+/// Seconds [`PowerUp::SlowTime`] lasts.
+pub const SLOW_TIME_DURATION: f32 = 4.0;
+/// Multiplier on the game speed while [`PowerUp::SlowTime`] lasts.
+pub const SLOW_TIME_FACTOR: f32 = 0.5;
+/// How many rings [`PowerUp::Push`] pushes the wavefront back.
+pub const POWER_UP_PUSH: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Normal,
+    /// Uses the practice options in [`RunConfig`]. Never ranked.
+    Practice,
+    /// The wavefront never kills and is pushed back after every jump. The run lasts until
+    /// the player quits. Never ranked.
+    Zen,
+    /// Reach [`RunConfig::time_attack_target`] as fast as possible. Timed against personal bests
+    /// instead of ranked.
+    TimeAttack,
+    /// The wavefront takes a life instead of ending the run, and extra lives are earned
+    /// every [`EXTRA_LIFE_LEVELS`].
+    Lives,
+    /// Chapters of stages, each run won by reaching the stage's [`Goal`]. Never ranked.
+    Campaign,
+    /// Two players on the same rings, see [`Run::second`]. The last orb alive wins. Never
+    /// ranked.
+    Versus,
+    /// Against someone else online, see [`crate::race`]. Never ranked.
+    Race,
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Normal => "NORMAL",
+            GameMode::Practice => "PRACTICE",
+            GameMode::Zen => "ZEN",
+            GameMode::TimeAttack => "TIME ATTACK",
+            GameMode::Lives => "LIVES",
+            GameMode::Campaign => "CAMPAIGN",
+            GameMode::Versus => "VERSUS",
+            GameMode::Race => "ONLINE RACE",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Normal => GameMode::Practice,
+            GameMode::Practice => GameMode::Zen,
+            GameMode::Zen => GameMode::TimeAttack,
+            GameMode::TimeAttack => GameMode::Lives,
+            GameMode::Lives => GameMode::Campaign,
+            GameMode::Campaign => GameMode::Versus,
+            GameMode::Versus => GameMode::Race,
+            GameMode::Race => GameMode::Normal,
+        }
+    }
+
+    /// Whether runs go on the leaderboards.
+    pub fn is_ranked(self) -> bool {
+        matches!(self, GameMode::Normal | GameMode::Lives)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "EASY",
+            Difficulty::Normal => "NORMAL",
+            Difficulty::Hard => "HARD",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    /// Multiplier on [`GAME_SPEED`].
+    pub fn speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+}
+
+/// Which of the game's mechanics a run uses. All of them by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mechanics {
+    /// See [`ARC_HAZARD`].
+    pub hazards: bool,
+    /// See [`SimInput::dive`].
+    pub dive: bool,
+    pub power_ups: bool,
+    /// See [`crate::boss`].
+    pub bosses: bool,
+}
+
+impl Default for Mechanics {
+    fn default() -> Self {
+        Self {
+            hazards: true,
+            dive: true,
+            power_ups: true,
+            bosses: true,
+        }
+    }
+}
+
+impl Mechanics {
+    /// Packed for the shader, matching the `MECHANIC_` constants there.
+    pub fn bits(self) -> u32 {
+        self.hazards as u32
+            | (self.power_ups as u32) << 1
+            | (self.bosses as u32) << 2
+            | (self.dive as u32) << 3
+    }
+}
+
+/// What finishes a run as won, see [`Sim::won`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Goal {
+    Level(i32),
+    Score(u64),
+    /// Seconds of [`Sim::run_time`].
+    Survive(u32),
+}
+
+impl Goal {
+    pub fn describe(self) -> String {
+        match self {
+            Goal::Level(level) => format!("REACH LEVEL {level}"),
+            Goal::Score(score) => format!("SCORE {score}"),
+            Goal::Survive(seconds) => format!("SURVIVE {seconds} SECONDS"),
+        }
+    }
+}
+
+/// Everything that decides how a run plays out, besides the player's input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunConfig {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub seed: u32,
+    /// Level to start on, relative to [`STARTING_LEVEL`].
+    pub start_level: u32,
+    /// Multiplier on [`GAME_SPEED`], in percent.
+    pub speed_percent: u32,
+    /// Holds the wavefront just behind the player instead of letting it catch them.
+    pub invincible: bool,
+    /// Arcs are colored and the player can only land on arcs of the orb's color.
+    pub colors: bool,
+    /// [`Level::id`] of the hand-made level the run is played on.
+    pub level: Option<u64>,
+    pub mechanics: Mechanics,
+    /// Finishes the run once reached.
+    pub goal: Option<Goal>,
+    /// Lets [`crate::adaptive`] tune the speed, arc sizes and miss cooldown to the player.
+    pub adaptive: bool,
+    pub mods: Mods,
+    /// Level [`GameMode::TimeAttack`] runs finish on.
+    pub time_attack_target: i32,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            mode: GameMode::Normal,
+            difficulty: Difficulty::Normal,
+            seed: 0,
+            start_level: 0,
+            speed_percent: 100,
+            invincible: false,
+            colors: false,
+            level: None,
+            mechanics: Mechanics::default(),
+            goal: None,
+            adaptive: false,
+            mods: Mods::default(),
+            time_attack_target: TIME_ATTACK_TARGET,
+        }
+    }
+}
+
+impl RunConfig {
+    /// The config whose leaderboards the run goes on. Mods are left out, since their score
+    /// bonus is what evens runs with and without them out.
+    pub fn ranked_as(self) -> Self {
+        Self {
+            mods: Mods::default(),
+            ..self
+        }
+    }
+
+    /// Splits of a time attack run, one every [`SPLIT_LEVELS`] and the last at the target.
+    pub fn time_attack_splits(&self) -> usize {
+        (self.time_attack_target.max(1) + SPLIT_LEVELS - 1) as usize / SPLIT_LEVELS as usize
+    }
+
+    /// Level the time attack split `i` is taken at.
+    pub fn split_level(&self, i: usize) -> i32 {
+        ((i as i32 + 1) * SPLIT_LEVELS).min(self.time_attack_target)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimInput {
+    pub jump: bool,
+    /// Ends a zen run.
+    pub quit: bool,
+    /// Jumps onto the inner ring.
+    pub dive: bool,
+    /// Cycles the orb's color.
+    pub change_color: bool,
+}
+
+impl SimInput {
+    pub fn to_bits(self) -> u8 {
+        self.jump as u8
+            | (self.quit as u8) << 1
+            | (self.dive as u8) << 2
+            | (self.change_color as u8) << 3
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            jump: bits & 1 != 0,
+            quit: bits & 2 != 0,
+            dive: bits & 4 != 0,
+            change_color: bits & 8 != 0,
+        }
+    }
+}
+
+/// Pickups on arcs, collected by being the first to land on them. Values match
+/// `get_power_up` in the shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUp {
+    SlowTime = 1,
+    Shield = 2,
+    Push = 3,
+}
+
+impl PowerUp {
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUp::SlowTime => "SLOW TIME",
+            PowerUp::Shield => "SHIELD",
+            PowerUp::Push => "PUSH",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JumpResult {
+    Landed {
+        sub_ring: u32,
+        points: u32,
+        power_up: Option<PowerUp>,
+    },
+    /// Landed on the inner ring.
+    Dived {
+        sub_ring: u32,
+    },
+    /// Jumped onto an [`ARC_HAZARD`] arc. Counts as a miss.
+    Hazard {
+        sub_ring: u32,
+    },
+    /// A miss or hazard that [`PowerUp::Shield`] absorbed.
+    Shielded,
+    Missed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sim {
+    pub config: RunConfig,
+    pub t: f32,
+    /// Ring the red wavefront has reached. The player dies once it passes their ring.
+    pub wavefront: f32,
+    /// Simulated seconds the player has been alive.
+    pub run_time: f32,
+    pub player_ring: u32,
+    pub player_offset: f32,
+    pub player_sub_ring: u32,
+    /// See [`RunConfig::colors`].
+    pub player_color: u32,
+    pub move_cooldown: f32,
+    pub dead: bool,
+    /// The player quit to the results in [`GameMode::Zen`], or reached the target in
+    /// [`GameMode::TimeAttack`].
+    pub finished: bool,
+    pub miss: u32,
+    pub score: Score,
+    /// `run_time` at every [`RunConfig::split_level`], for [`GameMode::TimeAttack`].
+    pub splits: Vec<f32>,
+    /// Lives left in [`GameMode::Lives`].
+    pub lives: u32,
+    /// Seconds left before the wavefront can take another life.
+    pub invulnerable: f32,
+    /// Level that earns the next extra life.
+    pub next_extra_life: i32,
+    /// Outermost ring reached. Power-ups only count on rings past it, so each is collected
+    /// once even when diving back.
+    pub highest_ring: u32,
+    /// Seconds of [`PowerUp::SlowTime`] left.
+    pub slow_time: f32,
+    pub shield: bool,
+    /// Boss rings jumped past, see [`crate::boss`].
+    pub bosses_passed: u32,
+    /// Overrides the procedural rings it covers.
+    pub level: Option<Arc<Level>>,
+    pub adaptive: Adaptive,
+}
+
+impl Sim {
+    pub fn new(config: RunConfig) -> Self {
+        Self::with_level(config, None)
+    }
+
+    /// Sets [`RunConfig::level`] to match `level`.
+    pub fn with_level(mut config: RunConfig, level: Option<Arc<Level>>) -> Self {
+        config.level = level.as_ref().map(|level| level.id());
+        Self {
+            config,
+            t: 0.0,
+            wavefront: config.start_level as f32,
+            run_time: 0.0,
+            player_ring: STARTING_LEVEL + config.start_level,
+            player_offset: 0.0,
+            player_sub_ring: 0,
+            player_color: 0,
+            move_cooldown: 0.0,
+            dead: false,
+            finished: false,
+            miss: 0,
+            score: Score::default(),
+            splits: Vec::new(),
+            lives: STARTING_LIVES,
+            invulnerable: 0.0,
+            next_extra_life: config.start_level as i32 + EXTRA_LIFE_LEVELS,
+            highest_ring: STARTING_LEVEL + config.start_level,
+            slow_time: 0.0,
+            shield: false,
+            bosses_passed: 0,
+            level,
+            adaptive: Adaptive::new(&config),
+        }
+    }
+
+    /// The player died or quit.
+    pub fn over(&self) -> bool {
+        self.dead || self.finished
+    }
+
+    pub fn level(&self) -> i32 {
+        self.player_ring as i32 - STARTING_LEVEL as i32
+    }
+
+    pub fn speed(&self) -> f32 {
+        let slow = if self.slow_time > 0.0 {
+            SLOW_TIME_FACTOR
+        } else {
+            1.0
+        };
+        GAME_SPEED
+            * self.config.difficulty.speed()
+            * self.config.speed_percent as f32
+            * 0.01
+            * slow
+            * self.adaptive.speed
+            * self.config.mods.speed()
+    }
+
+    /// Position of the player on their ring, in turns.
+    pub fn player_phase(&self) -> f32 {
+        let (start, _) = self.arc(self.player_ring, self.player_sub_ring);
+        pfract(start + self.player_offset)
+    }
+
+    /// Arcs on `ring`, from the level if it covers it.
+    pub fn arc_count(&self, ring: u32) -> u32 {
+        match self.level_ring(ring) {
+            Some(level_ring) => level_ring.arcs.len() as u32,
+            None if self.is_boss(ring) => BOSS_PATTERNS[boss_pattern(ring)].arcs,
+            None => get_max_arcs(ring),
+        }
+    }
+
+    /// Boss rings the level covers, or without [`Mechanics::bosses`], are plain rings.
+    pub fn is_boss(&self, ring: u32) -> bool {
+        self.config.mechanics.bosses && self.level_ring(ring).is_none() && is_boss_ring(ring)
+    }
+
+    /// Either [`ARC_SAFE`] or [`ARC_HAZARD`].
+    pub fn arc_kind(&self, ring: u32, sub_ring: u32) -> u32 {
+        match self.level_ring(ring) {
+            _ if !self.config.mechanics.hazards => ARC_SAFE,
+            Some(level_ring) if level_ring.arcs[sub_ring as usize].hazard => ARC_HAZARD,
+            Some(_) => ARC_SAFE,
+            None => get_ring_color(ring, sub_ring, self.config.seed),
+        }
+    }
+
+    pub fn arc_color(&self, ring: u32, sub_ring: u32) -> u32 {
+        match self.level_ring(ring) {
+            Some(level_ring) => level_ring.arcs[sub_ring as usize].color,
+            None => get_arc_color(ring, sub_ring, self.config.seed),
+        }
+    }
+
+    /// Levels don't place power-ups.
+    pub fn power_up(&self, ring: u32, sub_ring: u32) -> Option<PowerUp> {
+        if self.level_ring(ring).is_some() || !self.config.mechanics.power_ups {
+            return None;
+        }
+        get_power_up(ring, sub_ring, self.config.seed)
+    }
+
+    /// The run finished by reaching its [`RunConfig::goal`].
+    pub fn won(&self) -> bool {
+        self.finished && self.goal_reached()
+    }
+
+    fn goal_reached(&self) -> bool {
+        match self.config.goal {
+            Some(Goal::Level(level)) => self.level() >= level,
+            Some(Goal::Score(score)) => self.score.points >= score,
+            Some(Goal::Survive(seconds)) => self.run_time >= seconds as f32,
+            None => false,
+        }
+    }
+
+    fn level_ring(&self, ring: u32) -> Option<&LevelRing> {
+        self.level.as_ref()?.ring(ring)
+    }
+
+    /// Multiplier on every arc's size, from adaptive difficulty and mods.
+    pub fn arc_scale(&self) -> f32 {
+        self.adaptive.arc_size * self.config.mods.arc_size()
+    }
+
+    /// Start and size of an arc, in turns.
+    pub fn arc(&self, ring: u32, sub_ring: u32) -> (f32, f32) {
+        let seed = self.config.seed;
+        // Mirror turns every arc the other way.
+        let direction = if self.config.mods.mirror { -1.0 } else { 1.0 };
+        let (start, size) = if let Some(level_ring) = self.level_ring(ring) {
+            // Level arcs move at a constant speed, so running time backwards reverses them.
+            level_ring.arcs[sub_ring as usize].at(self.t * direction)
+        } else if self.is_boss(ring) {
+            boss_arc(ring, sub_ring, seed, self.t, direction)
+        } else {
+            let speed = direction * get_ring_speed(ring, sub_ring, seed);
+            let start = pfract(self.t * (speed * (ring + 1) as f32));
+            (start, get_arc_size(ring, sub_ring, seed))
+        };
+        (start, size * self.arc_scale())
+    }
+
+    /// Advances the simulation by `dt` seconds, then applies `input`.
+    pub fn step(&mut self, dt: f32, input: SimInput) -> Option<JumpResult> {
+        let zen = self.config.mode == GameMode::Zen;
+        if self.wavefront > (self.player_ring + 1) as f32
+            && !zen
+            && self.invulnerable == 0.0
+            && self.lose_life()
+        {
+            self.wavefront = self.player_ring as f32 - LIFE_PUSHBACK;
+            self.invulnerable = INVULNERABLE_TIME;
+        }
+        self.invulnerable = (self.invulnerable - dt).max(0.0);
+        self.slow_time = (self.slow_time - dt).max(0.0);
+        if input.quit && zen {
+            self.finished = true;
+        }
+        let speed = if self.over() {
+            self.speed() * 0.3
+        } else {
+            self.run_time += dt;
+            self.speed()
+        };
+        self.t += dt * speed;
+        self.wavefront += dt * speed * WAVEFRONT_SPEED;
+        if self.config.invincible || zen {
+            self.wavefront = self.wavefront.min(self.player_ring as f32);
+        }
+
+        if input.change_color && self.config.colors && !self.over() {
+            self.player_color = (self.player_color + 1) % COLOR_COUNT;
+        }
+        let can_move = !self.over() && self.move_cooldown == 1.0;
+        let result = if input.jump && can_move {
+            Some(self.jump())
+        } else if input.dive
+            && can_move
+            && self.config.mechanics.dive
+            && self.player_ring > STARTING_LEVEL
+        {
+            Some(self.dive())
+        } else {
+            None
+        };
+        if zen && matches!(result, Some(JumpResult::Landed { .. })) {
+            let pushed_back = self.player_ring as f32 - ZEN_WAVEFRONT_PUSHBACK;
+            self.wavefront = self.wavefront.min(pushed_back);
+        }
+        if self.config.mode == GameMode::Lives
+            && matches!(result, Some(JumpResult::Landed { .. }))
+            && self.level() >= self.next_extra_life
+        {
+            self.lives = (self.lives + 1).min(MAX_LIVES);
+            self.next_extra_life += EXTRA_LIFE_LEVELS;
+        }
+        if self.config.mode == GameMode::TimeAttack && result.is_some() {
+            let level = self.level();
+            if level >= self.config.split_level(self.splits.len()) {
+                self.splits.push(self.run_time);
+            }
+            if level >= self.config.time_attack_target {
+                self.finished = true;
+            }
+        }
+        if !self.over() && self.goal_reached() {
+            self.finished = true;
+        }
+        if let Some(result) = result {
+            let precision = matches!(result, JumpResult::Landed { .. }).then(|| {
+                let (_, size) = self.arc(self.player_ring, self.player_sub_ring);
+                1.0 - (self.player_offset / size * 2.0 - 1.0).abs()
+            });
+            let margin = (self.player_ring + 1) as f32 - self.wavefront;
+            self.adaptive.observe(result, precision, margin);
+        }
+        if !self.over() {
+            self.adaptive.update(dt, self.run_time);
+        }
+
+        let cooldown_speed = COOLDOWN_ANIM_SPEED / self.adaptive.cooldown;
+        self.move_cooldown = (self.move_cooldown + dt * cooldown_speed).min(1.0);
+        result
+    }
+
+    fn jump(&mut self) -> JumpResult {
+        let next_ring = self.player_ring + 1;
+        match self.move_to(next_ring) {
+            Ok(sub_ring) => {
+                let points = self.score.hit(next_ring, self.config.mods.score_percent());
+                let mut power_up = None;
+                if next_ring > self.highest_ring {
+                    self.highest_ring = next_ring;
+                    if self.is_boss(self.player_ring - 1) {
+                        self.bosses_passed += 1;
+                    }
+                    power_up = self.power_up(next_ring, sub_ring);
+                }
+                match power_up {
+                    Some(PowerUp::SlowTime) => self.slow_time = SLOW_TIME_DURATION,
+                    Some(PowerUp::Shield) => self.shield = true,
+                    Some(PowerUp::Push) => self.wavefront -= POWER_UP_PUSH,
+                    None => (),
+                }
+                JumpResult::Landed {
+                    sub_ring,
+                    points,
+                    power_up,
+                }
+            }
+            Err(hazard) => self.missed(hazard),
+        }
+    }
+
+    /// Moving inward breaks the combo, on top of bringing the player closer to the wavefront.
+    fn dive(&mut self) -> JumpResult {
+        match self.move_to(self.player_ring - 1) {
+            Ok(sub_ring) => {
+                self.score.miss();
+                JumpResult::Dived { sub_ring }
+            }
+            Err(hazard) => self.missed(hazard),
+        }
+    }
+
+    /// Moves onto whichever safe arc of `ring` is under the player, skipping arcs of the wrong
+    /// color. Otherwise returns the hazard arc under the player, if any.
+    fn move_to(&mut self, ring: u32) -> Result<u32, Option<u32>> {
+        let this_p = self.player_phase();
+        let mut hazard = None;
+        for sub_ring in 0..self.arc_count(ring) {
+            let (start, size) = self.arc(ring, sub_ring);
+            let within = pfract(this_p - start);
+            if within >= size {
+                continue;
+            }
+            if self.arc_kind(ring, sub_ring) == ARC_HAZARD {
+                hazard = hazard.or(Some(sub_ring));
+                continue;
+            }
+            if self.config.colors && self.arc_color(ring, sub_ring) != self.player_color {
+                continue;
+            }
+            self.player_offset = within;
+            self.player_ring = ring;
+            self.player_sub_ring = sub_ring;
+            return Ok(sub_ring);
+        }
+        Err(hazard)
+    }
+
+    fn missed(&mut self, hazard: Option<u32>) -> JumpResult {
+        self.move_cooldown = 0.0;
+        if self.shield {
+            self.shield = false;
+            return JumpResult::Shielded;
+        }
+        self.miss += 1;
+        self.score.miss();
+        match hazard {
+            Some(sub_ring) => {
+                if self.config.mode == GameMode::Lives {
+                    self.lose_life();
+                }
+                JumpResult::Hazard { sub_ring }
+            }
+            None => JumpResult::Missed,
+        }
+    }
+
+    /// Takes a life in [`GameMode::Lives`], or ends the run. Returns whether the player
+    /// survived.
+    fn lose_life(&mut self) -> bool {
+        if self.config.mode == GameMode::Lives && self.lives > 1 {
+            self.lives -= 1;
+            true
+        } else {
+            self.lives = 0;
+            self.dead = true;
+            false
+        }
+    }
+}
+
+/// The run being played, with every simulated frame recorded for its replay.
+#[derive(Resource, Clone, Debug)]
+pub struct Run {
+    pub sim: Sim,
+    pub replay: Replay,
+    /// Practice checkpoint. Retrying from it breaks the replay, which is fine since practice
+    /// runs aren't saved.
+    pub checkpoint: Option<Sim>,
+    /// The second player's orb in [`GameMode::Versus`], on the same rings as `sim`. Only the
+    /// first player is kept in the replay.
+    pub second: Option<Sim>,
+}
+
+impl Run {
+    pub fn new(config: RunConfig) -> Self {
+        Self::with_level(config, None)
+    }
+
+    pub fn with_level(mut config: RunConfig, level: Option<Arc<Level>>) -> Self {
+        let versus = config.mode == GameMode::Versus;
+        if versus {
+            // Both change how fast one player's rings move, and the rings are shared.
+            config.mechanics.power_ups = false;
+            config.adaptive = false;
+        }
+        let sim = Sim::with_level(config, level);
+        Self {
+            replay: Replay::new(sim.config),
+            second: versus.then(|| sim.clone()),
+            sim,
+            checkpoint: None,
+        }
+    }
+
+    /// Goes back to the checkpoint, or to the start if there is none.
+    pub fn retry(&mut self) {
+        self.sim = match &self.checkpoint {
+            Some(checkpoint) => checkpoint.clone(),
+            None => Sim::with_level(self.sim.config, self.sim.level.clone()),
+        };
+    }
+
+    /// Every player's orb, the first player's first.
+    pub fn players(&self) -> impl Iterator<Item = &Sim> {
+        std::iter::once(&self.sim).chain(&self.second)
+    }
+
+    /// In [`GameMode::Versus`], the player whose orb outlasted the other's. `None` while both
+    /// are alive, or when both died on the same frame.
+    pub fn winner(&self) -> Option<usize> {
+        let second = self.second.as_ref()?;
+        match (self.sim.dead, second.dead) {
+            (false, true) => Some(0),
+            (true, false) => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Steps every player, see [`Run::players`]. Once one of two players dies, the other's run
+    /// is finished as the winner.
+    pub fn step_players(&mut self, dt: f32, inputs: [SimInput; 2]) -> [Option<JumpResult>; 2] {
+        let first = self.step(dt, inputs[0]);
+        let Some(second) = &mut self.second else {
+            return [first, None];
+        };
+        let results = [first, second.step(dt, inputs[1])];
+        if self.sim.dead && !second.over() {
+            second.finished = true;
+        }
+        if second.dead && !self.sim.over() {
+            self.sim.finished = true;
+        }
+        // A player that died slowed down on that frame already, so the rings are lined up
+        // again for the slow motion after the run.
+        if self.sim.over() {
+            second.t = self.sim.t;
+        }
+        results
+    }
+
+    /// Also logs adaptive difficulty adjustments, and keeps them with the replay.
+    pub fn step(&mut self, dt: f32, input: SimInput) -> Option<JumpResult> {
+        self.replay.frames.push(ReplayFrame { dt, input });
+        let result = self.sim.step(dt, input);
+        let log = &self.sim.adaptive.log;
+        if log.len() > self.replay.adjustments.len() {
+            let adjustment = log[log.len() - 1];
+            info!("adaptive difficulty at {}", adjustment.describe());
+            self.replay.adjustments.push(adjustment);
+        }
+        result
+    }
+}
+
+pub fn get_max_arcs(ring: u32) -> u32 {
+    ((ring as i32 - 16).max(0) as u32 / 4).clamp(2, 6)
+}
+
+/// [`get_max_arcs`], or the pattern's arcs on boss rings.
+pub fn get_arc_count(ring: u32) -> u32 {
+    if is_boss_ring(ring) {
+        BOSS_PATTERNS[boss_pattern(ring)].arcs
+    } else {
+        get_max_arcs(ring)
+    }
+}
+
+pub fn get_arc_size(ring: u32, level: u32, seed: u32) -> f32 {
+    (hash_noise(ring, level, seed) * 0.2 + 0.2) / (((ring + 1) as f32) * 0.13 + 2.0)
+}
+
+/// Either [`ARC_SAFE`] or [`ARC_HAZARD`]. The first arc of a ring, and boss rings, are always
+/// safe.
+pub fn get_ring_color(ring: u32, level: u32, seed: u32) -> u32 {
+    if level != 0
+        && !is_boss_ring(ring)
+        && hash_noise(ring + 2048, level, seed) < get_hazard_chance(ring)
+    {
+        ARC_HAZARD
+    } else {
+        ARC_SAFE
+    }
+}
+
+/// Color of an arc when [`RunConfig::colors`] is on, below [`COLOR_COUNT`].
+pub fn get_arc_color(ring: u32, level: u32, seed: u32) -> u32 {
+    ((hash_noise(ring + 4096, level, seed) * COLOR_COUNT as f32) as u32).min(COLOR_COUNT - 1)
+}
+
+/// Power-up on an arc, if any. Never on hazards or boss rings.
+pub fn get_power_up(ring: u32, level: u32, seed: u32) -> Option<PowerUp> {
+    if ring < POWER_UP_FIRST_RING
+        || is_boss_ring(ring)
+        || get_ring_color(ring, level, seed) == ARC_HAZARD
+    {
+        return None;
+    }
+    let noise = hash_noise(ring + 8192, level, seed);
+    if noise >= POWER_UP_CHANCE {
+        return None;
+    }
+    Some(match (noise / POWER_UP_CHANCE * 3.0) as u32 {
+        0 => PowerUp::SlowTime,
+        1 => PowerUp::Shield,
+        _ => PowerUp::Push,
+    })
+}
+
+/// Chance of each arc after the first being a hazard. None before ring 20.
+pub fn get_hazard_chance(ring: u32) -> f32 {
+    ((ring as f32 - 20.0) * 0.015).clamp(0.0, 0.4)
+}
+
+pub fn get_ring_speed(ring: u32, level: u32, seed: u32) -> f32 {
+    ((hash_noise(ring, level, seed) * 1.0 + 0.8) / ((ring + 1) as f32))
+        * (1.0 + ring as f32 * 0.0)
+        * if ring % 2 == 0 { -1.0 } else { 1.0 }
+}
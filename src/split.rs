@@ -0,0 +1,129 @@
+//! Split-screen for [`GameMode::Versus`], when [`Settings::split_screen`] is on: each player gets
+//! half of the window, with their own camera and [`DataMaterial`] following their orb. Both views
+//! are copies of the game state in [`GameMaterial`], which is hidden meanwhile. Otherwise both
+//! orbs share the one full screen field.
+
+use bevy::core_pipeline::fxaa::{Fxaa, Sensitivity};
+use bevy::math::*;
+use bevy::prelude::*;
+use bevy::render::camera::{ClearColorConfig, Viewport};
+use bevy::render::view::RenderLayers;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::settings::Settings;
+use crate::sim::Run;
+use crate::{DataMaterial, GameMaterial, GameQuad};
+
+/// Marks the camera and quad of a player's view, by player index.
+#[derive(Component)]
+pub struct SplitView(usize);
+
+#[derive(Resource)]
+pub struct SplitMaterials([Handle<DataMaterial>; 2]);
+
+pub fn setup_split_views(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let handles = [0, 1].map(|player| {
+        // Layer 0 is the full screen game quad.
+        let layer = RenderLayers::layer(player + 1);
+        let material = materials.add(DataMaterial::default());
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    // Drawn under the full screen camera, which keeps the UI.
+                    order: player as isize - 2,
+                    is_active: false,
+                    ..default()
+                },
+                ..default()
+            },
+            Fxaa {
+                enabled: true,
+                edge_threshold: Sensitivity::Ultra,
+                edge_threshold_min: Sensitivity::Ultra,
+            },
+            layer.clone(),
+            SplitView(player),
+        ));
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(Triangle2d::default()).into(),
+                transform: Transform::from_translation(vec3(0.0, 0.0, -100.0)),
+                material: material.clone(),
+                ..default()
+            },
+            layer,
+            SplitView(player),
+        ));
+        material
+    });
+    commands.insert_resource(SplitMaterials(handles));
+}
+
+/// Turns split-screen on for versus runs if the player picked it, and copies the game state into
+/// each view, centered on its player and sized to its half of the window.
+pub fn update_split_views(
+    run: Res<Run>,
+    settings: Res<Settings>,
+    window: Query<&Window>,
+    split: Res<SplitMaterials>,
+    game_material: Res<GameMaterial>,
+    mut materials: ResMut<Assets<DataMaterial>>,
+    mut cameras: Query<(&mut Camera, Option<&SplitView>)>,
+    mut game_quad: Query<&mut Visibility, With<GameQuad>>,
+) {
+    let window = window.single();
+    let split_screen = run.second.is_some() && settings.split_screen;
+    let size = window.physical_size();
+    // The right view gets the odd pixel.
+    let widths = [size.x / 2, size.x - size.x / 2];
+    for (mut camera, view) in &mut cameras {
+        match view {
+            Some(&SplitView(player)) => {
+                camera.is_active = split_screen;
+                camera.viewport = Some(Viewport {
+                    physical_position: uvec2(widths[0] * player as u32, 0),
+                    physical_size: uvec2(widths[player].max(1), size.y.max(1)),
+                    ..default()
+                });
+            }
+            // Draws the UI over the views without clearing them.
+            None => {
+                camera.clear_color = if split_screen {
+                    ClearColorConfig::None
+                } else {
+                    ClearColorConfig::Default
+                };
+            }
+        }
+    }
+    *game_quad.single_mut() = if split_screen {
+        Visibility::Hidden
+    } else {
+        Visibility::Inherited
+    };
+    if !split_screen {
+        return;
+    }
+
+    let Some(game) = materials.get(&game_material.0) else {
+        return;
+    };
+    let game = game.state.clone();
+    for (player, handle) in split.0.iter().enumerate() {
+        let Some(view) = materials.get_mut(handle) else {
+            continue;
+        };
+        let state = &mut view.state;
+        *state = game.clone();
+        let physical = uvec2(widths[player], size.y).as_vec2();
+        let logical = physical / window.scale_factor();
+        state.resolution = vec4(physical.x, physical.y, logical.x, logical.y);
+        state.viewport = vec4((widths[0] * player as u32) as f32, 0.0, 0.0, 0.0);
+        state.scale_factor = window.scale_factor();
+        state.follow_player(&run, player);
+    }
+}
@@ -0,0 +1,152 @@
+//! Save files. Native builds keep them in `saves/` under the working directory,
+//! wasm builds and headless apps don't persist anything.
+//!
+//! The asset server reads save files as `saves://name`, which is how levels saved by the editor
+//! get loaded, see [`add_asset_source`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DIR: &str = "saves";
+/// Asset source of the save directory.
+pub const ASSET_SOURCE: &str = "saves";
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Stops reading and writing save files for the rest of the process, so headless apps start
+/// from defaults and leave the player's saves alone.
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn disabled() -> bool {
+    DISABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let text = String::from_utf8(load_bytes(name)?).ok()?;
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            bevy::log::warn!("Failed to parse {name}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    match ron::ser::to_string_pretty(value, Default::default()) {
+        Ok(text) => save_bytes(name, text.as_bytes()),
+        Err(e) => bevy::log::warn!("Failed to serialize {name}: {e}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_bytes(name: &str) -> Option<Vec<u8>> {
+    if disabled() {
+        return None;
+    }
+    std::fs::read(std::path::Path::new(SAVE_DIR).join(name)).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_bytes(name: &str, bytes: &[u8]) {
+    if let Err(e) = try_save_bytes(name, bytes) {
+        bevy::log::warn!("Failed to save {name}: {e}");
+    }
+}
+
+/// [`save_bytes`], for callers that show the player why a save failed.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn try_save_bytes(name: &str, bytes: &[u8]) -> std::io::Result<()> {
+    if disabled() {
+        return Ok(());
+    }
+    let path = std::path::Path::new(SAVE_DIR).join(name);
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    std::fs::write(path, bytes)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(name: &str) {
+    if disabled() {
+        return;
+    }
+    let _ = std::fs::remove_file(std::path::Path::new(SAVE_DIR).join(name));
+}
+
+/// Registers [`ASSET_SOURCE`], before the asset plugin is added.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn add_asset_source(app: &mut bevy::app::App) {
+    use bevy::asset::io::{file::FileAssetReader, AssetSource};
+    use bevy::asset::AssetApp;
+
+    // The asset server resolves relative paths against the assets' root, not the working
+    // directory the saves are in.
+    let dir = std::env::current_dir().unwrap_or_default().join(SAVE_DIR);
+    app.register_asset_source(
+        ASSET_SOURCE,
+        AssetSource::build().with_reader(move || Box::new(FileAssetReader::new(dir.clone()))),
+    );
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load<T: DeserializeOwned>(_name: &str) -> Option<T> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save<T: Serialize>(_name: &str, _value: &T) {}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_bytes(_name: &str) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_bytes(_name: &str, _bytes: &[u8]) {}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(_name: &str) {}
+
+#[cfg(target_arch = "wasm32")]
+pub fn try_save_bytes(_name: &str, _bytes: &[u8]) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn add_asset_source(_app: &mut bevy::app::App) {}
+
+/// Seconds since the unix epoch, or 0 where there's no wall clock.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn unix_now() -> u64 {
+    0
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD`.
+pub fn format_date(unix: u64) -> String {
+    // Days to civil date, from http://howardhinnant.github.io/date_algorithms.html
+    let z = (unix / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + (m <= 2) as i64;
+    format!("{y:04}-{m:02}-{d:02}")
+}
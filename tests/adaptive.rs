@@ -0,0 +1,125 @@
+use lib_Sol::adaptive::{ARC_SIZE_RANGE, COOLDOWN_RANGE, SPEED_RANGE};
+use lib_Sol::autoplay::Autoplay;
+use lib_Sol::replay::Replay;
+use lib_Sol::sim::{Run, RunConfig};
+
+/// Lets a bot play until it dies or three minutes pass.
+fn play(skill: f32, seed: u32, adaptive: bool) -> Run {
+    let mut run = Run::new(RunConfig {
+        seed,
+        adaptive,
+        ..Default::default()
+    });
+    let mut bot = Autoplay::new(skill, seed + 100);
+    for _ in 0..60 * 180 {
+        if run.sim.over() {
+            break;
+        }
+        let input = bot.input(&run.sim);
+        run.step(1.0 / 60.0, input);
+    }
+    run
+}
+
+#[test]
+fn struggling_players_get_an_easier_run() {
+    let (mut fixed_time, mut adaptive_time) = (0.0, 0.0);
+    for seed in 0..8 {
+        fixed_time += play(0.1, seed, false).sim.run_time;
+        let run = play(0.1, seed, true);
+        adaptive_time += run.sim.run_time;
+        let adaptive = &run.sim.adaptive;
+        assert!(
+            adaptive.speed < 1.0,
+            "seed {seed}: speed {}",
+            adaptive.speed
+        );
+        assert!(
+            adaptive.arc_size > 1.0,
+            "seed {seed}: arcs {}",
+            adaptive.arc_size
+        );
+        assert!(
+            adaptive.cooldown < 1.0,
+            "seed {seed}: cooldown {}",
+            adaptive.cooldown
+        );
+    }
+    assert!(
+        adaptive_time > fixed_time * 1.3,
+        "survived {adaptive_time:.0}s with adaptive difficulty, {fixed_time:.0}s without"
+    );
+}
+
+#[test]
+fn sharp_players_get_a_harder_run() {
+    for seed in 0..4 {
+        let run = play(1.0, seed, true);
+        let adaptive = &run.sim.adaptive;
+        assert!(
+            adaptive.speed > 1.1,
+            "seed {seed}: speed {}",
+            adaptive.speed
+        );
+        assert!(
+            adaptive.arc_size < 0.9,
+            "seed {seed}: arcs {}",
+            adaptive.arc_size
+        );
+        assert!(
+            adaptive.cooldown > 1.1,
+            "seed {seed}: cooldown {}",
+            adaptive.cooldown
+        );
+    }
+}
+
+#[test]
+fn adjustments_stay_in_bounds_and_are_replayed() {
+    let within = |value: f32, (low, high): (f32, f32)| (low..=high).contains(&value);
+    for skill in [0.0, 0.5, 1.0] {
+        let run = play(skill, 3, true);
+        let replay = &run.replay;
+        assert!(!replay.adjustments.is_empty(), "skill {skill}");
+        assert_eq!(replay.adjustments, run.sim.adaptive.log);
+        for adjustment in &replay.adjustments {
+            assert!(within(adjustment.speed, SPEED_RANGE), "{adjustment:?}");
+            assert!(
+                within(adjustment.arc_size, ARC_SIZE_RANGE),
+                "{adjustment:?}"
+            );
+            assert!(
+                within(adjustment.cooldown, COOLDOWN_RANGE),
+                "{adjustment:?}"
+            );
+        }
+
+        let loaded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(&loaded, replay);
+        let sim = loaded.play();
+        assert!(loaded.adjustments_match(&sim));
+        assert_eq!(sim.score.points, run.sim.score.points);
+    }
+}
+
+#[test]
+fn off_unless_enabled() {
+    let run = play(0.0, 1, false);
+    let adaptive = &run.sim.adaptive;
+    assert_eq!(
+        (adaptive.speed, adaptive.arc_size, adaptive.cooldown),
+        (1.0, 1.0, 1.0)
+    );
+    assert!(run.replay.adjustments.is_empty());
+}
+
+#[test]
+fn reads_replays_from_before_adjustments() {
+    let run = play(0.5, 2, false);
+    let mut bytes = run.replay.to_bytes();
+    // Version 1 ends right after the frames, without an adjustment count.
+    bytes[4..6].copy_from_slice(&1u16.to_le_bytes());
+    bytes.truncate(bytes.len() - 4);
+    let loaded = Replay::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, run.replay);
+}
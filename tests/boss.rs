@@ -0,0 +1,112 @@
+mod common;
+
+use common::*;
+use lib_Sol::boss::*;
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::sampling::pfract;
+use lib_Sol::sim::*;
+
+const FIRST_BOSS: u32 = STARTING_LEVEL + BOSS_RING_INTERVAL;
+
+fn config(bosses: bool) -> RunConfig {
+    RunConfig {
+        seed: 8,
+        mechanics: Mechanics {
+            bosses,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn every_few_rings_is_a_boss_cycling_through_the_patterns() {
+    let bosses: Vec<u32> = (0..100).filter(|&ring| is_boss_ring(ring)).collect();
+    let expected: Vec<u32> = (1..)
+        .map(|i| STARTING_LEVEL + i * BOSS_RING_INTERVAL)
+        .take_while(|&ring| ring < 100)
+        .collect();
+    assert_eq!(bosses, expected);
+    let patterns: Vec<usize> = bosses.iter().map(|&ring| boss_pattern(ring)).collect();
+    assert_eq!(patterns, [0, 1, 2, 0, 1]);
+}
+
+#[test]
+fn boss_arcs_follow_their_pattern() {
+    let mut sim = Sim::new(config(true));
+    let pattern = &BOSS_PATTERNS[boss_pattern(FIRST_BOSS)];
+    assert!(sim.is_boss(FIRST_BOSS));
+    assert_eq!(sim.arc_count(FIRST_BOSS), pattern.arcs);
+    for t in [0.0, 0.3, 1.7, 12.5] {
+        sim.t = t;
+        let (first, size) = sim.arc(FIRST_BOSS, 0);
+        assert!((size - pattern.size(t)).abs() < 1e-6);
+        for sub_ring in 1..pattern.arcs {
+            let (start, _) = sim.arc(FIRST_BOSS, sub_ring);
+            let spacing = pfract(start - first);
+            assert!((spacing - sub_ring as f32 / pattern.arcs as f32).abs() < 1e-4);
+            assert_eq!(sim.arc_kind(FIRST_BOSS, sub_ring), ARC_SAFE);
+        }
+    }
+}
+
+#[test]
+fn boss_arcs_move_smoothly_across_keys_and_loops() {
+    for pattern in &BOSS_PATTERNS {
+        let fastest = pattern
+            .keys
+            .iter()
+            .map(|k| k.speed.abs())
+            .fold(0.0, f32::max);
+        let dt = 0.001;
+        for step in 0..3000 {
+            let t = step as f32 * dt;
+            let moved = pfract(pattern.travel(t + dt) - pattern.travel(t) + 0.5) - 0.5;
+            assert!(
+                moved.abs() <= fastest * dt + 1e-4,
+                "{} at {t}",
+                pattern.name
+            );
+        }
+    }
+}
+
+#[test]
+fn jumping_past_a_boss_counts_once() {
+    let mut sim = Sim::new(RunConfig {
+        start_level: BOSS_RING_INTERVAL,
+        ..config(true)
+    });
+    assert_eq!(sim.player_ring, FIRST_BOSS);
+    land(&mut sim);
+    assert_eq!(sim.bosses_passed, 1);
+
+    // Diving back onto the boss ring and jumping past it again doesn't count.
+    let dive = SimInput {
+        dive: true,
+        ..Default::default()
+    };
+    while sim.player_ring > FIRST_BOSS {
+        assert!(!sim.over());
+        let mut attempt = sim.clone();
+        if let Some(JumpResult::Dived { .. }) = attempt.step(FRAME_DT, dive) {
+            sim = attempt;
+        } else {
+            sim.step(FRAME_DT, SimInput::default());
+        }
+    }
+    land(&mut sim);
+    assert_eq!(sim.bosses_passed, 1);
+}
+
+#[test]
+fn bosses_can_be_turned_off() {
+    let mut sim = Sim::new(RunConfig {
+        start_level: BOSS_RING_INTERVAL,
+        ..config(false)
+    });
+    assert!(!sim.is_boss(FIRST_BOSS));
+    assert_eq!(sim.arc_count(FIRST_BOSS), get_max_arcs(FIRST_BOSS));
+    land(&mut sim);
+    assert_eq!(sim.bosses_passed, 0);
+}
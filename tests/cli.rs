@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use lib_Sol::autoplay::Autoplay;
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::replay::{Replay, ReplayFrame};
+use lib_Sol::sim::{Difficulty, RunConfig, Sim};
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sol-cli-{}-{name}", std::process::id()))
+}
+
+/// Runs the game with `args` and returns what it printed.
+fn sol(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_Sol"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The number on the results line starting with `label`.
+fn result(results: &str, label: &str) -> i64 {
+    let line = results
+        .lines()
+        .find(|line| line.starts_with(label))
+        .unwrap_or_else(|| panic!("no {label} in {results:?}"));
+    line.split_whitespace().last().unwrap().parse().unwrap()
+}
+
+fn assert_results(results: &str, sim: &Sim) {
+    assert_eq!(result(results, "LEVEL"), sim.level() as i64);
+    assert_eq!(result(results, "MISSED JUMPS"), sim.miss as i64);
+    assert_eq!(result(results, "SCORE"), sim.score.points as i64);
+    assert_eq!(result(results, "BOSS RINGS"), sim.bosses_passed as i64);
+}
+
+#[test]
+fn headless_replays_print_their_results() {
+    let config = RunConfig {
+        seed: 11,
+        ..Default::default()
+    };
+    let mut sim = Sim::new(config);
+    let mut replay = Replay::new(sim.config);
+    let mut bot = Autoplay::new(0.2, 1);
+    while !sim.over() {
+        let input = bot.input(&sim);
+        replay.frames.push(ReplayFrame {
+            dt: FRAME_DT,
+            input,
+        });
+        sim.step(FRAME_DT, input);
+    }
+    assert!(sim.level() > 0);
+    let path = temp_file("played.solr");
+    std::fs::write(&path, replay.to_bytes()).unwrap();
+
+    let results = sol(&["--headless", "--replay", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_results(&results, &sim);
+}
+
+#[test]
+fn headless_autoplay_records_the_run() {
+    let path = temp_file("recorded.solr");
+    let results = sol(&[
+        "--headless",
+        "--skill",
+        "0.2",
+        "--seed",
+        "5",
+        "--difficulty",
+        "hard",
+        "--mute",
+        "--record",
+        path.to_str().unwrap(),
+    ]);
+    let replay = Replay::from_bytes(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.config.seed, 5);
+    assert_eq!(replay.config.difficulty, Difficulty::Hard);
+    assert_results(&results, &replay.play());
+}
+
+/// Runs the game with `args` that it should refuse, and returns what it printed to stderr.
+fn refused(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_Sol"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn unknown_options_are_refused() {
+    let stderr = refused(&["--fast"]);
+    assert!(stderr.starts_with("unknown option --fast\n"), "{stderr}");
+    assert!(stderr.contains("usage: Sol [OPTIONS]"), "{stderr}");
+}
+
+#[test]
+fn bad_option_values_are_refused() {
+    let stderr = refused(&["--seed", "many"]);
+    assert!(stderr.starts_with("--seed takes a number\n"), "{stderr}");
+    let stderr = refused(&["--difficulty", "brutal"]);
+    assert!(
+        stderr.starts_with("unknown difficulty brutal\n"),
+        "{stderr}"
+    );
+    let stderr = refused(&["--headless"]);
+    assert!(stderr.starts_with("--headless needs"), "{stderr}");
+    let missing = temp_file("missing.solr");
+    let stderr = refused(&["--replay", missing.to_str().unwrap()]);
+    assert!(stderr.starts_with("can't read replay"), "{stderr}");
+}
@@ -0,0 +1,66 @@
+//! Helpers shared by the tests that drive a [`Sim`] frame by frame.
+#![allow(dead_code)]
+
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::sim::*;
+
+pub const JUMP: SimInput = SimInput {
+    jump: true,
+    quit: false,
+    dive: false,
+    change_color: false,
+};
+pub const DIVE: SimInput = SimInput {
+    jump: false,
+    quit: false,
+    dive: true,
+    change_color: false,
+};
+pub const IDLE: SimInput = SimInput {
+    jump: false,
+    quit: false,
+    dive: false,
+    change_color: false,
+};
+
+/// Steps `sim` with no input until `input` on a frame gives a result `wanted` accepts, and keeps
+/// that frame. `None` if it never does within ten seconds.
+pub fn try_when(
+    sim: &mut Sim,
+    input: SimInput,
+    wanted: impl Fn(&JumpResult) -> bool,
+) -> Option<JumpResult> {
+    for _ in 0..60 * 10 {
+        let mut attempt = sim.clone();
+        if let Some(result) = attempt.step(FRAME_DT, input).filter(&wanted) {
+            *sim = attempt;
+            return Some(result);
+        }
+        sim.step(FRAME_DT, IDLE);
+    }
+    None
+}
+
+pub fn act_when(
+    sim: &mut Sim,
+    input: SimInput,
+    wanted: impl Fn(&JumpResult) -> bool,
+) -> JumpResult {
+    try_when(sim, input, wanted).unwrap_or_else(|| panic!("no {input:?} went as wanted"))
+}
+
+/// Steps `sim` with no input until jumping lands, and keeps that frame.
+pub fn land(sim: &mut Sim) -> JumpResult {
+    act_when(sim, JUMP, |result| {
+        matches!(result, JumpResult::Landed { .. })
+    })
+}
+
+/// Steps `sim` with no input for `seconds`, or until the run is over.
+pub fn wait(sim: &mut Sim, seconds: f32) {
+    let mut waited = 0.0;
+    while waited < seconds && !sim.over() {
+        sim.step(FRAME_DT, IDLE);
+        waited += FRAME_DT;
+    }
+}
@@ -0,0 +1,153 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use lib_Sol::gym::*;
+use lib_Sol::sim::RunConfig;
+use serde_json::Value;
+
+/// Waits until the player can jump and is or isn't under a safe arc of the next ring, with some
+/// margin.
+fn wait_until(env: &mut Env, under_arc: bool) -> Observation {
+    let mut observation = env.reset(3);
+    for _ in 0..600 {
+        let margin = observation.rings[0]
+            .iter()
+            .filter(|window| window.size > 0.0 && !window.hazard)
+            .map(|window| window.size * 0.5 - window.offset.abs())
+            .fold(f32::MIN, f32::max);
+        if observation.cooldown == 1.0 && (margin > 0.01) == under_arc && margin.abs() > 0.01 {
+            return observation;
+        }
+        observation = env.step(Action::Wait).0;
+    }
+    panic!("never {}under an arc", if under_arc { "" } else { "not " });
+}
+
+#[test]
+fn observations_follow_the_sim() {
+    let mut env = Env::new(RunConfig::default());
+    let observation = env.reset(7);
+    assert_eq!(observation, env.reset(7));
+    assert_eq!(observation.angle, env.sim().player_phase());
+    assert_eq!(observation.level, 0);
+    assert!(observation.wavefront_distance > 0.0);
+    assert!(observation.rings[0][0].size > 0.0);
+    assert_eq!(
+        observation.features().len(),
+        Observation::feature_names().len()
+    );
+}
+
+#[test]
+fn jumps_are_rewarded_and_misses_penalized() {
+    let mut env = Env::new(RunConfig::default());
+    wait_until(&mut env, true);
+    let (observation, reward, done) = env.step(Action::Jump);
+    assert_eq!((observation.level, reward, done), (1, RING_REWARD, false));
+
+    wait_until(&mut env, false);
+    let (observation, reward, _) = env.step(Action::Jump);
+    assert_eq!((observation.level, reward), (0, -MISS_PENALTY));
+    assert!(observation.cooldown < 1.0);
+}
+
+#[test]
+fn waiting_ends_in_death() {
+    let mut env = Env::new(RunConfig::default());
+    env.reset(1);
+    env.frames_per_step = 4;
+    let mut total = 0.0;
+    for _ in 0..10_000 {
+        let (observation, reward, done) = env.step(Action::Wait);
+        total += reward;
+        if done {
+            assert!(env.sim().dead);
+            assert!(observation.wavefront_distance < 0.0);
+            assert_eq!(total, -DEATH_PENALTY);
+            return;
+        }
+    }
+    panic!("still alive");
+}
+
+#[test]
+fn batches_match_single_games_and_reset_when_done() {
+    let config = RunConfig::default();
+    let seeds: Vec<u32> = (0..300).collect();
+    let mut batch = BatchEnv::new(config, 0);
+    let observations = batch.reset(&seeds);
+    let mut singles: Vec<Env> = seeds.iter().map(|_| Env::new(config)).collect();
+    for ((env, &seed), observation) in singles.iter_mut().zip(&seeds).zip(&observations) {
+        assert_eq!(&env.reset(seed), observation);
+    }
+
+    let mut resets = 0;
+    for frame in 0..2000 {
+        let actions: Vec<Action> = (0..seeds.len())
+            .map(|i| Action::ALL[(i + frame) % 7 / 2])
+            .collect();
+        let steps = batch.step(&actions);
+        for ((env, step), &action) in singles.iter_mut().zip(&steps).zip(&actions) {
+            if env.sim().over() {
+                continue;
+            }
+            let (observation, reward, done) = env.step(action);
+            assert_eq!((reward, done), (step.reward, step.done));
+            if done {
+                resets += 1;
+                // The next game starts on a seed none of the batch had.
+                assert_eq!(step.observation.level, 0);
+                assert_ne!(step.observation, observation);
+            } else {
+                assert_eq!(step.observation, observation);
+            }
+        }
+    }
+    assert!(resets > 0);
+}
+
+#[test]
+fn training_scripts_drive_the_binary_over_stdio() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gym"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut request = |line: &str| -> Value {
+        writeln!(stdin, "{line}").unwrap();
+        let mut response = String::new();
+        stdout.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    };
+
+    let spec = request(r#"{"cmd": "spec"}"#);
+    let features = spec["features"].as_array().unwrap().len();
+    assert_eq!(spec["actions"][1], "jump");
+    assert!(request(r#"{"cmd": "step", "actions": [0]}"#)["error"].is_string());
+
+    let reset = request(
+        r#"{"cmd": "reset", "seeds": [1, 2, 3], "config": {"difficulty": "Hard"}, "frames_per_step": 8}"#,
+    );
+    let observations = reset["observations"].as_array().unwrap();
+    assert_eq!(observations.len(), 3);
+    assert_eq!(observations[0].as_array().unwrap().len(), features);
+
+    let mut done = false;
+    for _ in 0..1000 {
+        let step = request(r#"{"cmd": "step", "actions": [0, 0, 0]}"#);
+        assert_eq!(step["rewards"].as_array().unwrap().len(), 3);
+        if step["dones"][0] == true {
+            assert_eq!(step["rewards"][0], -DEATH_PENALTY as f64);
+            done = true;
+            break;
+        }
+    }
+    assert!(done);
+    assert!(request(r#"{"cmd": "step", "actions": [0, 9, 0]}"#)["error"].is_string());
+    assert!(request("not json")["error"].is_string());
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
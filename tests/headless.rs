@@ -0,0 +1,167 @@
+use bevy::prelude::KeyCode;
+use lib_Sol::headless::{Headless, FRAME_DT};
+use lib_Sol::sampling::pfract;
+use lib_Sol::sim::{GameMode, RunConfig, Sim, SimInput, ARC_SAFE, STARTING_LEVEL};
+
+fn game(seed: u32) -> Headless {
+    let mut game = Headless::new();
+    game.start(RunConfig {
+        seed,
+        ..Default::default()
+    });
+    game
+}
+
+/// Whether the player will be well inside a safe arc of the next ring on the next frame, or well
+/// clear of every arc. `None` close to an edge, or while a jump isn't allowed.
+fn over_safe_arc(sim: &Sim) -> Option<bool> {
+    if sim.move_cooldown < 1.0 {
+        return None;
+    }
+    let mut sim = sim.clone();
+    sim.step(FRAME_DT, SimInput::default());
+    let next = sim.player_ring + 1;
+    let phase = sim.player_phase();
+    let mut clear = true;
+    for sub_ring in 0..sim.arc_count(next) {
+        let (start, size) = sim.arc(next, sub_ring);
+        let within = pfract(phase - start + 0.02);
+        if within < size + 0.04 {
+            clear = false;
+            if within > 0.04 && within < size && sim.arc_kind(next, sub_ring) == ARC_SAFE {
+                return Some(true);
+            }
+        }
+    }
+    clear.then_some(false)
+}
+
+fn jump_when(game: &mut Headless, over: bool) {
+    for _ in 0..60 * 10 {
+        if over_safe_arc(&game.run().sim) == Some(over) {
+            game.tap(KeyCode::Space);
+            return;
+        }
+        game.frame();
+    }
+    panic!(
+        "the player was never {}",
+        if over { "over an arc" } else { "clear of arcs" }
+    );
+}
+
+#[test]
+fn jumping_onto_an_arc_reaches_the_next_level() {
+    for seed in [1, 2, 3] {
+        let mut game = game(seed);
+        jump_when(&mut game, true);
+        let sim = &game.run().sim;
+        assert_eq!((sim.level(), sim.miss), (1, 0), "seed {seed}");
+        assert!(sim.score.points > 0);
+    }
+}
+
+#[test]
+fn jumping_between_arcs_misses() {
+    for seed in [1, 2, 3] {
+        let mut game = game(seed);
+        jump_when(&mut game, false);
+        let sim = &game.run().sim;
+        assert_eq!((sim.level(), sim.miss), (0, 1), "seed {seed}");
+        assert!(sim.move_cooldown < 1.0);
+    }
+}
+
+#[test]
+fn waiting_dies_and_enter_restarts() {
+    let mut game = game(1);
+    let mut frames = 0;
+    while !game.run().sim.dead {
+        frames += 1;
+        assert!(frames < 60 * 60, "still alive");
+        game.frame();
+    }
+    game.frame();
+    assert!(game.text().contains("PRESS ENTER TO RESTART"));
+
+    // Saves don't load headless, so the run makes the empty leaderboard and the first Enter
+    // confirms the initials.
+    game.tap(KeyCode::Enter);
+    assert!(game.run().sim.dead);
+    // Without a leaderboard server there's nothing to submit to, so no online line either.
+    game.frames(10);
+    assert!(game.leaderboard_text().contains("HIGH SCORES"));
+    assert!(!game.leaderboard_text().contains("ONLINE"));
+    game.tap(KeyCode::Enter);
+    game.frame();
+    let sim = &game.run().sim;
+    assert!(!sim.dead);
+    assert!(sim.run_time < 0.1);
+    assert!(!game.text().contains("RESTART"));
+}
+
+#[test]
+fn pausing_stops_the_run_until_resumed() {
+    let mut game = game(1);
+    game.frames(30);
+    game.tap(KeyCode::KeyP);
+    assert!(game.paused());
+    let t = game.run().sim.t;
+    game.frames(60);
+    assert_eq!(game.run().sim.t, t);
+    assert!(game.text().contains("RESUME"));
+
+    // The jump that resumes doesn't count as one.
+    game.tap(KeyCode::Space);
+    assert!(!game.paused());
+    game.frames(5);
+    let sim = &game.run().sim;
+    assert!(sim.t > t);
+    assert_eq!((sim.level(), sim.miss), (0, 0));
+}
+
+#[test]
+fn m_toggles_mute() {
+    let mut game = game(1);
+    assert!(!game.muted());
+    game.tap(KeyCode::KeyM);
+    assert!(game.muted());
+    game.frames(10);
+    assert!(game.muted());
+    game.tap(KeyCode::KeyM);
+    assert!(!game.muted());
+}
+
+#[test]
+fn the_editor_builds_and_play_tests_a_level() {
+    let mut game = game(1);
+    game.tap(KeyCode::F3);
+    assert!(game.paused());
+    // A new level previews with one ring of one arc.
+    let level = game.run().sim.level.clone().unwrap();
+    assert_eq!(level.rings.len(), 1);
+
+    // A ring past the end, whose only arc is a hazard, can't be played.
+    game.tap(KeyCode::KeyW);
+    game.tap(KeyCode::KeyA);
+    game.tap(KeyCode::KeyH);
+    game.tap(KeyCode::KeyT);
+    assert!(game.paused());
+    let level = game.run().sim.level.clone().unwrap();
+    assert_eq!(level.rings.len(), 2);
+    assert!(level.rings[1].arcs[0].hazard);
+
+    game.tap(KeyCode::KeyA);
+    game.tap(KeyCode::KeyT);
+    assert!(!game.paused());
+    let sim = &game.run().sim;
+    assert_eq!(sim.config.mode, GameMode::Practice);
+    assert_eq!(sim.player_ring, STARTING_LEVEL + 1);
+    assert_eq!(sim.level.as_ref().unwrap().rings[1].arcs.len(), 2);
+
+    // F3 goes back to the edits.
+    game.tap(KeyCode::F3);
+    assert!(game.paused());
+    let level = game.run().sim.level.clone().unwrap();
+    assert_eq!(level.rings[1].arcs.len(), 2);
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+
+use lib_Sol::leaderboard::{Leaderboard, LeaderboardFile, RunEntry, MAX_ENTRIES};
+use lib_Sol::sim::{Difficulty, RunConfig};
+
+fn entry(id: u64, config: RunConfig, score: u64) -> RunEntry {
+    RunEntry {
+        id,
+        config,
+        initials: String::from("ABC"),
+        level: score as i32 / 10,
+        score,
+        replay: Some(format!("replays/{id}.solr")),
+        ..Default::default()
+    }
+}
+
+fn referenced(leaderboard: &Leaderboard) -> BTreeSet<String> {
+    leaderboard
+        .entries
+        .iter()
+        .chain(&leaderboard.history)
+        .filter_map(|e| e.replay.clone())
+        .collect()
+}
+
+#[test]
+fn insert_keeps_the_best_runs_and_reports_orphaned_replays() {
+    let config = RunConfig::default();
+    let mut leaderboard = Leaderboard::default();
+    let mut orphaned = BTreeSet::new();
+    // Scores go up and down so some late runs miss the table.
+    let scores: Vec<u64> = (0..80).map(|i| (i * 37) % 101).collect();
+    for (id, &score) in scores.iter().enumerate() {
+        let qualifies = leaderboard.qualifies(&config, score);
+        let insertion = leaderboard.insert(entry(id as u64, config, score));
+        assert_eq!(insertion.made_table, qualifies);
+        orphaned.extend(insertion.orphaned_replays);
+    }
+
+    let top: Vec<u64> = leaderboard.top(&config).iter().map(|e| e.score).collect();
+    let mut best = scores.clone();
+    best.sort_by(|a, b| b.cmp(a));
+    assert_eq!(top, best[..MAX_ENTRIES]);
+    assert_eq!(leaderboard.entries.len(), MAX_ENTRIES);
+    assert!(!leaderboard.qualifies(&config, best[MAX_ENTRIES - 1]));
+
+    // Every replay is either still referenced or was handed back to be deleted, never both.
+    let referenced = referenced(&leaderboard);
+    assert!(referenced.is_disjoint(&orphaned));
+    assert_eq!(referenced.len() + orphaned.len(), scores.len());
+}
+
+#[test]
+fn configs_rank_separately() {
+    let normal = RunConfig::default();
+    let hard = RunConfig {
+        difficulty: Difficulty::Hard,
+        ..normal
+    };
+    let mut leaderboard = Leaderboard::default();
+    for id in 0..MAX_ENTRIES as u64 {
+        leaderboard.insert(entry(id, normal, 1000));
+    }
+    assert!(!leaderboard.qualifies(&normal, 10));
+    assert!(leaderboard.qualifies(&hard, 10));
+    assert!(leaderboard.insert(entry(100, hard, 10)).made_table);
+    assert_eq!(leaderboard.top(&hard).len(), 1);
+}
+
+#[test]
+fn saves_round_trip() {
+    let mut leaderboard = Leaderboard::default();
+    for id in 0..3 {
+        leaderboard.insert(entry(id, RunConfig::default(), id * 100));
+    }
+    leaderboard.next_id = 3;
+    leaderboard.set_initials(1, "XYZ");
+
+    let file = LeaderboardFile::V1(leaderboard.clone());
+    let text = ron::ser::to_string_pretty(&file, Default::default()).unwrap();
+    let loaded = ron::from_str::<LeaderboardFile>(&text).unwrap().migrate();
+    assert_eq!(loaded.next_id, 3);
+    assert_eq!(loaded.entries, leaderboard.entries);
+    assert_eq!(loaded.history, leaderboard.history);
+    assert_eq!(loaded.last_initials, "XYZ");
+}
+
+#[test]
+fn version_1_saves_load_with_defaults_for_new_fields() {
+    // Runs were only keyed by mode, difficulty and seed.
+    let text = include_str!("fixtures/leaderboard.v1.ron");
+    let loaded = ron::from_str::<LeaderboardFile>(text).unwrap().migrate();
+    assert_eq!(loaded.next_id, 3);
+    assert_eq!(loaded.last_initials, "SOL");
+    let config = RunConfig {
+        difficulty: Difficulty::Hard,
+        seed: 4,
+        ..Default::default()
+    };
+    let top = loaded.top(&config);
+    assert_eq!(top.len(), 1);
+    assert_eq!(top[0].score, 340);
+    assert_eq!(top[0].replay.as_deref(), Some("replays/0.solr"));
+    assert_eq!(loaded.top(&RunConfig::default())[0].score, 60);
+    assert_eq!(loaded.history.len(), 1);
+}
+
+#[test]
+fn unversioned_saves_are_refused() {
+    let text = ron::ser::to_string(&Leaderboard::default()).unwrap();
+    assert!(ron::from_str::<LeaderboardFile>(&text).is_err());
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use lib_Sol::level::*;
+use lib_Sol::sim::*;
+
+fn arc(size: f32) -> LevelArc {
+    LevelArc {
+        start: 0.25,
+        size,
+        speed: 1.5,
+        reverse: false,
+        hazard: false,
+        color: 0,
+    }
+}
+
+fn level(rings: Vec<Vec<LevelArc>>) -> Level {
+    Level {
+        name: String::from("Test"),
+        rings: rings.into_iter().map(|arcs| LevelRing { arcs }).collect(),
+    }
+}
+
+/// The error `validate` gives for a level whose second ring is `arcs`.
+fn invalid(arcs: Vec<LevelArc>) -> String {
+    match level(vec![vec![arc(0.2)], arcs]).validate() {
+        Err(LevelError::Invalid(e)) => e,
+        other => panic!("{other:?}"),
+    }
+}
+
+#[test]
+fn bundled_levels_are_valid() {
+    for entry in std::fs::read_dir("assets/levels").unwrap() {
+        let path = entry.unwrap().path();
+        let bytes = std::fs::read(&path).unwrap();
+        if let Err(e) = Level::from_ron(&bytes) {
+            panic!("{}: {e}", path.display());
+        }
+    }
+    for path in LEVELS {
+        assert!(std::path::Path::new("assets").join(path).exists());
+    }
+}
+
+#[test]
+fn invalid_rings_are_refused() {
+    let hazard = LevelArc {
+        hazard: true,
+        ..arc(0.2)
+    };
+    let ring = STARTING_LEVEL + 1;
+    assert_eq!(
+        invalid(vec![arc(0.1); MAX_LEVEL_ARCS + 1]),
+        format!("ring {ring}: more than {MAX_LEVEL_ARCS} arcs")
+    );
+    assert_eq!(
+        invalid(vec![hazard, hazard]),
+        format!("ring {ring}: no safe arc")
+    );
+    assert!(invalid(vec![arc(0.0)]).contains("arc sizes"));
+    assert!(invalid(vec![arc(1.0)]).contains("arc sizes"));
+    assert!(invalid(vec![arc(f32::NAN)]).contains("arc sizes"));
+    let colored = LevelArc {
+        color: COLOR_COUNT,
+        ..arc(0.2)
+    };
+    assert!(invalid(vec![colored]).contains("arc colors"));
+
+    assert!(level(vec![vec![arc(0.2), hazard]]).validate().is_ok());
+    let text = r#"(name: "Bad", rings: [(arcs: [(size: 0.2, speed: 1.0, hazard: true)])])"#;
+    assert!(matches!(
+        Level::from_ron(text.as_bytes()),
+        Err(LevelError::Invalid(_))
+    ));
+    assert!(matches!(
+        Level::from_ron(b"(name: \"Bad\")"),
+        Err(LevelError::Parse(_))
+    ));
+}
+
+#[test]
+fn runs_play_the_levels_rings_then_procedural_ones() {
+    let reversed = LevelArc {
+        reverse: true,
+        hazard: true,
+        color: 2,
+        ..arc(0.1)
+    };
+    let level = Arc::new(level(vec![vec![arc(0.2)], vec![arc(0.3), reversed]]));
+    let mut sim = Sim::with_level(
+        RunConfig {
+            seed: 2,
+            ..Default::default()
+        },
+        Some(level.clone()),
+    );
+    assert_eq!(sim.config.level, Some(level.id()));
+    let ring = STARTING_LEVEL + 1;
+    assert_eq!(sim.arc_count(ring), 2);
+    sim.t = 0.1;
+    assert_eq!(sim.arc(ring, 0), (0.25 + 0.15, 0.3));
+    assert_eq!(sim.arc(ring, 1), (0.25 - 0.15, 0.1));
+    assert_eq!(sim.arc_kind(ring, 1), ARC_HAZARD);
+    assert_eq!(sim.arc_color(ring, 1), 2);
+    assert_eq!(sim.power_up(ring, 0), None);
+
+    let past = STARTING_LEVEL + 2;
+    let procedural = Sim::new(RunConfig {
+        seed: 2,
+        ..Default::default()
+    });
+    assert_eq!(sim.arc_count(past), procedural.arc_count(past));
+    assert_eq!(sim.arc(past, 0), {
+        let mut procedural = procedural;
+        procedural.t = 0.1;
+        procedural.arc(past, 0)
+    });
+}
+
+#[test]
+fn different_levels_rank_apart() {
+    let a = level(vec![vec![arc(0.2)]]);
+    let b = level(vec![vec![arc(0.25)]]);
+    assert_eq!(a.id(), a.clone().id());
+    assert_ne!(a.id(), b.id());
+}
+
+#[test]
+fn the_shader_gets_the_rings_around_the_player() {
+    let rings = (0..LEVEL_WINDOW * 2)
+        .map(|i| vec![arc(0.2); i % MAX_LEVEL_ARCS + 1])
+        .collect();
+    let level = level(rings);
+    let (first, count, counts, arcs) = level.gpu_window(STARTING_LEVEL);
+    assert_eq!((first, count as usize), (STARTING_LEVEL, LEVEL_WINDOW));
+    assert_eq!(counts[0].x, 1);
+    assert_eq!(counts[0].y, 2);
+    assert_eq!(arcs[1][1].y, 0.2);
+
+    // Ends with the level.
+    let last = STARTING_LEVEL + LEVEL_WINDOW as u32 * 2 - 1;
+    let (first, count, _, _) = level.gpu_window(last);
+    assert!(first < last);
+    assert_eq!(first + count, last + 1);
+}
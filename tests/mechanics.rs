@@ -0,0 +1,285 @@
+mod common;
+
+use common::*;
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::sim::*;
+
+fn config(seed: u32) -> RunConfig {
+    RunConfig {
+        seed,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn diving_lands_on_the_inner_ring_and_breaks_the_combo() {
+    let mut sim = Sim::new(config(4));
+    // Nothing inside the starting ring.
+    assert_eq!(try_when(&mut sim.clone(), DIVE, |_| true), None);
+
+    land(&mut sim);
+    land(&mut sim);
+    let points = sim.score.points;
+    assert_eq!(sim.score.combo, 2);
+    act_when(&mut sim, DIVE, |result| {
+        matches!(result, JumpResult::Dived { .. })
+    });
+    assert_eq!(sim.level(), 1);
+    assert_eq!(sim.score.combo, 0);
+    assert_eq!(sim.score.points, points);
+    assert_eq!(sim.highest_ring, STARTING_LEVEL + 2);
+}
+
+#[test]
+fn diving_can_be_turned_off() {
+    let mut sim = Sim::new(RunConfig {
+        mechanics: Mechanics {
+            dive: false,
+            ..Default::default()
+        },
+        ..config(4)
+    });
+    land(&mut sim);
+    assert_eq!(try_when(&mut sim, DIVE, |_| true), None);
+    assert_eq!(sim.level(), 1);
+}
+
+/// A run starting just inside `ring`.
+fn outside(ring: u32, config: RunConfig) -> Sim {
+    Sim::new(RunConfig {
+        start_level: ring - 1 - STARTING_LEVEL,
+        ..config
+    })
+}
+
+/// The first ring from 30 on with a hazard.
+fn hazard_ring(seed: u32) -> u32 {
+    (30..)
+        .find(|&ring| {
+            (0..get_arc_count(ring))
+                .any(|sub_ring| get_ring_color(ring, sub_ring, seed) == ARC_HAZARD)
+        })
+        .unwrap()
+}
+
+fn hazard(result: &JumpResult) -> bool {
+    matches!(result, JumpResult::Hazard { .. })
+}
+
+#[test]
+fn hazards_leave_the_first_arc_inner_rings_and_bosses_safe() {
+    let mut hazards = 0;
+    for seed in 0..10 {
+        for ring in STARTING_LEVEL..100 {
+            for sub_ring in 0..get_arc_count(ring) {
+                if get_ring_color(ring, sub_ring, seed) == ARC_HAZARD {
+                    assert!(sub_ring > 0 && ring > 20, "ring {ring}");
+                    assert!(get_power_up(ring, sub_ring, seed).is_none());
+                    hazards += 1;
+                }
+            }
+        }
+    }
+    assert!(hazards > 0);
+    let boss = STARTING_LEVEL + lib_Sol::boss::BOSS_RING_INTERVAL * 4;
+    let sim = Sim::new(config(1));
+    assert!((0..sim.arc_count(boss)).all(|sub_ring| sim.arc_kind(boss, sub_ring) == ARC_SAFE));
+}
+
+#[test]
+fn jumping_onto_a_hazard_is_a_miss() {
+    let ring = hazard_ring(4);
+    let mut sim = outside(ring, config(4));
+    act_when(&mut sim, JUMP, hazard);
+    assert_eq!(sim.player_ring, ring - 1);
+    assert_eq!(sim.miss, 1);
+    assert!(!sim.over());
+    assert!(sim.move_cooldown < 0.1);
+
+    let mut sim = outside(
+        ring,
+        RunConfig {
+            mode: GameMode::Lives,
+            ..config(4)
+        },
+    );
+    act_when(&mut sim, JUMP, hazard);
+    assert_eq!(sim.lives, STARTING_LIVES - 1);
+}
+
+#[test]
+fn hazards_can_be_turned_off() {
+    let ring = hazard_ring(4);
+    let mut sim = outside(
+        ring,
+        RunConfig {
+            mechanics: Mechanics {
+                hazards: false,
+                ..Default::default()
+            },
+            ..config(4)
+        },
+    );
+    assert!((0..sim.arc_count(ring)).all(|sub_ring| sim.arc_kind(ring, sub_ring) == ARC_SAFE));
+    assert_eq!(try_when(&mut sim, JUMP, hazard), None);
+}
+
+fn colored(seed: u32) -> Sim {
+    Sim::new(RunConfig {
+        colors: true,
+        ..config(seed)
+    })
+}
+
+#[test]
+fn the_orb_cycles_through_colors_only_when_they_are_on() {
+    let change_color = SimInput {
+        change_color: true,
+        ..Default::default()
+    };
+    let mut sim = colored(2);
+    for i in 1..=COLOR_COUNT * 2 {
+        sim.step(FRAME_DT, change_color);
+        assert_eq!(sim.player_color, i % COLOR_COUNT);
+    }
+    let mut sim = Sim::new(config(2));
+    sim.step(FRAME_DT, change_color);
+    assert_eq!(sim.player_color, 0);
+}
+
+#[test]
+fn landing_needs_an_arc_of_the_orbs_color() {
+    // Jumps that would land on an arc of another color without colors don't with them.
+    let mut sim = colored(2);
+    let (mut refused, mut landed) = (0, 0);
+    while refused < 3 || landed < 3 {
+        assert!(!sim.over());
+        let mut plain = sim.clone();
+        plain.config.colors = false;
+        let mut attempt = sim.clone();
+        let result = attempt.step(FRAME_DT, JUMP);
+        if let Some(JumpResult::Landed { sub_ring, .. }) = plain.step(FRAME_DT, JUMP) {
+            let next = sim.player_ring + 1;
+            if sim.arc_color(next, sub_ring) != sim.player_color {
+                let landed_there = matches!(
+                    result,
+                    Some(JumpResult::Landed { sub_ring: landed, .. }) if landed == sub_ring
+                );
+                assert!(!landed_there, "landed on ring {next} arc {sub_ring}");
+                refused += 1;
+            }
+        }
+        // Climbs on whatever it can land on, so the wavefront doesn't catch up.
+        if let Some(JumpResult::Landed { sub_ring, .. }) = result {
+            sim = attempt;
+            assert_eq!(sim.arc_color(sim.player_ring, sub_ring), sim.player_color);
+            landed += 1;
+        } else {
+            sim.step(FRAME_DT, SimInput::default());
+        }
+    }
+}
+
+/// The first arc holding `power_up`, on a ring just outside a plain one.
+fn power_up_spot(seed: u32, power_up: PowerUp) -> (u32, u32) {
+    (POWER_UP_FIRST_RING..)
+        .filter(|&ring| !lib_Sol::boss::is_boss_ring(ring - 1))
+        .find_map(|ring| {
+            (0..get_arc_count(ring))
+                .find(|&sub_ring| get_power_up(ring, sub_ring, seed) == Some(power_up))
+                .map(|sub_ring| (ring, sub_ring))
+        })
+        .unwrap()
+}
+
+/// Plays from just inside the first ring with `power_up` until landing on it. Returns the run
+/// as it would be on that frame without jumping, and the one that collected it.
+fn collect(power_up: PowerUp) -> (Sim, Sim) {
+    let (ring, _) = power_up_spot(5, power_up);
+    let mut sim = outside(ring, config(5));
+    loop {
+        assert!(!sim.over());
+        let (mut idle, mut attempt) = (sim.clone(), sim.clone());
+        idle.step(FRAME_DT, SimInput::default());
+        if let Some(JumpResult::Landed {
+            power_up: Some(collected),
+            ..
+        }) = attempt.step(FRAME_DT, JUMP)
+        {
+            assert_eq!(collected, power_up);
+            return (idle, attempt);
+        }
+        sim = idle;
+    }
+}
+
+#[test]
+fn power_ups_stay_off_inner_rings_bosses_and_hazards() {
+    let mut found = [false; 3];
+    for seed in 0..10 {
+        for ring in STARTING_LEVEL..100 {
+            for sub_ring in 0..get_arc_count(ring) {
+                let Some(power_up) = get_power_up(ring, sub_ring, seed) else {
+                    continue;
+                };
+                assert!(ring >= POWER_UP_FIRST_RING);
+                assert!(!lib_Sol::boss::is_boss_ring(ring));
+                assert_eq!(get_ring_color(ring, sub_ring, seed), ARC_SAFE);
+                found[power_up as usize - 1] = true;
+            }
+        }
+    }
+    assert_eq!(found, [true; 3]);
+
+    let (ring, sub_ring) = power_up_spot(5, PowerUp::Shield);
+    let off = Sim::new(RunConfig {
+        mechanics: Mechanics {
+            power_ups: false,
+            ..Default::default()
+        },
+        ..config(5)
+    });
+    assert_eq!(off.power_up(ring, sub_ring), None);
+}
+
+#[test]
+fn slow_time_halves_the_speed_for_a_while() {
+    let (idle, mut sim) = collect(PowerUp::SlowTime);
+    assert_eq!(sim.slow_time, SLOW_TIME_DURATION);
+    assert!((sim.speed() - idle.speed() * SLOW_TIME_FACTOR).abs() < 1e-6);
+    for _ in 0..(SLOW_TIME_DURATION / FRAME_DT) as usize + 2 {
+        sim.step(FRAME_DT, SimInput::default());
+    }
+    assert_eq!(sim.slow_time, 0.0);
+    assert_eq!(sim.speed(), idle.speed());
+}
+
+#[test]
+fn shields_absorb_one_miss() {
+    let (_, mut sim) = collect(PowerUp::Shield);
+    assert!(sim.shield);
+    let combo = sim.score.combo;
+    act_when(&mut sim, JUMP, |result| *result == JumpResult::Shielded);
+    assert!(!sim.shield);
+    assert_eq!((sim.miss, sim.score.combo), (0, combo));
+    act_when(&mut sim, JUMP, |result| *result == JumpResult::Missed);
+    assert_eq!(sim.miss, 1);
+}
+
+#[test]
+fn push_moves_the_wavefront_back() {
+    let (idle, sim) = collect(PowerUp::Push);
+    assert!((idle.wavefront - POWER_UP_PUSH - sim.wavefront).abs() < 1e-4);
+}
+
+#[test]
+fn power_ups_are_collected_once() {
+    let (_, mut sim) = collect(PowerUp::Shield);
+    let ring = sim.player_ring;
+    act_when(&mut sim, DIVE, |result| {
+        matches!(result, JumpResult::Dived { .. })
+    });
+    let result = land(&mut sim);
+    assert_eq!(sim.player_ring, ring);
+    assert!(matches!(result, JumpResult::Landed { power_up: None, .. }));
+}
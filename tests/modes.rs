@@ -0,0 +1,192 @@
+mod common;
+
+use common::*;
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::sim::*;
+
+fn config(mode: GameMode) -> RunConfig {
+    RunConfig {
+        mode,
+        seed: 3,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn practice_starts_further_out_at_its_own_speed() {
+    let practice = |speed_percent| RunConfig {
+        start_level: 20,
+        speed_percent,
+        ..config(GameMode::Practice)
+    };
+    let sim = Sim::new(practice(100));
+    assert_eq!(sim.level(), 20);
+    assert_eq!(sim.player_ring, STARTING_LEVEL + 20);
+    assert_eq!(sim.wavefront, 20.0);
+    let slow = Sim::new(practice(50));
+    assert!((slow.speed() * 2.0 - sim.speed()).abs() < 1e-6);
+    assert!(!GameMode::Practice.is_ranked());
+}
+
+#[test]
+fn invincible_practice_holds_the_wavefront_back() {
+    let mut sim = Sim::new(RunConfig {
+        invincible: true,
+        ..config(GameMode::Practice)
+    });
+    wait(&mut sim, 120.0);
+    assert!(!sim.over());
+    assert!(sim.wavefront <= sim.player_ring as f32);
+}
+
+#[test]
+fn retrying_goes_back_to_the_checkpoint() {
+    let mut run = Run::new(config(GameMode::Practice));
+    land(&mut run.sim);
+    land(&mut run.sim);
+    run.checkpoint = Some(run.sim.clone());
+    let t = run.sim.t;
+    wait(&mut run.sim, 120.0);
+    assert!(run.sim.dead);
+    run.retry();
+    assert!(!run.sim.dead);
+    assert_eq!((run.sim.level(), run.sim.t), (2, t));
+
+    run.checkpoint = None;
+    run.retry();
+    assert_eq!((run.sim.level(), run.sim.t), (0, 0.0));
+}
+
+#[test]
+fn zen_runs_last_until_the_player_quits() {
+    let mut sim = Sim::new(config(GameMode::Zen));
+    wait(&mut sim, 120.0);
+    assert!(!sim.over());
+    assert!(sim.wavefront <= sim.player_ring as f32);
+
+    land(&mut sim);
+    assert!(sim.wavefront <= sim.player_ring as f32 - ZEN_WAVEFRONT_PUSHBACK);
+    let quit = SimInput {
+        quit: true,
+        ..Default::default()
+    };
+    sim.step(FRAME_DT, quit);
+    assert!(sim.finished && !sim.dead);
+    assert!(!GameMode::Zen.is_ranked());
+}
+
+#[test]
+fn quitting_only_ends_zen_runs() {
+    let mut sim = Sim::new(config(GameMode::Normal));
+    let quit = SimInput {
+        quit: true,
+        ..Default::default()
+    };
+    sim.step(FRAME_DT, quit);
+    assert!(!sim.over());
+}
+
+#[test]
+fn time_attack_splits_every_few_levels_and_finishes_at_the_target() {
+    let mut sim = Sim::new(config(GameMode::TimeAttack));
+    while !sim.over() {
+        land(&mut sim);
+        let splits = (sim.level() / SPLIT_LEVELS) as usize;
+        assert_eq!(sim.splits.len(), splits, "level {}", sim.level());
+    }
+    assert!(sim.finished && !sim.dead);
+    assert_eq!(sim.level(), TIME_ATTACK_TARGET);
+    assert_eq!(*sim.splits.last().unwrap(), sim.run_time);
+    assert!(sim.splits.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn time_attack_takes_its_last_split_at_any_target() {
+    let mut sim = Sim::new(RunConfig {
+        time_attack_target: 15,
+        ..config(GameMode::TimeAttack)
+    });
+    assert_eq!(sim.config.time_attack_splits(), 2);
+    while !sim.over() {
+        land(&mut sim);
+    }
+    assert!(sim.finished && !sim.dead);
+    assert_eq!(sim.level(), 15);
+    assert_eq!(sim.splits.len(), 2);
+    assert_eq!(*sim.splits.last().unwrap(), sim.run_time);
+    assert_ne!(sim.config, config(GameMode::TimeAttack));
+}
+
+#[test]
+fn the_wavefront_takes_lives_before_ending_the_run() {
+    let mut sim = Sim::new(config(GameMode::Lives));
+    while sim.lives == STARTING_LIVES {
+        sim.step(FRAME_DT, SimInput::default());
+    }
+    assert!(!sim.over());
+    assert_eq!(sim.lives, STARTING_LIVES - 1);
+    assert!(sim.invulnerable > 0.0);
+    assert!(sim.wavefront < sim.player_ring as f32 - LIFE_PUSHBACK + 0.1);
+
+    wait(&mut sim, 600.0);
+    assert!(sim.dead);
+    assert_eq!(sim.lives, 0);
+}
+
+#[test]
+fn extra_lives_come_every_few_levels_up_to_the_most() {
+    let mut sim = Sim::new(config(GameMode::Lives));
+    assert_eq!(sim.next_extra_life, EXTRA_LIFE_LEVELS);
+    sim.next_extra_life = 1;
+    land(&mut sim);
+    assert_eq!(sim.lives, STARTING_LIVES + 1);
+    assert_eq!(sim.next_extra_life, 1 + EXTRA_LIFE_LEVELS);
+
+    sim.lives = MAX_LIVES;
+    sim.next_extra_life = 2;
+    land(&mut sim);
+    assert_eq!(sim.lives, MAX_LIVES);
+
+    // Only lives mode has them.
+    let mut sim = Sim::new(config(GameMode::Normal));
+    sim.next_extra_life = 1;
+    land(&mut sim);
+    assert_eq!(sim.lives, STARTING_LIVES);
+}
+
+fn goal(goal: Goal) -> Sim {
+    Sim::new(RunConfig {
+        goal: Some(goal),
+        ..config(GameMode::Campaign)
+    })
+}
+
+#[test]
+fn reaching_the_goal_wins_the_run() {
+    let mut sim = goal(Goal::Level(3));
+    land(&mut sim);
+    land(&mut sim);
+    assert!(!sim.over());
+    land(&mut sim);
+    assert!(sim.won() && !sim.dead);
+
+    let mut sim = goal(Goal::Score(1));
+    land(&mut sim);
+    assert!(sim.won());
+
+    let mut sim = goal(Goal::Survive(5));
+    wait(&mut sim, 4.9);
+    assert!(!sim.over());
+    wait(&mut sim, 0.2);
+    assert!(sim.won());
+    assert!(!GameMode::Campaign.is_ranked());
+}
+
+#[test]
+fn dying_first_loses_the_run() {
+    let mut sim = goal(Goal::Level(100));
+    wait(&mut sim, 600.0);
+    assert!(sim.dead && !sim.won());
+    assert_eq!(Goal::Level(100).describe(), "REACH LEVEL 100");
+    assert_eq!(Goal::Survive(30).describe(), "SURVIVE 30 SECONDS");
+}
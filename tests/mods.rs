@@ -0,0 +1,83 @@
+use lib_Sol::boss::BOSS_RING_INTERVAL;
+use lib_Sol::mods::{Mod, Mods};
+use lib_Sol::sampling::pfract;
+use lib_Sol::score::Score;
+use lib_Sol::sim::{RunConfig, Sim, STARTING_LEVEL};
+
+fn sim(mods: Mods) -> Sim {
+    Sim::new(RunConfig {
+        seed: 6,
+        mods,
+        ..Default::default()
+    })
+}
+
+/// How far an arc's start moves over a short time, in turns either way.
+fn travel(sim: &mut Sim, ring: u32, t: f32) -> f32 {
+    sim.t = t;
+    let before = sim.arc(ring, 0).0;
+    sim.t = t + 0.01;
+    pfract(sim.arc(ring, 0).0 - before + 0.5) - 0.5
+}
+
+#[test]
+fn score_bonuses_add_up() {
+    assert_eq!(Mods::default().score_percent(), 100);
+    let mut mods = Mods::default();
+    mods.toggle(Mod::Hidden);
+    mods.toggle(Mod::DoubleTime);
+    assert_eq!(mods.score_percent(), 128);
+    assert_eq!(mods.codes(), "+HD+DT");
+
+    let all = Mod::ALL.into_iter().fold(Mods::default(), |mut mods, m| {
+        mods.toggle(m);
+        mods
+    });
+    let bonus: u32 = Mod::ALL.into_iter().map(Mod::score_bonus).sum();
+    assert_eq!(all.score_percent(), 100 + bonus);
+}
+
+#[test]
+fn landings_are_scaled_by_the_score_percent() {
+    let ring = STARTING_LEVEL + 1;
+    let (mut plain, mut modded) = (Score::default(), Score::default());
+    assert_eq!(plain.hit(ring, 100), Score::ring_value(ring));
+    assert_eq!(modded.hit(ring, 150), Score::ring_value(ring) * 3 / 2);
+}
+
+#[test]
+fn mirror_reverses_every_ring() {
+    let mirror = Mods {
+        mirror: true,
+        ..Default::default()
+    };
+    let (mut plain, mut mirrored) = (sim(Mods::default()), sim(mirror));
+    let boss = STARTING_LEVEL + BOSS_RING_INTERVAL;
+    for ring in [STARTING_LEVEL + 1, STARTING_LEVEL + 4, boss] {
+        for t in [0.5, 3.0, 20.0] {
+            let forwards = travel(&mut plain, ring, t);
+            let backwards = travel(&mut mirrored, ring, t);
+            assert!(forwards != 0.0, "ring {ring} doesn't move");
+            assert!((forwards + backwards).abs() < 1e-4, "ring {ring} at {t}");
+            assert_eq!(plain.arc(ring, 0).1, mirrored.arc(ring, 0).1);
+        }
+    }
+}
+
+#[test]
+fn narrow_arcs_keep_their_start_when_mirrored() {
+    let mirror = Mods {
+        mirror: true,
+        ..Default::default()
+    };
+    let narrow = Mods {
+        narrow: true,
+        ..mirror
+    };
+    let (mut wide, mut narrowed) = (sim(mirror), sim(narrow));
+    let ring = STARTING_LEVEL + 2;
+    wide.t = 7.0;
+    narrowed.t = 7.0;
+    let (start, size) = wide.arc(ring, 0);
+    assert_eq!(narrowed.arc(ring, 0), (start, size * 0.75));
+}
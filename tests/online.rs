@@ -0,0 +1,156 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+use lib_Sol::online::*;
+use lib_Sol::sim::{Run, RunConfig, SimInput};
+
+/// Plays a run to the end, jumping every `jump_every` frames.
+fn finished_run(jump_every: u32) -> Run {
+    let mut run = Run::new(RunConfig::default());
+    let mut frame = 0;
+    while !run.sim.dead {
+        frame += 1;
+        run.step(
+            1.0 / 60.0,
+            SimInput {
+                jump: frame % jump_every == 0,
+                ..Default::default()
+            },
+        );
+    }
+    run
+}
+
+#[test]
+fn submit_and_rank_against_mock_server() {
+    let server = MockServer::start().unwrap();
+    let client = HttpLeaderboard::new(server.addr.clone());
+
+    let low = finished_run(37);
+    let high = finished_run(23);
+    assert!(high.sim.score.points > low.sim.score.points);
+
+    let submit = |initials, run: &Run| {
+        client.submit(&Submission::new(
+            initials,
+            run.sim.score.points,
+            &run.replay,
+        ))
+    };
+    assert_eq!(submit("LOW", &low).unwrap().rank, 1);
+    assert_eq!(submit("TOP", &high).unwrap().rank, 1);
+
+    let rankings = client
+        .rankings(&RankingsRequest {
+            config: RunConfig::default(),
+            limit: RANKINGS_LIMIT,
+        })
+        .unwrap();
+    let names: Vec<_> = rankings.iter().map(|e| e.initials.as_str()).collect();
+    assert_eq!(names, ["TOP", "LOW"]);
+    assert_eq!(rankings[0].rank, 1);
+    assert_eq!(rankings[0].level, high.sim.level());
+    assert_eq!(server.runs().len(), 2);
+}
+
+#[test]
+fn server_rejects_wrong_score() {
+    let server = MockServer::start().unwrap();
+    let client = HttpLeaderboard::new(server.addr.clone());
+    let run = finished_run(23);
+
+    let result = client.submit(&Submission::new(
+        "BAD",
+        run.sim.score.points + 1,
+        &run.replay,
+    ));
+    assert!(matches!(result, Err(OnlineError::Rejected(_))));
+    assert!(server.runs().is_empty());
+}
+
+#[test]
+fn server_rejects_unfinished_run() {
+    let server = MockServer::start().unwrap();
+    let client = HttpLeaderboard::new(server.addr.clone());
+    let mut run = Run::new(RunConfig::default());
+    run.step(1.0 / 60.0, SimInput::default());
+
+    let result = client.submit(&Submission::new("BAD", 0, &run.replay));
+    assert!(matches!(result, Err(OnlineError::Rejected(_))));
+}
+
+/// Answers one request with `response`, after reading the request through.
+fn raw_server(response: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(length) = line.strip_prefix("Content-Length: ") {
+                content_length = length.trim().parse().unwrap();
+            }
+        }
+        reader.read_exact(&mut vec![0; content_length]).unwrap();
+        reader.into_inner().write_all(response.as_bytes()).unwrap();
+    });
+    addr
+}
+
+fn rankings(addr: String) -> Result<Vec<OnlineEntry>, OnlineError> {
+    HttpLeaderboard::new(addr).rankings(&RankingsRequest {
+        config: RunConfig::default(),
+        limit: RANKINGS_LIMIT,
+    })
+}
+
+#[test]
+fn unreachable_server_is_unavailable() {
+    // Nothing listens on the port once the listener is dropped.
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .to_string();
+    let client = HttpLeaderboard::new(addr);
+    let run = finished_run(23);
+    let result = client.submit(&Submission::new("OFF", run.sim.score.points, &run.replay));
+    assert!(matches!(result, Err(OnlineError::Unavailable(_))));
+
+    let result = OfflineLeaderboard.rankings(&RankingsRequest {
+        config: RunConfig::default(),
+        limit: RANKINGS_LIMIT,
+    });
+    assert!(matches!(result, Err(OnlineError::Unavailable(_))));
+}
+
+#[test]
+fn error_pages_report_their_status() {
+    let addr =
+        raw_server("HTTP/1.1 503 Service Unavailable\r\nContent-Length: 11\r\n\r\nmaintenance");
+    match rankings(addr) {
+        Err(OnlineError::BadResponse(e)) => assert_eq!(e, "status 503: maintenance"),
+        result => panic!("{result:?}"),
+    }
+}
+
+#[test]
+fn unreadable_responses_are_unavailable() {
+    for response in [
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n[]\r\n0\r\n\r\n",
+        "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n[]",
+        "SSH-2.0-OpenSSH_9.6\r\n",
+    ] {
+        let result = rankings(raw_server(response));
+        assert!(
+            matches!(result, Err(OnlineError::Unavailable(_))),
+            "{response:?}: {result:?}"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use lib_Sol::progress::{format_time, ProgressFile, TimeAttackRecord};
+use lib_Sol::sim::{Difficulty, GameMode, RunConfig, SPLIT_LEVELS, TIME_ATTACK_TARGET};
+
+fn record() -> TimeAttackRecord {
+    TimeAttackRecord::new(RunConfig {
+        difficulty: Difficulty::Hard,
+        seed: 9,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn only_faster_finished_runs_are_personal_bests() {
+    let mut record = record();
+    record.add(&[10.0, 25.0, 35.0], false);
+    assert!(record.personal_best.is_empty());
+
+    record.add(&[12.0, 24.0, 40.0, 52.0, 70.0], true);
+    assert_eq!(record.personal_best, [12.0, 24.0, 40.0, 52.0, 70.0]);
+
+    // Faster splits early on, but a slower finish.
+    record.add(&[9.0, 18.0, 30.0, 50.0, 71.0], true);
+    assert_eq!(record.personal_best, [12.0, 24.0, 40.0, 52.0, 70.0]);
+
+    record.add(&[11.0, 23.0, 36.0, 50.0, 65.0], true);
+    assert_eq!(record.personal_best, [11.0, 23.0, 36.0, 50.0, 65.0]);
+    assert_eq!(record.attempts, 4);
+}
+
+#[test]
+fn best_segments_come_from_any_attempt() {
+    let mut record = record();
+    record.add(&[10.0, 25.0, 35.0], false);
+    record.add(&[12.0, 24.0, 40.0, 52.0, 70.0], true);
+    record.add(&[9.0, 18.0, 30.0, 50.0, 71.0], true);
+    assert_eq!(record.best_segments, [9.0, 9.0, 10.0, 12.0, 18.0]);
+}
+
+#[test]
+fn livesplit_export_lists_every_split() {
+    let mut record = record();
+    record.add(&[12.5, 24.0, 40.0], false);
+    record.add(&[12.0, 24.0, 40.0, 52.0, 65.25], true);
+    let lss = record.to_lss();
+
+    assert!(lss.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Run version=\"1.7.0\">"));
+    assert!(lss.contains(&format!(
+        "<CategoryName>Level {TIME_ATTACK_TARGET} HARD Seed 9</CategoryName>"
+    )));
+    assert!(lss.contains("<AttemptCount>2</AttemptCount>"));
+    let segments = (TIME_ATTACK_TARGET / SPLIT_LEVELS) as usize;
+    assert_eq!(lss.matches("<Segment>").count(), segments);
+    assert_eq!(lss.matches("</Segment>").count(), segments);
+    assert!(lss.contains(&format!("<Name>Level {SPLIT_LEVELS}</Name>")));
+    assert!(lss.contains(
+        "<SplitTime name=\"Personal Best\"><GameTime>00:01:05.2500000</GameTime></SplitTime>"
+    ));
+    assert!(
+        lss.contains("<BestSegmentTime><GameTime>00:00:12.0000000</GameTime></BestSegmentTime>")
+    );
+    assert!(lss.ends_with("</Segments>\n  <AutoSplitterSettings />\n</Run>\n"));
+}
+
+#[test]
+fn livesplit_export_follows_the_target() {
+    let record = TimeAttackRecord::new(RunConfig {
+        time_attack_target: 25,
+        ..record().config
+    });
+    let lss = record.to_lss();
+    assert!(lss.contains("<CategoryName>Level 25 HARD Seed 9</CategoryName>"));
+    assert_eq!(lss.matches("<Segment>").count(), 3);
+    assert!(lss.contains("<Name>Level 20</Name>"));
+    assert!(lss.contains("<Name>Level 25</Name>"));
+    assert!(!lss.contains("<Name>Level 30</Name>"));
+}
+
+#[test]
+fn unplayed_splits_are_left_empty() {
+    let mut record = record();
+    record.add(&[12.0, 24.0], false);
+    let lss = record.to_lss();
+    assert_eq!(
+        lss.matches("<SplitTime name=\"Personal Best\"></SplitTime>")
+            .count(),
+        (TIME_ATTACK_TARGET / SPLIT_LEVELS) as usize
+    );
+    assert_eq!(
+        lss.matches("<BestSegmentTime></BestSegmentTime>").count(),
+        3
+    );
+}
+
+#[test]
+fn times_show_minutes_and_hundredths() {
+    assert_eq!(format_time(5.0), "0:05.00");
+    assert_eq!(format_time(65.25), "1:05.25");
+}
+
+#[test]
+fn version_1_saves_load_with_defaults_for_new_fields() {
+    // Before boss stats and campaign unlocks.
+    let text = include_str!("fixtures/progress.v1.ron");
+    let progress = ron::from_str::<ProgressFile>(text).unwrap().migrate();
+    assert_eq!((progress.zen.runs, progress.zen.best_level), (4, 31));
+    let config = RunConfig {
+        mode: GameMode::TimeAttack,
+        seed: 9,
+        ..Default::default()
+    };
+    let record = progress.time_attack(&config).unwrap();
+    assert_eq!(record.attempts, 3);
+    assert_eq!(record.personal_best, [12.0, 24.0, 40.0, 52.0, 70.0]);
+    assert_eq!(progress.bosses.passed, 0);
+    assert!(progress.campaign.is_empty());
+
+    let file = ProgressFile::V1(progress.clone());
+    let text = ron::ser::to_string_pretty(&file, Default::default()).unwrap();
+    let loaded = ron::from_str::<ProgressFile>(&text).unwrap().migrate();
+    assert_eq!(loaded.time_attack, progress.time_attack);
+}
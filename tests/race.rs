@@ -0,0 +1,237 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use lib_Sol::autoplay::Autoplay;
+use lib_Sol::race::*;
+use lib_Sol::sim::{Difficulty, GameMode, Run, RunConfig};
+
+/// The relay binary, killed on drop.
+struct RelayProcess {
+    child: Child,
+    addr: String,
+}
+
+impl RelayProcess {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_relay"))
+            .arg("127.0.0.1:0")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let addr = line.trim().rsplit(' ').next().unwrap().to_string();
+        Self { child, addr }
+    }
+}
+
+impl Drop for RelayProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+fn config(seed: u32) -> RunConfig {
+    RunConfig {
+        seed,
+        mode: GameMode::Race,
+        difficulty: Difficulty::Hard,
+        ..Default::default()
+    }
+}
+
+/// A client with its run, played by a bot once the race starts.
+struct Racer {
+    client: RaceClient,
+    run: Option<Run>,
+    bot: Autoplay,
+}
+
+impl Racer {
+    fn join(relay: &str, room: &str, seed: u32, skill: f32) -> Self {
+        Self {
+            client: RaceClient::join(relay, room, config(seed)).unwrap(),
+            run: None,
+            bot: Autoplay::new(skill, seed),
+        }
+    }
+
+    fn frame(&mut self) {
+        let frames = self.run.as_ref().map_or(&[][..], |run| &run.replay.frames);
+        self.client.update(frames);
+        if self.run.is_none() && self.client.status == RaceStatus::Racing {
+            self.run = Some(Run::new(self.client.config));
+        }
+        let Some(run) = &mut self.run else {
+            return;
+        };
+        if !run.sim.over() {
+            let input = self.bot.input(&run.sim);
+            run.step(1.0 / 60.0, input);
+        }
+    }
+
+    fn result(&self) -> Option<RaceResult> {
+        self.client.result(&self.run.as_ref()?.sim)
+    }
+}
+
+/// Runs both racers until they agree on a result.
+fn race(racers: &mut [Racer; 2]) {
+    let start = Instant::now();
+    while racers.iter().any(|racer| racer.result().is_none()) {
+        assert!(
+            start.elapsed() < Duration::from_secs(60),
+            "no result, {:?} and {:?}",
+            racers[0].client.status,
+            racers[1].client.status
+        );
+        for racer in racers.iter_mut() {
+            racer.frame();
+        }
+        std::thread::sleep(Duration::from_micros(200));
+    }
+}
+
+fn check_results(racers: &[Racer; 2]) {
+    // Both started on the first joiner's seed, and each simulated the other exactly.
+    for (racer, other) in [(&racers[0], &racers[1]), (&racers[1], &racers[0])] {
+        let (run, other_run) = (racer.run.as_ref().unwrap(), other.run.as_ref().unwrap());
+        assert_eq!(run.sim.config.seed, 1);
+        let opponent = racer.client.opponent();
+        assert_eq!(opponent.run_time, other_run.sim.run_time);
+        assert_eq!(opponent.player_ring, other_run.sim.player_ring);
+        assert_eq!(opponent.score.points, other_run.sim.score.points);
+    }
+    assert_eq!(racers[0].client.player, 0);
+    assert_eq!(racers[1].client.player, 1);
+    let results = [racers[0].result().unwrap(), racers[1].result().unwrap()];
+    assert!(
+        matches!(
+            results,
+            [RaceResult::Won, RaceResult::Lost]
+                | [RaceResult::Lost, RaceResult::Won]
+                | [RaceResult::Draw, RaceResult::Draw]
+        ),
+        "{results:?}"
+    );
+}
+
+#[test]
+fn two_clients_race_through_the_relay_binary() {
+    let relay = RelayProcess::start();
+    let mut racers = [
+        Racer::join(&relay.addr, "test", 1, 0.3),
+        Racer::join(&relay.addr, "test", 2, 0.9),
+    ];
+    race(&mut racers);
+    check_results(&racers);
+    // The sharper bot outlasts the sloppy one.
+    assert_eq!(racers[1].result(), Some(RaceResult::Won));
+}
+
+#[test]
+fn races_survive_latency_and_packet_loss() {
+    let mut relay = Relay::bind("127.0.0.1:0").unwrap();
+    relay.loss = 0.3;
+    relay.delay = Duration::from_millis(40);
+    let relay = relay.spawn().unwrap();
+    let addr = relay.addr.to_string();
+    let mut racers = [
+        Racer::join(&addr, "lossy", 1, 0.5),
+        Racer::join(&addr, "lossy", 2, 0.5),
+    ];
+    race(&mut racers);
+    check_results(&racers);
+    let rtt = racers[0].client.rtt.unwrap();
+    assert!(rtt >= 0.08, "round trip {rtt}");
+}
+
+#[test]
+fn third_client_finds_the_room_full() {
+    let relay = RelayProcess::start();
+    let mut racers = [
+        Racer::join(&relay.addr, "full", 1, 0.5),
+        Racer::join(&relay.addr, "full", 2, 0.5),
+        Racer::join(&relay.addr, "full", 3, 0.5),
+    ];
+    let start = Instant::now();
+    while racers[2].client.status != RaceStatus::Full {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        for racer in &mut racers {
+            racer.frame();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(racers[0].client.status, RaceStatus::Racing);
+    assert_eq!(racers[1].client.status, RaceStatus::Racing);
+}
+
+#[test]
+fn disconnects_are_noticed() {
+    let relay = RelayProcess::start();
+    let mut racers = [
+        Racer::join(&relay.addr, "leave", 1, 0.5),
+        Racer::join(&relay.addr, "leave", 2, 0.5),
+        Racer::join(&relay.addr, "silent", 1, 0.5),
+        Racer::join(&relay.addr, "silent", 2, 0.5),
+    ];
+    let start = Instant::now();
+    while racers
+        .iter()
+        .any(|racer| racer.client.status != RaceStatus::Racing)
+    {
+        assert!(start.elapsed() < Duration::from_secs(10));
+        for racer in &mut racers {
+            racer.frame();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    // Leaving tells the opponent right away, going quiet takes the timeout.
+    let [leaver, stayer, mut waiter, _silent] = racers;
+    drop(leaver);
+    waiter.client.timeout = Duration::from_millis(300);
+    let mut stayer = stayer;
+    let start = Instant::now();
+    while stayer.client.status == RaceStatus::Racing || waiter.client.status == RaceStatus::Racing {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        stayer.frame();
+        waiter.frame();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(matches!(
+        stayer.client.status,
+        RaceStatus::Disconnected(ref reason) if reason.contains("LEFT")
+    ));
+    assert!(matches!(
+        waiter.client.status,
+        RaceStatus::Disconnected(ref reason) if reason.contains("TIMED OUT")
+    ));
+    // The run goes on alone.
+    let frames = stayer.run.as_ref().unwrap().replay.frames.len();
+    stayer.frame();
+    assert_eq!(stayer.run.as_ref().unwrap().replay.frames.len(), frames + 1);
+}
+
+#[test]
+fn relay_refuses_bad_arguments_and_taken_addresses() {
+    let relay = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_relay"))
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let output = relay(&["127.0.0.1:0", "--loss", "lots"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("--loss takes"));
+    assert_eq!(relay(&["--fast"]).status.code(), Some(2));
+
+    let taken = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+    let output = relay(&[&taken.local_addr().unwrap().to_string()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("can't listen on"));
+}
@@ -0,0 +1,181 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use lib_Sol::remote::{Remote, RemotePlugin, FRAME_DT};
+use lib_Sol::sim::{Run, RunConfig, SimInput};
+use lib_Sol::{DataMaterial, GameMaterial};
+use serde_json::Value;
+
+/// What the game saw each frame.
+#[derive(Clone, Debug)]
+struct Frame {
+    delta: f32,
+    pressed: Vec<KeyCode>,
+    /// Frames in the run's replay, once this one was played.
+    replay: usize,
+}
+
+#[derive(Resource, Clone, Default)]
+struct Frames(Arc<Mutex<Vec<Frame>>>);
+
+fn record(time: Res<Time>, keys: Res<ButtonInput<KeyCode>>, run: Res<Run>, frames: Res<Frames>) {
+    frames.0.lock().unwrap().push(Frame {
+        delta: time.delta_seconds(),
+        pressed: keys.get_just_pressed().copied().collect(),
+        replay: run.replay.frames.len(),
+    });
+}
+
+/// Steps the run like the game does, only on frames the clock moved.
+fn play(time: Res<Time>, mut run: ResMut<Run>) {
+    if time.delta_seconds() > 0.0 {
+        run.step_players(time.delta_seconds(), [SimInput::default(); 2]);
+    }
+}
+
+/// Just enough of the game for the remote control, listening on a free port.
+fn app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        InputPlugin,
+        RemotePlugin {
+            addr: String::from("127.0.0.1:0"),
+        },
+    ));
+    let mut materials = Assets::<DataMaterial>::default();
+    let material = materials.add(DataMaterial::default());
+    app.insert_resource(materials)
+        .insert_resource(GameMaterial(material))
+        .insert_resource(Run::new(RunConfig::default()))
+        .init_resource::<Frames>()
+        .add_systems(Update, (play, record).chain());
+    app.finish();
+    app.cleanup();
+    app
+}
+
+struct Client {
+    stream: BufReader<TcpStream>,
+}
+
+impl Client {
+    fn request(&mut self, line: &str) -> Value {
+        writeln!(self.stream.get_mut(), "{line}").unwrap();
+        let mut reply = String::new();
+        self.stream.read_line(&mut reply).unwrap();
+        serde_json::from_str(&reply).unwrap()
+    }
+
+    fn ok(&mut self, line: &str) {
+        let reply = self.request(line);
+        assert_eq!(reply["ok"], true, "{line}: {reply}");
+    }
+}
+
+#[test]
+fn scripts_drive_the_game_over_localhost() {
+    let mut app = app();
+    let addr = app.world().resource::<Remote>().addr;
+    let frames = app.world().resource::<Frames>().clone();
+    let log = frames.clone();
+    let script = std::thread::spawn(move || {
+        let mut client = Client {
+            stream: BufReader::new(TcpStream::connect(addr).unwrap()),
+        };
+        let state = client.request(r#"{"cmd": "state"}"#);
+        assert_eq!(state["state"]["players"].as_array().unwrap().len(), 2);
+        assert!(state["state"]["t"].is_number());
+
+        client.ok(r#"{"cmd": "pause"}"#);
+        // Replies come at the start of a frame, so this one is after the pause took effect.
+        client.ok(r#"{"cmd": "press", "keys": ["Space"]}"#);
+        let paused_at = log.0.lock().unwrap().len();
+        std::thread::sleep(Duration::from_millis(50));
+        client.ok(r#"{"cmd": "step", "frames": 3}"#);
+        client.ok(r#"{"cmd": "step", "frames": 2}"#);
+        let seen = log.0.lock().unwrap()[paused_at..].to_vec();
+        let stepped: Vec<_> = seen.iter().filter(|frame| frame.delta > 0.0).collect();
+        assert_eq!(stepped.len(), 5, "{seen:?}");
+        for frame in &stepped {
+            assert!((frame.delta - FRAME_DT as f32).abs() < 1e-5, "{frame:?}");
+        }
+        // Only the stepped frames went into the replay.
+        for pair in seen.windows(2) {
+            let stepped = (pair[1].delta > 0.0) as usize;
+            assert_eq!(pair[1].replay, pair[0].replay + stepped, "{seen:?}");
+        }
+        // The press waited for the first stepped frame, and no paused frame saw it.
+        assert_eq!(stepped[0].pressed, [KeyCode::Space]);
+        assert_eq!(
+            seen.iter()
+                .filter(|frame| !frame.pressed.is_empty())
+                .count(),
+            1
+        );
+
+        assert!(client.request(r#"{"cmd": "screenshot", "path": "x.png"}"#)["error"].is_string());
+        assert!(client.request(r#"{"cmd": "jump"}"#)["error"].is_string());
+        client.ok(r#"{"cmd": "seed", "seed": 42}"#);
+        client.ok(r#"{"cmd": "resume"}"#);
+        assert!(client.request(r#"{"cmd": "step"}"#)["error"].is_string());
+    });
+
+    let start = Instant::now();
+    while !script.is_finished() {
+        assert!(start.elapsed() < Duration::from_secs(30));
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    script.join().unwrap();
+    assert_eq!(app.world().resource::<Run>().sim.config.seed, 42);
+    let frames = frames.0.lock().unwrap();
+    assert!(frames.last().unwrap().delta > 0.0);
+}
+
+#[test]
+fn stepping_puts_back_how_the_clock_was_updated() {
+    let mut app = app();
+    let fixed = Duration::from_millis(5);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(fixed));
+    let addr = app.world().resource::<Remote>().addr;
+    let frames = app.world().resource::<Frames>().clone();
+    let log = frames.clone();
+    let script = std::thread::spawn(move || {
+        let mut client = Client {
+            stream: BufReader::new(TcpStream::connect(addr).unwrap()),
+        };
+        client.ok(r#"{"cmd": "pause"}"#);
+        client.ok(r#"{"cmd": "step", "frames": 2}"#);
+        client.ok(r#"{"cmd": "resume"}"#);
+        let resumed_at = log.0.lock().unwrap().len();
+        while log.0.lock().unwrap().len() < resumed_at + 3 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        resumed_at
+    });
+
+    let start = Instant::now();
+    while !script.is_finished() {
+        assert!(start.elapsed() < Duration::from_secs(30));
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    let resumed_at = script.join().unwrap();
+    assert!(matches!(
+        app.world().resource::<TimeUpdateStrategy>(),
+        TimeUpdateStrategy::ManualDuration(duration) if *duration == fixed
+    ));
+    let frames = frames.0.lock().unwrap();
+    for frame in &frames[resumed_at..] {
+        assert!(
+            (frame.delta - fixed.as_secs_f32()).abs() < 1e-5,
+            "{frame:?}"
+        );
+    }
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::*;
+use lib_Sol::headless::FRAME_DT;
+use lib_Sol::sim::*;
+
+fn versus() -> Run {
+    Run::new(RunConfig {
+        mode: GameMode::Versus,
+        seed: 7,
+        ..Default::default()
+    })
+}
+
+/// Plays until a player's orb is gone, with player `climber` jumping whenever it lands them
+/// and the other one idle.
+fn outlast(climber: usize) -> Run {
+    let mut run = versus();
+    while run.players().all(|sim| !sim.over()) {
+        let mut inputs = [IDLE; 2];
+        inputs[climber] = JUMP;
+        let mut attempt = run.clone();
+        let results = attempt.step_players(FRAME_DT, inputs);
+        if let Some(JumpResult::Landed { .. }) = results[climber] {
+            run = attempt;
+        } else {
+            run.step_players(FRAME_DT, [IDLE; 2]);
+        }
+    }
+    run
+}
+
+#[test]
+fn both_players_share_the_rings() {
+    let run = versus();
+    let second = run.second.as_ref().unwrap();
+    assert_eq!(second.config, run.sim.config);
+    // Both would change the speed of the shared rings for one player.
+    assert!(!run.sim.config.mechanics.power_ups);
+    assert!(!run.sim.config.adaptive);
+    assert_eq!(run.players().count(), 2);
+    assert!(!GameMode::Versus.is_ranked());
+
+    let solo = Run::new(RunConfig::default());
+    assert_eq!(solo.players().count(), 1);
+    assert_eq!(solo.winner(), None);
+}
+
+#[test]
+fn the_last_orb_alive_wins() {
+    for climber in [0, 1] {
+        let run = outlast(climber);
+        let players: Vec<&Sim> = run.players().collect();
+        let (winner, loser) = (players[climber], players[1 - climber]);
+        assert!(loser.dead);
+        assert!(winner.finished && !winner.dead);
+        assert!(winner.level() > 0);
+        assert_eq!(run.winner(), Some(climber));
+    }
+}
+
+#[test]
+fn orbs_lost_on_the_same_frame_draw() {
+    let mut run = versus();
+    while !run.sim.over() {
+        assert_eq!(run.winner(), None);
+        run.step_players(FRAME_DT, [IDLE; 2]);
+    }
+    let second = run.second.as_ref().unwrap();
+    assert!(run.sim.dead && second.dead);
+    assert_eq!(run.winner(), None);
+}
+
+#[test]
+fn the_rings_stay_lined_up_after_the_run() {
+    let mut run = outlast(1);
+    for _ in 0..60 {
+        run.step_players(FRAME_DT, [IDLE; 2]);
+        assert_eq!(run.second.as_ref().unwrap().t, run.sim.t);
+    }
+}
+
+#[test]
+fn the_replay_keeps_the_first_player() {
+    let run = outlast(0);
+    let played = run.replay.play();
+    assert_eq!(played.level(), run.sim.level());
+    assert_eq!(played.score, run.sim.score);
+    assert_ne!(played.level(), run.second.as_ref().unwrap().level());
+}